@@ -9,12 +9,15 @@ use winit::window::Window;
 #[cfg(target_arch = "wasm32")]
 use winit::event_loop::EventLoop;
 
+use crate::config::GlowConfig;
 use crate::state::*;
 
 pub struct App {
     #[cfg(target_arch = "wasm32")]
     proxy: Option<winit::event_loop::EventLoopProxy<State>>,
     state: Option<State>,
+    // Settings from the command line, res/glow.toml is loaded when None
+    glow_config: Option<GlowConfig>,
 }
 
 impl App {
@@ -23,10 +26,19 @@ impl App {
         let proxy = Some(event_loop.create_proxy());
         Self {
             state: None,
+            glow_config: None,
             #[cfg(target_arch = "wasm32")]
             proxy,
         }
     }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_config(glow_config: GlowConfig) -> Self {
+        Self {
+            state: None,
+            glow_config: Some(glow_config),
+        }
+    }
 }
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -64,6 +76,11 @@ impl ApplicationHandler<State> for App {
             //let window_icon: Option<winit::window::Icon> = Some(load_icon("./assets/icon.png"));
             window_attributes = window_attributes.with_title("Ubik says Learn WGPU");
              //.with_window_icon(window_icon);
+
+            if let Some([width, height]) = self.glow_config.as_ref().and_then(|c| c.window.size) {
+                window_attributes =
+                    window_attributes.with_inner_size(winit::dpi::PhysicalSize::new(width, height));
+            }
         }
 
         let window = Arc::new(event_loop.create_window(window_attributes).unwrap());
@@ -72,7 +89,7 @@ impl ApplicationHandler<State> for App {
         {
             // If we are not on web we can use pollster to
             // await the
-            self.state = Some(pollster::block_on(State::new(window)).unwrap());
+            self.state = Some(pollster::block_on(State::new(window, self.glow_config.clone())).unwrap());
         }
 
         #[cfg(target_arch = "wasm32")]
@@ -83,7 +100,7 @@ impl ApplicationHandler<State> for App {
                 wasm_bindgen_futures::spawn_local(async move {
                     assert!(proxy
                         .send_event(
-                            State::new(window)
+                            State::new(window, None)
                                 .await
                                 .expect("Unable to create canvas!!!")
                         )
@@ -97,11 +114,11 @@ impl ApplicationHandler<State> for App {
     fn user_event(&mut self, _event_loop: &ActiveEventLoop, mut event: State) {
         // This is where proxy.send_event() ends up
         #[cfg(target_arch = "wasm32")]
-        {
-            event.window.request_redraw();
+        if let Some(window) = event.window.clone() {
+            window.request_redraw();
             event.resize(
-                event.window.inner_size().width,
-                event.window.inner_size().height,
+                window.inner_size().width,
+                window.inner_size().height,
             );
         }
         self.state = Some(event);
@@ -127,8 +144,10 @@ impl ApplicationHandler<State> for App {
                     Ok(_) => {}
                     // Reconfigure the surface if it's lost or outdated
                     Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
-                        let size = state.window.inner_size();
-                        state.resize(size.width, size.height);
+                        if let Some(window) = state.window.clone() {
+                            let size = window.inner_size();
+                            state.resize(size.width, size.height);
+                        }
                     }
                     Err(e) => {
                         log::error!("Unable to render {}", e);
@@ -144,6 +163,10 @@ impl ApplicationHandler<State> for App {
                     },
                 ..
             } => state.handle_key(event_loop, code, key_state.is_pressed()),
+            WindowEvent::CursorMoved { position, .. } => state.handle_cursor_moved(position.x, position.y),
+            WindowEvent::MouseInput { state: button_state, button, .. } => {
+                state.handle_mouse_button(button, button_state.is_pressed())
+            }
 
             _ => {}
         }
@@ -0,0 +1,293 @@
+// /
+// /  C O M M A N D   L I N E
+// /
+// / Native only. Every option overrides the matching value of the config file
+// / (and of the GLOW_* adapter env vars):
+// /
+// /     glow_web models/ship.gltf --window-size 1280x720 --msaa 1 --tone-map reinhard
+// /     glow_web /path/to/ship.obj --frames 120 --screenshot ship.png
+// /     glow_web --screenshot bloom.exr --target blur-vertical
+// /     glow_web --record fly_glow.gif --window-size 480x270
+
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Context};
+use clap::Parser;
+
+use crate::{
+    config::{
+        AutoExposure, Backend, BloomMode, BlurPath, DEFAULT_CONFIG_FILE, EdgeOperator, GlowConfig, OutlineMode, PowerPreference, PresentMode, SurfaceFormat, ToneMapOperator,
+    },
+    recorder::{DEFAULT_RECORD_FPS, RecordLength, Recording},
+    state::{FrameTarget, State},
+};
+
+// / Window size when neither the command line nor the config file sets one (screenshot mode)
+pub const DEFAULT_SCREENSHOT_SIZE: [u32; 2] = [800, 600];
+
+// / Fixed time step of the screenshot mode, so N frames always give the same image
+const SCREENSHOT_FRAME_TIME: f32 = 1.0 / 60.0;
+
+#[derive(Debug, Clone, Parser)]
+#[command(name = "glow_web", version, about = "Glow / bloom outline viewer")]
+pub struct Cli {
+    /// Model to display (.obj or .gltf), relative to res/ or absolute
+    pub model: Option<String>,
+
+    /// Config file, relative to res/ or absolute
+    #[arg(long, default_value = DEFAULT_CONFIG_FILE)]
+    pub config: String,
+
+    /// Window size, WIDTHxHEIGHT
+    #[arg(long, value_name = "WIDTHxHEIGHT", value_parser = parse_size)]
+    pub window_size: Option<[u32; 2]>,
+
+    /// VSync: fifo (on), fifo-relaxed (adaptive), mailbox / immediate (off, uncapped), auto
+    #[arg(long, value_name = "MODE")]
+    pub present_mode: Option<PresentMode>,
+
+    /// Frames queued ahead of the display (1 = lowest latency)
+    #[arg(long, value_name = "FRAMES")]
+    pub frame_latency: Option<u32>,
+
+    /// Swapchain format: auto, bgra8-unorm-srgb, rgba8-unorm-srgb, bgra8-unorm, rgba8-unorm, rgb10a2-unorm, rgba16-float
+    #[arg(long, value_name = "FORMAT")]
+    pub surface_format: Option<SurfaceFormat>,
+
+    /// MSAA sample count (1 or 4)
+    #[arg(long, value_name = "SAMPLES")]
+    pub msaa: Option<u32>,
+
+    /// Render the glow passes in HDR (Rgba16Float) and tone map to the surface
+    #[arg(long, value_name = "BOOL")]
+    pub hdr: Option<bool>,
+
+    /// Tone map operator: aces, aces-narkowicz, reinhard, reinhard-extended, uncharted2, agx,
+    /// pbr-neutral (T key cycles them at runtime)
+    #[arg(long, value_name = "OPERATOR")]
+    pub tone_map: Option<ToneMapOperator>,
+
+    /// Exposure before the tone map, in stops (-/= keys at runtime)
+    #[arg(long, value_name = "EV", allow_hyphen_values = true)]
+    pub exposure: Option<f32>,
+
+    /// Scene brightness mapped to white (reinhard-extended, uncharted2)
+    #[arg(long, value_name = "LUMINANCE")]
+    pub white_point: Option<f32>,
+
+    /// Exposure metered from the frame: off, histogram (compute) or downsample (X key at runtime)
+    #[arg(long, value_name = "MODE")]
+    pub auto_exposure: Option<AutoExposure>,
+
+    /// Color grading .cube LUT relative to res/, repeat for more (G key swaps them at runtime).
+    /// Replaces the [color_grading] LUTs of the config
+    #[arg(long = "lut", value_name = "FILE")]
+    pub luts: Vec<String>,
+
+    /// How much of the LUT shows, 0 to 1 ([ / ] keys at runtime)
+    #[arg(long, value_name = "STRENGTH")]
+    pub lut_strength: Option<f32>,
+
+    /// Glow spread: mip-chain or separable
+    #[arg(long, value_name = "MODE")]
+    pub bloom: Option<BloomMode>,
+
+    /// Mip levels of the mip-chain bloom (1 to 8)
+    #[arg(long, value_name = "LEVELS")]
+    pub bloom_levels: Option<u32>,
+
+    /// Separable blur in render or compute passes (B key toggles it at runtime)
+    #[arg(long, value_name = "PATH")]
+    pub blur_path: Option<BlurPath>,
+
+    /// Outlines extruded along the normals or jump flooded from the stencil mask (O key toggles it at runtime)
+    #[arg(long, value_name = "MODE")]
+    pub outline_mode: Option<OutlineMode>,
+
+    /// Edge detection kernel: cross, sobel, roberts (E key cycles them at runtime)
+    #[arg(long, value_name = "OPERATOR")]
+    pub edge_operator: Option<EdgeOperator>,
+
+    /// Log per-pass GPU timings (P key toggles it at runtime)
+    #[arg(long)]
+    pub profile: bool,
+
+    /// Graphics API: auto, vulkan, metal, dx12, gl, web-gpu
+    #[arg(long)]
+    pub backend: Option<Backend>,
+
+    /// Adapter ranking: none, low-power (integrated first), high-performance (discrete first)
+    #[arg(long, value_name = "PREFERENCE")]
+    pub power_preference: Option<PowerPreference>,
+
+    /// Use the software adapter (llvmpipe, WARP)
+    #[arg(long)]
+    pub force_fallback_adapter: bool,
+
+    /// Only use an adapter whose name contains NAME (case insensitive)
+    #[arg(long, value_name = "NAME")]
+    pub adapter: Option<String>,
+
+    /// Number of frames to render before taking the screenshot
+    #[arg(long, value_name = "N", requires = "screenshot", value_parser = clap::value_parser!(u32).range(1..))]
+    pub frames: Option<u32>,
+
+    /// Render offscreen, save the last frame (.png, .exr or .hdr) and exit
+    #[arg(long, value_name = "FILE")]
+    pub screenshot: Option<PathBuf>,
+
+    /// Target saved by --screenshot: depth, stencil-mask, outline, blur-horizontal, blur-vertical,
+    /// scene, normal, edge, composite, tone-map
+    #[arg(long, value_name = "TARGET", default_value = "tone-map", requires = "screenshot")]
+    pub target: FrameTarget,
+
+    /// Record a turntable offscreen and exit: animated GIF (*.gif) or PNG sequence (directory)
+    #[arg(long, value_name = "GIF|DIR", conflicts_with = "screenshot")]
+    pub record: Option<PathBuf>,
+
+    /// Recording length in seconds (default: one full revolution of the model)
+    #[arg(long, value_name = "SECONDS", requires = "record")]
+    pub record_seconds: Option<f32>,
+
+    /// Recording frame rate, the animation is stepped by 1 / FPS
+    #[arg(long, value_name = "FPS", default_value_t = DEFAULT_RECORD_FPS, requires = "record")]
+    pub fps: u32,
+}
+
+impl Cli {
+    // / Config file, then the env vars, then the command line. Validated.
+    pub async fn load_config(&self) -> anyhow::Result<GlowConfig> {
+        let mut config = if self.config == DEFAULT_CONFIG_FILE {
+            GlowConfig::load_or_default(&self.config).await?
+        } else {
+            GlowConfig::load(&self.config).await?
+        };
+
+        config.adapter.apply_env()?;
+        self.apply(&mut config);
+        config.validate().context("invalid command line")?;
+
+        Ok(config)
+    }
+
+    pub fn apply(&self, config: &mut GlowConfig) {
+        if let Some(model) = &self.model {
+            config.scene.model = model.clone();
+        }
+        if let Some(size) = self.window_size {
+            config.window.size = Some(size);
+        }
+        if let Some(present_mode) = self.present_mode {
+            config.window.present_mode = present_mode;
+        }
+        if let Some(frame_latency) = self.frame_latency {
+            config.window.frame_latency = frame_latency;
+        }
+        if let Some(surface_format) = self.surface_format {
+            config.window.surface_format = surface_format;
+        }
+        if let Some(sample_count) = self.msaa {
+            config.renderer.sample_count = sample_count;
+        }
+        if let Some(is_hdr) = self.hdr {
+            config.renderer.is_hdr = is_hdr;
+        }
+        if let Some(tone_map) = self.tone_map {
+            config.renderer.tone_map = tone_map;
+        }
+        if let Some(exposure) = self.exposure {
+            config.renderer.exposure = exposure;
+        }
+        if let Some(white_point) = self.white_point {
+            config.renderer.white_point = white_point;
+        }
+        if let Some(auto_exposure) = self.auto_exposure {
+            config.effects.auto_exposure = auto_exposure;
+        }
+        if !self.luts.is_empty() {
+            config.color_grading.luts = self.luts.clone();
+        }
+        if let Some(strength) = self.lut_strength {
+            config.color_grading.strength = strength;
+        }
+        if let Some(bloom) = self.bloom {
+            config.effects.bloom = bloom;
+        }
+        if let Some(bloom_levels) = self.bloom_levels {
+            config.effects.bloom_levels = bloom_levels;
+        }
+        if let Some(blur_path) = self.blur_path {
+            config.effects.blur_path = blur_path;
+        }
+        if let Some(outline_mode) = self.outline_mode {
+            config.effects.outline_mode = outline_mode;
+        }
+        if let Some(edge_operator) = self.edge_operator {
+            config.effects.edge_operator = edge_operator;
+        }
+        if self.profile {
+            config.renderer.profile = true;
+        }
+        if let Some(backend) = self.backend {
+            config.adapter.backend = backend;
+        }
+        if let Some(power_preference) = self.power_preference {
+            config.adapter.power_preference = power_preference;
+        }
+        if self.force_fallback_adapter {
+            config.adapter.force_fallback = true;
+        }
+        if let Some(name) = &self.adapter {
+            config.adapter.name = Some(name.clone());
+        }
+    }
+
+    // / The --record settings, sized like the screenshots.
+    pub fn recording(&self, config: &GlowConfig) -> Option<Recording> {
+        let [width, height] = config.window.size.unwrap_or(DEFAULT_SCREENSHOT_SIZE);
+
+        Some(Recording {
+            output: self.record.clone()?,
+            width,
+            height,
+            fps: self.fps,
+            length: self.record_seconds.map_or(RecordLength::Revolution, RecordLength::Seconds),
+        })
+    }
+}
+
+// / Render `frames` frames (at least 1, see --frames) offscreen (fixed time step) and save `target` of the last one
+// / (.png, .exr or .hdr, from the extension).
+pub async fn render_screenshot(
+    config: GlowConfig,
+    frames: u32,
+    target: FrameTarget,
+    path: &std::path::Path,
+) -> anyhow::Result<()> {
+    let [width, height] = config.window.size.unwrap_or(DEFAULT_SCREENSHOT_SIZE);
+
+    let mut state = State::new_headless(width, height, config).await?;
+
+    for _ in 0..frames {
+        state.advance(SCREENSHOT_FRAME_TIME);
+        state.render_to_image()?;
+    }
+
+    state.capture(target)?.save(path)?;
+
+    log::info!(
+        "screenshot of {} saved to {} ({width}x{height}, {frames} frames)",
+        target.name(),
+        path.display()
+    );
+
+    Ok(())
+}
+
+fn parse_size(s: &str) -> anyhow::Result<[u32; 2]> {
+    let (width, height) = s
+        .split_once(['x', 'X'])
+        .ok_or(anyhow!("expected WIDTHxHEIGHT, got '{s}'"))?;
+
+    Ok([width.trim().parse()?, height.trim().parse()?])
+}
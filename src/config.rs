@@ -0,0 +1,1511 @@
+// /
+// /  G L O W   C O N F I G
+// /
+// / Scene and renderer settings, loaded at startup from a TOML file (res/glow.toml).
+// / Every field has a default, a file only needs the values it changes:
+// /
+// /     [renderer]
+// /     sample_count = 1
+// /
+// /     [effects]
+// /     bloom_strength = 2.0
+
+use anyhow::{anyhow, bail, Context};
+use serde::{Deserialize, Serialize};
+
+use crate::{extra::MAX_BLUR_RADIUS, resources::ModelFile};
+
+pub const DEFAULT_CONFIG_FILE: &str = "glow.toml";
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GlowConfig {
+    pub window: WindowConfig,
+    pub adapter: AdapterConfig,
+    pub renderer: RendererConfig,
+    pub scene: SceneConfig,
+    pub camera: CameraConfig,
+    pub effects: EffectsConfig,
+    pub color_grading: ColorGradingConfig,
+    // [[highlight_groups]], stencil references 2, 3 ... in order
+    pub highlight_groups: Vec<HighlightGroupConfig>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WindowConfig {
+    // Inner size in pixels, platform default when missing (native only, the canvas sizes the web build)
+    pub size: Option<[u32; 2]>,
+    // Only modes reported by the surface are used, see PresentMode
+    pub present_mode: PresentMode,
+    // Frames queued ahead of the display (desired_maximum_frame_latency)
+    pub frame_latency: u32,
+    pub surface_format: SurfaceFormat,
+}
+
+impl Default for WindowConfig {
+    fn default() -> Self {
+        Self {
+            size: None,
+            present_mode: PresentMode::Auto,
+            frame_latency: 2,
+            surface_format: SurfaceFormat::Auto,
+        }
+    }
+}
+
+// / Swapchain present mode. Unsupported modes fall back: immediate -> mailbox -> fifo,
+// / fifo-relaxed -> fifo (fifo is always supported).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PresentMode {
+    // First mode reported by the surface
+    #[default]
+    Auto,
+    // VSync on
+    Fifo,
+    // Adaptive VSync: tears when a frame is late
+    FifoRelaxed,
+    // VSync off, no tearing (uncapped, latest frame wins)
+    Mailbox,
+    // VSync off, tearing
+    Immediate,
+}
+
+impl PresentMode {
+    pub const ALL: [PresentMode; 5] = [
+        PresentMode::Auto,
+        PresentMode::Fifo,
+        PresentMode::FifoRelaxed,
+        PresentMode::Mailbox,
+        PresentMode::Immediate,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PresentMode::Auto => "auto",
+            PresentMode::Fifo => "fifo",
+            PresentMode::FifoRelaxed => "fifo-relaxed",
+            PresentMode::Mailbox => "mailbox",
+            PresentMode::Immediate => "immediate",
+        }
+    }
+
+    // / Pick from the modes the surface supports (`supported` is never empty).
+    pub fn resolve(&self, supported: &[wgpu::PresentMode]) -> wgpu::PresentMode {
+        let preferred: &[wgpu::PresentMode] = match self {
+            PresentMode::Auto => &[],
+            PresentMode::Fifo => &[wgpu::PresentMode::Fifo],
+            PresentMode::FifoRelaxed => &[wgpu::PresentMode::FifoRelaxed, wgpu::PresentMode::Fifo],
+            PresentMode::Mailbox => &[wgpu::PresentMode::Mailbox, wgpu::PresentMode::Fifo],
+            PresentMode::Immediate => &[
+                wgpu::PresentMode::Immediate,
+                wgpu::PresentMode::Mailbox,
+                wgpu::PresentMode::Fifo,
+            ],
+        };
+
+        let mode = preferred
+            .iter()
+            .find(|mode| supported.contains(mode))
+            .copied()
+            .unwrap_or(supported[0]);
+
+        if preferred.first().is_some_and(|first| *first != mode) {
+            log::warn!("present mode {} not supported by the surface, using {mode:?}", self.name());
+        }
+
+        mode
+    }
+}
+
+impl std::str::FromStr for PresentMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        parse_named(s, &Self::ALL, Self::name, "present mode")
+    }
+}
+
+// / Swapchain format. `Auto` takes the first sRGB format the surface reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SurfaceFormat {
+    #[default]
+    Auto,
+    Bgra8UnormSrgb,
+    Rgba8UnormSrgb,
+    Bgra8Unorm,
+    Rgba8Unorm,
+    Rgb10a2Unorm,
+    Rgba16Float,
+}
+
+impl SurfaceFormat {
+    pub const ALL: [SurfaceFormat; 7] = [
+        SurfaceFormat::Auto,
+        SurfaceFormat::Bgra8UnormSrgb,
+        SurfaceFormat::Rgba8UnormSrgb,
+        SurfaceFormat::Bgra8Unorm,
+        SurfaceFormat::Rgba8Unorm,
+        SurfaceFormat::Rgb10a2Unorm,
+        SurfaceFormat::Rgba16Float,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SurfaceFormat::Auto => "auto",
+            SurfaceFormat::Bgra8UnormSrgb => "bgra8-unorm-srgb",
+            SurfaceFormat::Rgba8UnormSrgb => "rgba8-unorm-srgb",
+            SurfaceFormat::Bgra8Unorm => "bgra8-unorm",
+            SurfaceFormat::Rgba8Unorm => "rgba8-unorm",
+            SurfaceFormat::Rgb10a2Unorm => "rgb10a2-unorm",
+            SurfaceFormat::Rgba16Float => "rgba16-float",
+        }
+    }
+
+    pub fn format(&self) -> Option<wgpu::TextureFormat> {
+        match self {
+            SurfaceFormat::Auto => None,
+            SurfaceFormat::Bgra8UnormSrgb => Some(wgpu::TextureFormat::Bgra8UnormSrgb),
+            SurfaceFormat::Rgba8UnormSrgb => Some(wgpu::TextureFormat::Rgba8UnormSrgb),
+            SurfaceFormat::Bgra8Unorm => Some(wgpu::TextureFormat::Bgra8Unorm),
+            SurfaceFormat::Rgba8Unorm => Some(wgpu::TextureFormat::Rgba8Unorm),
+            SurfaceFormat::Rgb10a2Unorm => Some(wgpu::TextureFormat::Rgb10a2Unorm),
+            SurfaceFormat::Rgba16Float => Some(wgpu::TextureFormat::Rgba16Float),
+        }
+    }
+
+    // / Pick from the formats the surface supports (`supported` is never empty).
+    pub fn resolve(&self, supported: &[wgpu::TextureFormat]) -> wgpu::TextureFormat {
+        // Shader code in this tutorial assumes an sRGB surface texture. Using a different
+        // one will result in all the colors coming out darker (the tone map pass encodes
+        // to sRGB itself when the format has an sRGB variant).
+        let first_srgb = supported
+            .iter()
+            .find(|f| f.is_srgb())
+            .copied()
+            .unwrap_or(supported[0]);
+
+        match self.format() {
+            Some(format) if supported.contains(&format) => format,
+            Some(format) => {
+                log::warn!("surface format {format:?} not supported by the surface, using {first_srgb:?}");
+                first_srgb
+            }
+            None => first_srgb,
+        }
+    }
+}
+
+impl std::str::FromStr for SurfaceFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        parse_named(s, &Self::ALL, Self::name, "surface format")
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RendererConfig {
+    // 1 or 4
+    pub sample_count: u32,
+    // Render the glow passes in Rgba16Float and tone map to the surface
+    pub is_hdr: bool,
+    pub tone_map: ToneMapOperator,
+    // Exposure before the tone map, in stops (EV): each one doubles the brightness
+    pub exposure: f32,
+    // Scene brightness mapped to white (extended Reinhard, Uncharted 2)
+    pub white_point: f32,
+    // Per-pass timings in the log (GPU timestamps, CPU encoding times on WebGL)
+    pub profile: bool,
+}
+
+impl Default for RendererConfig {
+    fn default() -> Self {
+        Self {
+            sample_count: 4,
+            is_hdr: true,
+            tone_map: ToneMapOperator::Aces,
+            exposure: 0.0,
+            white_point: 4.0,
+            profile: false,
+        }
+    }
+}
+
+// / Which GPU to run on. Env vars (GLOW_BACKEND, GLOW_POWER_PREFERENCE, GLOW_FORCE_FALLBACK_ADAPTER,
+// / GLOW_ADAPTER_NAME) override the file, the command line overrides both.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AdapterConfig {
+    pub backend: Backend,
+    // Discrete GPU first (high-performance) or integrated first (low-power)
+    pub power_preference: PowerPreference,
+    // Software adapter only (llvmpipe, WARP): CI machines
+    pub force_fallback: bool,
+    // Case insensitive part of the adapter name, e.g. "nvidia"
+    pub name: Option<String>,
+}
+
+impl AdapterConfig {
+    pub fn apply_env(&mut self) -> anyhow::Result<()> {
+        let var = |name| std::env::var(name).ok().filter(|value: &String| !value.is_empty());
+
+        if let Some(value) = var("GLOW_BACKEND") {
+            self.backend = value.parse().context("GLOW_BACKEND")?;
+        }
+        if let Some(value) = var("GLOW_POWER_PREFERENCE") {
+            self.power_preference = value.parse().context("GLOW_POWER_PREFERENCE")?;
+        }
+        if let Some(value) = var("GLOW_FORCE_FALLBACK_ADAPTER") {
+            self.force_fallback = match value.to_lowercase().as_str() {
+                "1" | "true" | "yes" | "on" => true,
+                "0" | "false" | "no" | "off" => false,
+                _ => bail!("GLOW_FORCE_FALLBACK_ADAPTER: expected true or false, got '{value}'"),
+            };
+        }
+        if let Some(value) = var("GLOW_ADAPTER_NAME") {
+            self.name = Some(value);
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PowerPreference {
+    // First adapter found (software adapters last)
+    #[default]
+    None,
+    LowPower,
+    HighPerformance,
+}
+
+impl PowerPreference {
+    pub const ALL: [PowerPreference; 3] = [
+        PowerPreference::None,
+        PowerPreference::LowPower,
+        PowerPreference::HighPerformance,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            PowerPreference::None => "none",
+            PowerPreference::LowPower => "low-power",
+            PowerPreference::HighPerformance => "high-performance",
+        }
+    }
+
+    pub fn to_wgpu(self) -> wgpu::PowerPreference {
+        match self {
+            PowerPreference::None => wgpu::PowerPreference::None,
+            PowerPreference::LowPower => wgpu::PowerPreference::LowPower,
+            PowerPreference::HighPerformance => wgpu::PowerPreference::HighPerformance,
+        }
+    }
+}
+
+impl std::str::FromStr for PowerPreference {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        parse_named(s, &Self::ALL, Self::name, "power preference")
+    }
+}
+
+// / Tone map operator of the last pass (hdr_lite.wgsl)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ToneMapOperator {
+    // ACES fitted (Stephen Hill)
+    Aces,
+    // ACES filmic curve (Krzysztof Narkowicz)
+    AcesNarkowicz,
+    Reinhard,
+    // Reinhard with the white point mapped to 1
+    ReinhardExtended,
+    // Filmic curve of Uncharted 2 (John Hable)
+    Uncharted2,
+    // AgX base contrast (Troy Sobotka), desaturates bright colors towards white
+    Agx,
+    // Khronos PBR Neutral: keeps the base colors up to the highlights
+    PbrNeutral,
+}
+
+impl ToneMapOperator {
+    pub const ALL: [ToneMapOperator; 7] = [
+        ToneMapOperator::Aces,
+        ToneMapOperator::AcesNarkowicz,
+        ToneMapOperator::Reinhard,
+        ToneMapOperator::ReinhardExtended,
+        ToneMapOperator::Uncharted2,
+        ToneMapOperator::Agx,
+        ToneMapOperator::PbrNeutral,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ToneMapOperator::Aces => "aces",
+            ToneMapOperator::AcesNarkowicz => "aces-narkowicz",
+            ToneMapOperator::Reinhard => "reinhard",
+            ToneMapOperator::ReinhardExtended => "reinhard-extended",
+            ToneMapOperator::Uncharted2 => "uncharted2",
+            ToneMapOperator::Agx => "agx",
+            ToneMapOperator::PbrNeutral => "pbr-neutral",
+        }
+    }
+
+    // / Value of ToneMapParams.operator in hdr_lite.wgsl
+    pub fn shader_index(&self) -> u32 {
+        match self {
+            ToneMapOperator::Aces => 0,
+            ToneMapOperator::AcesNarkowicz => 1,
+            ToneMapOperator::Reinhard => 2,
+            ToneMapOperator::ReinhardExtended => 3,
+            ToneMapOperator::Uncharted2 => 4,
+            ToneMapOperator::Agx => 5,
+            ToneMapOperator::PbrNeutral => 6,
+        }
+    }
+}
+
+impl std::str::FromStr for ToneMapOperator {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        parse_named(s, &Self::ALL, Self::name, "tone map operator")
+    }
+}
+
+// / Graphics API. `Auto` keeps the platform default (Vulkan/Metal/DX12 natively, WebGL on the web).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Backend {
+    #[default]
+    Auto,
+    Vulkan,
+    Metal,
+    Dx12,
+    Gl,
+    WebGpu,
+}
+
+impl Backend {
+    pub const ALL: [Backend; 6] = [
+        Backend::Auto,
+        Backend::Vulkan,
+        Backend::Metal,
+        Backend::Dx12,
+        Backend::Gl,
+        Backend::WebGpu,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Backend::Auto => "auto",
+            Backend::Vulkan => "vulkan",
+            Backend::Metal => "metal",
+            Backend::Dx12 => "dx12",
+            Backend::Gl => "gl",
+            Backend::WebGpu => "web-gpu",
+        }
+    }
+
+    // / None for `Auto`: the visualizer picks its default.
+    pub fn backends(&self) -> Option<wgpu::Backends> {
+        match self {
+            Backend::Auto => None,
+            Backend::Vulkan => Some(wgpu::Backends::VULKAN),
+            Backend::Metal => Some(wgpu::Backends::METAL),
+            Backend::Dx12 => Some(wgpu::Backends::DX12),
+            Backend::Gl => Some(wgpu::Backends::GL),
+            Backend::WebGpu => Some(wgpu::Backends::BROWSER_WEBGPU),
+        }
+    }
+}
+
+impl std::str::FromStr for Backend {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        parse_named(s, &Self::ALL, Self::name, "backend")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SceneConfig {
+    // Paths are relative to res/
+    pub texture: String,
+    // .obj or .gltf
+    pub model: String,
+    // Model spin, radians per second
+    pub spin_speed: f32,
+    // Copies of the model ([[scene.instances]]), one default copy when empty
+    pub instances: Vec<InstanceConfig>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct InstanceConfig {
+    pub position: [f32; 3],
+    // Degrees around the up axis
+    pub angle: f32,
+    // Highlight group name: "default" when missing, "none" for no outline
+    pub group: Option<String>,
+    // Outline of this instance, instead of the one of its group
+    pub glow: Option<GlowStyle>,
+}
+
+// / Stencil reference of the instances without outline
+pub const NO_HIGHLIGHT: u8 = 0;
+// / Stencil reference of the group drawn with the [effects] outline, every instance's default
+pub const DEFAULT_HIGHLIGHT_GROUP: u8 = 1;
+// / [[highlight_groups]] take the other stencil references, 2 to 255
+pub const MAX_HIGHLIGHT_GROUPS: usize = u8::MAX as usize - 1;
+
+// / Instances outlined together: one stencil reference per group. Intensity sets how
+// / strongly the group blooms.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HighlightGroupConfig {
+    pub name: String,
+    pub glow: GlowStyle,
+    pub occlusion: OcclusionStyle,
+}
+
+// / Where an outline shows, compared with the geometry in front of its object
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Visibility {
+    // Over everything (on top)
+    Always,
+    // Only where its object is in view
+    Visible,
+    // Only where its object is hidden (x-ray through walls)
+    Occluded,
+}
+
+impl Visibility {
+    pub const ALL: [Visibility; 3] = [Visibility::Always, Visibility::Visible, Visibility::Occluded];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Visibility::Always => "always",
+            Visibility::Visible => "visible",
+            Visibility::Occluded => "occluded",
+        }
+    }
+}
+
+impl std::str::FromStr for Visibility {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        parse_named(s, &Self::ALL, Self::name, "visibility")
+    }
+}
+
+// / Outline of a highlight group behind other geometry (extruded outlines)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OcclusionStyle {
+    pub visibility: Visibility,
+    // Color of the hidden parts (times the glow intensity), None: the glow color
+    pub color: Option<[f32; 3]>,
+    // Hidden parts dashed with stripes this many pixels wide, 0: solid
+    pub dash: f32,
+}
+
+impl Default for OcclusionStyle {
+    fn default() -> Self {
+        Self { visibility: Visibility::Always, color: None, dash: 0.0 }
+    }
+}
+
+impl OcclusionStyle {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let errors = occlusion_style_errors(self, |field| format!("occlusion style {field}"));
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!(errors.join("\n")))
+        }
+    }
+}
+
+// / Outline glow of an object: per model (from [effects]) or per instance
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GlowStyle {
+    pub color: [f32; 3],
+    // Color multiplier, above 1 the outline blooms brighter (HDR)
+    pub intensity: f32,
+    // Model space offset along the normals
+    pub thickness: f32,
+    pub alpha: f32,
+    // Simplex noise on the outline alpha, 0 (solid) to 1
+    pub noise: f32,
+}
+
+impl GlowStyle {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let errors = glow_style_errors(self, |field| format!("glow style {field}"));
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!(errors.join("\n")))
+        }
+    }
+}
+
+impl Default for GlowStyle {
+    fn default() -> Self {
+        Self {
+            color: [0.0, 0.5, 0.5],
+            intensity: 1.0,
+            thickness: 0.07,
+            alpha: 0.7,
+            noise: 0.0,
+        }
+    }
+}
+
+impl Default for SceneConfig {
+    fn default() -> Self {
+        Self {
+            texture: "images/wgpu-logo.png".to_string(),
+            model: "models/craft_speederD.gltf".to_string(),
+            spin_speed: 1.5,
+            instances: Vec::new(),
+        }
+    }
+}
+
+impl SceneConfig {
+    pub fn model_file(&self) -> ModelFile<'_> {
+        if self.model.to_lowercase().ends_with(".obj") {
+            ModelFile::Obj(&self.model)
+        } else {
+            ModelFile::Gltf(&self.model)
+        }
+    }
+}
+
+// / 3D LUTs (Adobe .cube) graded over the tone mapped frame. The first one is in use at
+// / startup, the others are loaded too and can be swapped in at runtime.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ColorGradingConfig {
+    // Paths are relative to res/. No color grading when empty
+    pub luts: Vec<String>,
+    // 0 (tone mapped frame) to 1 (fully graded)
+    pub strength: f32,
+}
+
+impl Default for ColorGradingConfig {
+    fn default() -> Self {
+        Self { luts: Vec::new(), strength: 1.0 }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CameraConfig {
+    pub eye: [f32; 3],
+    pub target: [f32; 3],
+    pub up: [f32; 3],
+    // Degrees
+    pub fovy: f32,
+    pub znear: f32,
+    pub zfar: f32,
+    // Camera controller speed
+    pub speed: f32,
+}
+
+impl Default for CameraConfig {
+    fn default() -> Self {
+        Self {
+            // position the camera 1 unit up and 2 units back, +z is out of the screen
+            eye: [0.0, 1.0, 2.0],
+            // have it look at the origin
+            target: [0.0, 0.0, 0.0],
+            up: [0.0, 1.0, 0.0],
+            fovy: 45.0,
+            znear: 0.1,
+            zfar: 100.0,
+            speed: 0.1,
+        }
+    }
+}
+
+// / How the outline glow is spread (the BLOOM target read by the composite pass)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BloomMode {
+    // Horizontal + vertical Gaussian blur at full resolution (blur.wgsl / blur_compute.wgsl)
+    Separable,
+    // Downsample into a mip chain, tent upsample back (bloom.wgsl)
+    MipChain,
+}
+
+impl BloomMode {
+    pub const ALL: [BloomMode; 2] = [BloomMode::Separable, BloomMode::MipChain];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BloomMode::Separable => "separable",
+            BloomMode::MipChain => "mip-chain",
+        }
+    }
+}
+
+impl std::str::FromStr for BloomMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        parse_named(s, &Self::ALL, Self::name, "bloom mode")
+    }
+}
+
+// / How the separable blur runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BlurPath {
+    // Fullscreen triangle render passes, bilinear taps (works everywhere, WebGL included)
+    Render,
+    // Compute passes caching a row / column tile in workgroup memory (blur_compute.wgsl)
+    Compute,
+}
+
+impl BlurPath {
+    pub const ALL: [BlurPath; 2] = [BlurPath::Render, BlurPath::Compute];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BlurPath::Render => "render",
+            BlurPath::Compute => "compute",
+        }
+    }
+}
+
+impl std::str::FromStr for BlurPath {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        parse_named(s, &Self::ALL, Self::name, "blur path")
+    }
+}
+
+// / How the exposure follows the brightness of the frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AutoExposure {
+    // Manual exposure only (renderer.exposure)
+    Off,
+    // Compute passes: log luminance histogram of the frame, then its average (auto_exposure.wgsl)
+    Histogram,
+    // Render passes: log luminance averaged down to one texel (works everywhere, WebGL included)
+    Downsample,
+}
+
+impl AutoExposure {
+    pub const ALL: [AutoExposure; 3] = [AutoExposure::Off, AutoExposure::Histogram, AutoExposure::Downsample];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            AutoExposure::Off => "off",
+            AutoExposure::Histogram => "histogram",
+            AutoExposure::Downsample => "downsample",
+        }
+    }
+}
+
+impl std::str::FromStr for AutoExposure {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        parse_named(s, &Self::ALL, Self::name, "auto exposure")
+    }
+}
+
+// / How the outline around the highlighted objects is drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutlineMode {
+    // Meshes extruded along their normals (expand.wgsl), width in model units
+    Extrude,
+    // Distance field of the stencil mask by jump flooding (jump_flood.wgsl), width in pixels
+    JumpFlood,
+}
+
+impl OutlineMode {
+    pub const ALL: [OutlineMode; 2] = [OutlineMode::Extrude, OutlineMode::JumpFlood];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            OutlineMode::Extrude => "extrude",
+            OutlineMode::JumpFlood => "jump-flood",
+        }
+    }
+}
+
+impl std::str::FromStr for OutlineMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        parse_named(s, &Self::ALL, Self::name, "outline mode")
+    }
+}
+
+// / Edge detection kernel of the edge pass (normal_depth_edges.wgsl)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EdgeOperator {
+    // Differences with the 4 direct neighbours
+    Cross,
+    // 3x3 horizontal and vertical gradients, smoother on noisy depth
+    Sobel,
+    // 2x2 diagonal differences, thinnest lines
+    Roberts,
+}
+
+impl EdgeOperator {
+    pub const ALL: [EdgeOperator; 3] = [EdgeOperator::Cross, EdgeOperator::Sobel, EdgeOperator::Roberts];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            EdgeOperator::Cross => "cross",
+            EdgeOperator::Sobel => "sobel",
+            EdgeOperator::Roberts => "roberts",
+        }
+    }
+
+    // / Value of EdgeParams.params.w in normal_depth_edges.wgsl
+    pub fn shader_index(&self) -> u32 {
+        match self {
+            EdgeOperator::Cross => 0,
+            EdgeOperator::Sobel => 1,
+            EdgeOperator::Roberts => 2,
+        }
+    }
+}
+
+impl std::str::FromStr for EdgeOperator {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        parse_named(s, &Self::ALL, Self::name, "edge operator")
+    }
+}
+
+// / Farthest pixel from the mask a jump flood outline reaches (outline_width + outline_falloff)
+pub const MAX_OUTLINE_DISTANCE: f32 = 256.0;
+
+// / Texels cached on each side of a compute blur tile (APRON in blur_compute.wgsl): the
+// / farthest tap, blur_radius * blur_spread, must stay below it
+pub const COMPUTE_BLUR_APRON: u32 = 64;
+
+// / Distance of the neighbours sampled by the edge pass
+pub const MAX_EDGE_WIDTH: f32 = 16.0;
+
+// / Exposure range of the tone map pass, in stops either way
+pub const MAX_EXPOSURE: f32 = 16.0;
+
+// / Bins of the auto exposure histogram: 0 for black, the others spread over auto_exposure_range
+pub const AUTO_EXPOSURE_BINS: u32 = 256;
+
+// / Mip levels of the bloom chain, the first one is half resolution
+pub const MAX_BLOOM_LEVELS: u32 = 8;
+pub const MAX_BLUR_ITERATIONS: u32 = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EffectsConfig {
+    // Outline of the model (GlowStyle), instances can override it. Thickness is a model
+    // space offset along the normals, intensity multiplies the color, noise is 0 to 1
+    pub outline_thickness: f32,
+    pub outline_color: [f32; 3],
+    pub outline_alpha: f32,
+    pub outline_intensity: f32,
+    pub outline_noise: f32,
+    // Outline of the model behind other geometry (OcclusionStyle): always, visible or
+    // occluded, then the color and dashes of the hidden parts
+    pub outline_visibility: Visibility,
+    pub outline_occluded_color: Option<[f32; 3]>,
+    pub outline_occluded_dash: f32,
+    // Extrude or jump flood (falls back to extrude where unsupported)
+    pub outline_mode: OutlineMode,
+    // Jump flood: solid width then fade out distance, in pixels whatever the mesh or its
+    // distance. Per-instance styles give way to their group's, and there's no noise
+    pub outline_width: f32,
+    pub outline_falloff: f32,
+    pub bloom: BloomMode,
+    // Separable: Gaussian kernel radius (texels per side), sigma, and how many times the
+    // horizontal + vertical pair runs (each pass blurs the previous result again)
+    pub blur_radius: u32,
+    pub blur_sigma: f32,
+    pub blur_iterations: u32,
+    // Separable: scale of the tap offsets (1 = exact kernel)
+    pub blur_spread: f32,
+    // Separable: render passes or compute passes (falls back to render where unsupported)
+    pub blur_path: BlurPath,
+    // Mip chain: number of levels (halo size doubles with each) and tent radius in texels
+    pub bloom_levels: u32,
+    pub bloom_radius: f32,
+    // Mip chain: scene brightness that blooms too, on top of the outline
+    pub bloom_threshold: f32,
+    // Bloom added on top of the scene in the composite pass
+    pub bloom_strength: f32,
+    // Edge pass: kernel, line color and width in pixels (distance of the sampled neighbours)
+    pub edge_operator: EdgeOperator,
+    pub edge_color: [f32; 3],
+    pub edge_width: f32,
+    // Edge pass: smoothstep ranges of the linear depth difference (view space units) and of
+    // the normal difference (1 - cos of the angle), then the strength of each kind of edge.
+    // Object ID edges outline every instance and mesh, even where they touch
+    pub edge_depth_threshold: [f32; 2],
+    pub edge_normal_threshold: [f32; 2],
+    pub edge_depth_weight: f32,
+    pub edge_normal_weight: f32,
+    pub edge_id_weight: f32,
+    // Exposure metered from the composited frame (HDR only), added to renderer.exposure:
+    // histogram (compute, falls back to downsample where unsupported) or downsample
+    pub auto_exposure: AutoExposure,
+    // Metered brightness clamped to [min, max] (log2 luminance, EV): beyond it the frame
+    // darkens or brightens instead. Adaptation rate per second (exponential)
+    pub auto_exposure_range: [f32; 2],
+    pub auto_exposure_speed: f32,
+}
+
+impl Default for EffectsConfig {
+    fn default() -> Self {
+        Self {
+            outline_thickness: 0.07,
+            outline_color: [0.0, 0.5, 0.5],
+            outline_alpha: 0.7,
+            outline_intensity: 1.0,
+            outline_noise: 0.0,
+            outline_visibility: Visibility::Always,
+            outline_occluded_color: None,
+            outline_occluded_dash: 0.0,
+            outline_mode: OutlineMode::Extrude,
+            outline_width: 3.0,
+            outline_falloff: 6.0,
+            bloom: BloomMode::MipChain,
+            blur_radius: 10,
+            blur_sigma: 4.0,
+            blur_iterations: 1,
+            blur_spread: 1.0,
+            blur_path: BlurPath::Render,
+            bloom_levels: 5,
+            bloom_radius: 1.0,
+            bloom_threshold: 1.0,
+            bloom_strength: 3.7,
+            edge_operator: EdgeOperator::Cross,
+            edge_color: [0.6, 0.7, 0.7],
+            edge_width: 1.0,
+            edge_depth_threshold: [0.067, 0.2],
+            edge_normal_threshold: [0.0015, 0.021],
+            edge_depth_weight: 0.6,
+            edge_normal_weight: 1.2,
+            edge_id_weight: 1.0,
+            auto_exposure: AutoExposure::Off,
+            auto_exposure_range: [-8.0, 8.0],
+            auto_exposure_speed: 1.5,
+        }
+    }
+}
+
+impl EffectsConfig {
+    // / Outline of the model, the default of every instance
+    pub fn glow_style(&self) -> GlowStyle {
+        GlowStyle {
+            color: self.outline_color,
+            intensity: self.outline_intensity,
+            thickness: self.outline_thickness,
+            alpha: self.outline_alpha,
+            noise: self.outline_noise,
+        }
+    }
+
+    pub fn set_glow_style(&mut self, style: &GlowStyle) {
+        self.outline_color = style.color;
+        self.outline_intensity = style.intensity;
+        self.outline_thickness = style.thickness;
+        self.outline_alpha = style.alpha;
+        self.outline_noise = style.noise;
+    }
+
+    // / Outline of the model behind other geometry
+    pub fn occlusion_style(&self) -> OcclusionStyle {
+        OcclusionStyle {
+            visibility: self.outline_visibility,
+            color: self.outline_occluded_color,
+            dash: self.outline_occluded_dash,
+        }
+    }
+
+    pub fn set_occlusion_style(&mut self, style: &OcclusionStyle) {
+        self.outline_visibility = style.visibility;
+        self.outline_occluded_color = style.color;
+        self.outline_occluded_dash = style.dash;
+    }
+
+    // / Pipeline-overridable constants of jump_flood.wgsl
+    pub fn jump_flood_constants(&self) -> Vec<(&'static str, f64)> {
+        vec![
+            ("outline_width", self.outline_width as f64),
+            ("outline_falloff", self.outline_falloff as f64),
+        ]
+    }
+
+    // / Jump flood step lengths in pixels, halving down to 1: the largest covers the
+    // / outline distance
+    pub fn jump_flood_steps(&self) -> Vec<u32> {
+        let reach = (self.outline_width + self.outline_falloff).ceil().max(1.0) as u32;
+        let mut step = reach.next_power_of_two();
+        let mut steps = Vec::new();
+        while step >= 1 {
+            steps.push(step);
+            step /= 2;
+        }
+        steps
+    }
+
+    // / Pipeline-overridable constants of blur.wgsl
+    pub fn blur_constants(&self) -> Vec<(&'static str, f64)> {
+        vec![("blur_spread", self.blur_spread as f64)]
+    }
+
+    // / The compute blur caches COMPUTE_BLUR_APRON texels around its tile: false when the
+    // / kernel reaches farther
+    pub fn blur_fits_compute_tile(&self) -> bool {
+        self.blur_radius as f32 * self.blur_spread < (COMPUTE_BLUR_APRON - 1) as f32
+    }
+
+    // / Pipeline-overridable constants of bloom.wgsl (upsample_scale is set per pass)
+    pub fn bloom_constants(&self) -> Vec<(&'static str, f64)> {
+        vec![
+            ("bloom_radius", self.bloom_radius as f64),
+            ("bloom_threshold", self.bloom_threshold as f64),
+        ]
+    }
+
+    // / Pipeline-overridable constants of auto_exposure*.wgsl
+    pub fn auto_exposure_constants(&self) -> Vec<(&'static str, f64)> {
+        let [min_ev, max_ev] = self.auto_exposure_range;
+        vec![("min_ev", min_ev as f64), ("max_ev", max_ev as f64)]
+    }
+
+    // / Pipeline-overridable constants of composite.wgsl
+    pub fn composite_constants(&self) -> Vec<(&'static str, f64)> {
+        vec![("bloom_strength", self.bloom_strength as f64)]
+    }
+}
+
+impl GlowConfig {
+    pub fn from_toml_str(text: &str) -> anyhow::Result<Self> {
+        let config: GlowConfig = toml::from_str(text)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn to_toml_string(&self) -> anyhow::Result<String> {
+        Ok(toml::to_string_pretty(self)?)
+    }
+
+    // / Load and validate a config file from res/ (fetched on the web).
+    pub async fn load(file_name: &str) -> anyhow::Result<Self> {
+        let text = crate::resources::load_string(file_name)
+            .await
+            .with_context(|| format!("cannot read config '{file_name}'"))?;
+
+        Self::from_toml_str(&text).with_context(|| format!("invalid config '{file_name}'"))
+    }
+
+    // / Like `load`, but a missing file means defaults. A file that exists must be valid.
+    pub async fn load_or_default(file_name: &str) -> anyhow::Result<Self> {
+        match crate::resources::load_string(file_name).await {
+            Ok(text) => Self::from_toml_str(&text).with_context(|| format!("invalid config '{file_name}'")),
+            Err(e) => {
+                log::warn!("config '{file_name}' not loaded ({e}), using defaults");
+                Ok(Self::default())
+            }
+        }
+    }
+
+    // / Stencil reference of a highlight group: "none" 0, "default" 1, then the
+    // / [[highlight_groups]] in order
+    pub fn highlight_group(&self, name: &str) -> anyhow::Result<u8> {
+        highlight_group_reference(&self.highlight_groups, name)
+    }
+
+    // / Value checks that don't need a GPU. Every problem is reported, not just the first.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let mut errors = Vec::new();
+
+        if !matches!(self.renderer.sample_count, 1 | 4) {
+            errors.push(format!(
+                "renderer.sample_count must be 1 or 4, got {}",
+                self.renderer.sample_count
+            ));
+        }
+
+        errors.extend(tone_map_errors(self.renderer.exposure, self.renderer.white_point, |field| {
+            format!("renderer.{field}")
+        }));
+
+        if let Some([width, height]) = self.window.size
+            && (width == 0 || height == 0)
+        {
+            errors.push(format!("window.size must be non zero, got {width}x{height}"));
+        }
+
+        if self.window.frame_latency == 0 {
+            errors.push("window.frame_latency must be >= 1".to_string());
+        }
+
+        if self.adapter.name.as_deref().is_some_and(|name| name.trim().is_empty()) {
+            errors.push("adapter.name is empty".to_string());
+        }
+
+        if self.scene.texture.is_empty() {
+            errors.push("scene.texture is empty".to_string());
+        }
+        if self.scene.model.is_empty() {
+            errors.push("scene.model is empty".to_string());
+        }
+        if !self.scene.spin_speed.is_finite() {
+            errors.push(format!("scene.spin_speed must be finite, got {}", self.scene.spin_speed));
+        }
+
+        let camera = &self.camera;
+        if camera.eye == camera.target {
+            errors.push("camera.eye and camera.target are the same point".to_string());
+        }
+        if camera.up == [0.0; 3] {
+            errors.push("camera.up is a zero vector".to_string());
+        }
+        if !(camera.fovy > 0.0 && camera.fovy < 180.0) {
+            errors.push(format!("camera.fovy must be in ]0, 180[ degrees, got {}", camera.fovy));
+        }
+        if !(camera.znear > 0.0 && camera.znear < camera.zfar) {
+            errors.push(format!(
+                "camera.znear must be > 0 and < camera.zfar, got znear {} zfar {}",
+                camera.znear, camera.zfar
+            ));
+        }
+        if !non_negative(camera.speed) {
+            errors.push(format!("camera.speed must be >= 0, got {}", camera.speed));
+        }
+
+        if self.color_grading.luts.iter().any(|lut| lut.is_empty()) {
+            errors.push("color_grading.luts has an empty path".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.color_grading.strength) {
+            errors.push(format!("color_grading.strength must be in [0, 1], got {}", self.color_grading.strength));
+        }
+
+        if self.highlight_groups.len() > MAX_HIGHLIGHT_GROUPS {
+            errors.push(format!(
+                "at most {MAX_HIGHLIGHT_GROUPS} highlight_groups, got {}",
+                self.highlight_groups.len()
+            ));
+        }
+        for (i, group) in self.highlight_groups.iter().enumerate() {
+            if group.name.is_empty() || matches!(group.name.as_str(), "none" | "default") {
+                errors.push(format!("highlight_groups[{i}].name '{}' is empty or reserved", group.name));
+            } else if self.highlight_groups[..i].iter().any(|other| other.name == group.name) {
+                errors.push(format!("highlight_groups[{i}].name '{}' is used twice", group.name));
+            }
+            errors.extend(glow_style_errors(&group.glow, |field| format!("highlight_groups[{i}].glow.{field}")));
+            errors.extend(occlusion_style_errors(&group.occlusion, |field| {
+                format!("highlight_groups[{i}].occlusion.{field}")
+            }));
+        }
+
+        for (i, instance) in self.scene.instances.iter().enumerate() {
+            if instance.position.iter().any(|c| !c.is_finite()) || !instance.angle.is_finite() {
+                errors.push(format!("scene.instances[{i}] position / angle must be finite"));
+            }
+            if let Some(Err(e)) = instance.group.as_deref().map(|name| self.highlight_group(name)) {
+                errors.push(format!("scene.instances[{i}].group: {e}"));
+            }
+            if let Some(glow) = &instance.glow {
+                errors.extend(glow_style_errors(glow, |field| format!("scene.instances[{i}].glow.{field}")));
+            }
+        }
+
+        let effects = &self.effects;
+        errors.extend(glow_style_errors(&effects.glow_style(), |field| format!("effects.outline_{field}")));
+        errors.extend(occlusion_style_errors(&effects.occlusion_style(), |field| {
+            format!("effects.outline_occluded_{field}")
+        }));
+        if !non_negative(effects.outline_width) {
+            errors.push(format!("effects.outline_width must be >= 0, got {}", effects.outline_width));
+        }
+        if !non_negative(effects.outline_falloff) {
+            errors.push(format!("effects.outline_falloff must be >= 0, got {}", effects.outline_falloff));
+        }
+        let reach = effects.outline_width + effects.outline_falloff;
+        if !(reach > 0.0 && reach <= MAX_OUTLINE_DISTANCE) {
+            errors.push(format!(
+                "effects.outline_width + effects.outline_falloff must be in ]0, {MAX_OUTLINE_DISTANCE}] pixels, got {reach}"
+            ));
+        }
+        if !(1..=MAX_BLUR_RADIUS).contains(&effects.blur_radius) {
+            errors.push(format!(
+                "effects.blur_radius must be in [1, {MAX_BLUR_RADIUS}], got {}",
+                effects.blur_radius
+            ));
+        }
+        if !(effects.blur_sigma > 0.0 && effects.blur_sigma.is_finite()) {
+            errors.push(format!("effects.blur_sigma must be > 0, got {}", effects.blur_sigma));
+        }
+        if !(1..=MAX_BLUR_ITERATIONS).contains(&effects.blur_iterations) {
+            errors.push(format!(
+                "effects.blur_iterations must be in [1, {MAX_BLUR_ITERATIONS}], got {}",
+                effects.blur_iterations
+            ));
+        }
+        if !non_negative(effects.blur_spread) {
+            errors.push(format!("effects.blur_spread must be >= 0, got {}", effects.blur_spread));
+        }
+        if !(1..=MAX_BLOOM_LEVELS).contains(&effects.bloom_levels) {
+            errors.push(format!(
+                "effects.bloom_levels must be in [1, {MAX_BLOOM_LEVELS}], got {}",
+                effects.bloom_levels
+            ));
+        }
+        if !(effects.bloom_radius > 0.0 && effects.bloom_radius.is_finite()) {
+            errors.push(format!("effects.bloom_radius must be > 0, got {}", effects.bloom_radius));
+        }
+        if !non_negative(effects.bloom_threshold) {
+            errors.push(format!("effects.bloom_threshold must be >= 0, got {}", effects.bloom_threshold));
+        }
+        if !non_negative(effects.bloom_strength) {
+            errors.push(format!("effects.bloom_strength must be >= 0, got {}", effects.bloom_strength));
+        }
+        if effects.edge_color.iter().any(|c| !non_negative(*c)) {
+            errors.push(format!("effects.edge_color must be >= 0, got {:?}", effects.edge_color));
+        }
+        if !(effects.edge_width > 0.0 && effects.edge_width <= MAX_EDGE_WIDTH) {
+            errors.push(format!(
+                "effects.edge_width must be in ]0, {MAX_EDGE_WIDTH}] pixels, got {}",
+                effects.edge_width
+            ));
+        }
+        for (name, [low, high]) in [
+            ("edge_depth_threshold", effects.edge_depth_threshold),
+            ("edge_normal_threshold", effects.edge_normal_threshold),
+        ] {
+            if !(non_negative(low) && high.is_finite() && low < high) {
+                errors.push(format!("effects.{name} must be [low, high] with 0 <= low < high, got [{low}, {high}]"));
+            }
+        }
+        if !non_negative(effects.edge_depth_weight) {
+            errors.push(format!("effects.edge_depth_weight must be >= 0, got {}", effects.edge_depth_weight));
+        }
+        if !non_negative(effects.edge_normal_weight) {
+            errors.push(format!("effects.edge_normal_weight must be >= 0, got {}", effects.edge_normal_weight));
+        }
+        if !non_negative(effects.edge_id_weight) {
+            errors.push(format!("effects.edge_id_weight must be >= 0, got {}", effects.edge_id_weight));
+        }
+        let [min_ev, max_ev] = effects.auto_exposure_range;
+        if !((-MAX_EXPOSURE..=MAX_EXPOSURE).contains(&min_ev) && (-MAX_EXPOSURE..=MAX_EXPOSURE).contains(&max_ev) && min_ev < max_ev) {
+            errors.push(format!(
+                "effects.auto_exposure_range must be [min, max] with -{MAX_EXPOSURE} <= min < max <= {MAX_EXPOSURE}, got [{min_ev}, {max_ev}]"
+            ));
+        }
+        if !(effects.auto_exposure_speed.is_finite() && effects.auto_exposure_speed > 0.0) {
+            errors.push(format!("effects.auto_exposure_speed must be > 0, got {}", effects.auto_exposure_speed));
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!(errors.join("\n")))
+        }
+    }
+
+    // / Check the adapter can render every target of the glow graph with these settings.
+    pub fn validate_for_adapter(
+        &self,
+        adapter: &wgpu::Adapter,
+        surface_format: wgpu::TextureFormat,
+    ) -> anyhow::Result<()> {
+        if let Some((name, format)) = unrenderable_target(adapter, self.renderer.is_hdr, surface_format) {
+            bail!("{name} target format {format:?} is not renderable on this adapter");
+        }
+
+        if let Some((name, format)) =
+            unsupported_sample_count(adapter, self.renderer.is_hdr, surface_format, self.renderer.sample_count)
+        {
+            bail!(
+                "renderer.sample_count = {} is not supported for the {name} target format {format:?} on this adapter",
+                self.renderer.sample_count
+            );
+        }
+
+        Ok(())
+    }
+}
+
+// / Formats of the glow graph targets (color, normal, depth stencil)
+pub fn glow_target_formats(
+    is_hdr: bool,
+    surface_format: wgpu::TextureFormat,
+) -> [(&'static str, wgpu::TextureFormat); 3] {
+    let color_format = if is_hdr {
+        wgpu::TextureFormat::Rgba16Float
+    } else {
+        surface_format
+    };
+
+    [
+        ("color", color_format),
+        ("normal", wgpu::TextureFormat::Rgba16Float),
+        ("depth stencil", wgpu::TextureFormat::Depth24PlusStencil8),
+    ]
+}
+
+// / First glow target the adapter can't render to (the object IDs are never multisampled).
+pub fn unrenderable_target(
+    adapter: &wgpu::Adapter,
+    is_hdr: bool,
+    surface_format: wgpu::TextureFormat,
+) -> Option<(&'static str, wgpu::TextureFormat)> {
+    glow_target_formats(is_hdr, surface_format)
+        .into_iter()
+        .chain([("object id", OBJECT_ID_FORMAT)])
+        .find(|(_, format)| {
+            !adapter
+                .get_texture_format_features(*format)
+                .allowed_usages
+                .contains(wgpu::TextureUsages::RENDER_ATTACHMENT)
+        })
+}
+
+// / First glow target that can't be multisampled `sample_count` times on this adapter.
+pub fn unsupported_sample_count(
+    adapter: &wgpu::Adapter,
+    is_hdr: bool,
+    surface_format: wgpu::TextureFormat,
+    sample_count: u32,
+) -> Option<(&'static str, wgpu::TextureFormat)> {
+    glow_target_formats(is_hdr, surface_format)
+        .into_iter()
+        .find(|(_, format)| {
+            !adapter
+                .get_texture_format_features(*format)
+                .flags
+                .sample_count_supported(sample_count)
+        })
+}
+
+// / Why the compute passes (blur, histogram auto exposure) can't run on this adapter (no
+// / compute shaders: WebGL, or no Rgba16Float storage texture), None when it can.
+pub fn compute_unsupported(adapter: &wgpu::Adapter) -> Option<&'static str> {
+    if !adapter
+        .get_downlevel_capabilities()
+        .flags
+        .contains(wgpu::DownlevelFlags::COMPUTE_SHADERS)
+    {
+        return Some("no compute shaders");
+    }
+    if !adapter
+        .get_texture_format_features(wgpu::TextureFormat::Rgba16Float)
+        .allowed_usages
+        .contains(wgpu::TextureUsages::STORAGE_BINDING)
+    {
+        return Some("Rgba16Float is not a storage texture format");
+    }
+    None
+}
+
+// / Why the jump flood outline can't run on this adapter (Rgba16Uint seeds not
+// / renderable), None when it can.
+pub fn jump_flood_unsupported(adapter: &wgpu::Adapter) -> Option<&'static str> {
+    if !adapter
+        .get_texture_format_features(JUMP_FLOOD_FORMAT)
+        .allowed_usages
+        .contains(wgpu::TextureUsages::RENDER_ATTACHMENT)
+    {
+        return Some("Rgba16Uint is not renderable");
+    }
+    None
+}
+
+// / Jump flood seeds: pixel coordinates of the nearest mask pixel, its group, 1 when set
+pub const JUMP_FLOOD_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Uint;
+
+// / Object ID of each pixel (model::ObjectId), single sampled: integer targets don't resolve
+pub const OBJECT_ID_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::R32Uint;
+
+pub(crate) fn parse_named<T: Copy>(
+    s: &str,
+    all: &[T],
+    name: impl Fn(&T) -> &'static str,
+    what: &str,
+) -> anyhow::Result<T> {
+    all.iter()
+        .find(|value| name(value).eq_ignore_ascii_case(s))
+        .copied()
+        .ok_or_else(|| {
+            let names = all.iter().map(&name).collect::<Vec<_>>().join(", ");
+            anyhow!("unknown {what} '{s}', expected one of: {names}")
+        })
+}
+
+fn glow_style_errors(style: &GlowStyle, field: impl Fn(&str) -> String) -> Vec<String> {
+    let mut errors = Vec::new();
+    if !non_negative(style.thickness) {
+        errors.push(format!("{} must be >= 0, got {}", field("thickness"), style.thickness));
+    }
+    if !(0.0..=1.0).contains(&style.alpha) {
+        errors.push(format!("{} must be in [0, 1], got {}", field("alpha"), style.alpha));
+    }
+    if style.color.iter().any(|c| !non_negative(*c)) {
+        errors.push(format!("{} must be >= 0, got {:?}", field("color"), style.color));
+    }
+    if !non_negative(style.intensity) {
+        errors.push(format!("{} must be >= 0, got {}", field("intensity"), style.intensity));
+    }
+    if !(0.0..=1.0).contains(&style.noise) {
+        errors.push(format!("{} must be in [0, 1], got {}", field("noise"), style.noise));
+    }
+    errors
+}
+
+// / Exposure (EV) and white point of the tone map pass
+pub fn validate_tone_map(exposure: f32, white_point: f32) -> anyhow::Result<()> {
+    let errors = tone_map_errors(exposure, white_point, |field| field.replace('_', " "));
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow!(errors.join("\n")))
+    }
+}
+
+fn tone_map_errors(exposure: f32, white_point: f32, field: impl Fn(&str) -> String) -> Vec<String> {
+    let mut errors = Vec::new();
+    if !(-MAX_EXPOSURE..=MAX_EXPOSURE).contains(&exposure) {
+        errors.push(format!("{} must be in [-{MAX_EXPOSURE}, {MAX_EXPOSURE}] EV, got {exposure}", field("exposure")));
+    }
+    if !(white_point.is_finite() && white_point > 0.0) {
+        errors.push(format!("{} must be > 0, got {white_point}", field("white_point")));
+    }
+    errors
+}
+
+fn occlusion_style_errors(style: &OcclusionStyle, field: impl Fn(&str) -> String) -> Vec<String> {
+    let mut errors = Vec::new();
+    if let Some(color) = style.color
+        && color.iter().any(|c| !non_negative(*c))
+    {
+        errors.push(format!("{} must be >= 0, got {color:?}", field("color")));
+    }
+    if !non_negative(style.dash) {
+        errors.push(format!("{} must be >= 0, got {}", field("dash"), style.dash));
+    }
+    errors
+}
+
+// / Stencil reference of the highlight group `name` among `groups` (the config or the runtime ones).
+// / A group past the stencil range is an error, not a wrapped reference.
+pub fn highlight_group_reference(groups: &[HighlightGroupConfig], name: &str) -> anyhow::Result<u8> {
+    match name {
+        "none" => Ok(NO_HIGHLIGHT),
+        "default" => Ok(DEFAULT_HIGHLIGHT_GROUP),
+        _ => {
+            let i = groups
+                .iter()
+                .position(|group| group.name == name)
+                .ok_or_else(|| anyhow!("unknown highlight group '{name}'"))?;
+            u8::try_from(i + DEFAULT_HIGHLIGHT_GROUP as usize + 1)
+                .map_err(|_| anyhow!("highlight group '{name}' is past the {MAX_HIGHLIGHT_GROUPS} groups of the stencil"))
+        }
+    }
+}
+
+fn non_negative(value: f32) -> bool {
+    value.is_finite() && value >= 0.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error_of(text: &str) -> String {
+        format!("{:#}", GlowConfig::from_toml_str(text).expect_err("invalid config accepted"))
+    }
+
+    #[test]
+    fn defaults_round_trip() {
+        let text = GlowConfig::default().to_toml_string().expect("defaults serialize");
+        assert_eq!(GlowConfig::from_toml_str(&text).expect("defaults are valid"), GlowConfig::default());
+        assert_eq!(GlowConfig::from_toml_str("").expect("empty file"), GlowConfig::default());
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected() {
+        for (text, field) in [
+            ("sample_cout = 4", "sample_cout"),
+            ("[renderer]\nsample_cout = 4", "sample_cout"),
+            ("[effects]\nblur_radiuss = 4", "blur_radiuss"),
+            ("[[highlight_groups]]\nname = \"a\"\ncolour = [1.0, 0.0, 0.0, 1.0]", "colour"),
+        ] {
+            let e = error_of(text);
+            assert!(e.contains("unknown field") && e.contains(field), "'{e}' for '{text}'");
+        }
+    }
+
+    #[test]
+    fn out_of_range_values_are_rejected() {
+        for (text, error) in [
+            ("[renderer]\nsample_count = 2", "renderer.sample_count must be 1 or 4, got 2"),
+            ("[window]\nsize = [0, 600]", "window.size must be non zero, got 0x600"),
+            ("[effects]\nblur_radius = 0", "effects.blur_radius must be in"),
+            ("[effects]\nblur_sigma = -1.0", "effects.blur_sigma must be > 0"),
+            ("[color_grading]\nstrength = 1.5", "color_grading.strength must be in [0, 1], got 1.5"),
+        ] {
+            let e = error_of(text);
+            assert!(e.contains(error), "'{e}' instead of '{error}'");
+        }
+    }
+
+    #[test]
+    fn highlight_groups_past_the_stencil_are_rejected() {
+        let mut config = GlowConfig {
+            highlight_groups: (0..=MAX_HIGHLIGHT_GROUPS)
+                .map(|i| HighlightGroupConfig { name: format!("group {i}"), ..Default::default() })
+                .collect(),
+            ..GlowConfig::default()
+        };
+        assert_eq!(config.highlight_group("group 253").expect("last stencil reference"), u8::MAX);
+        config.scene.instances = vec![InstanceConfig { group: Some("group 254".into()), ..Default::default() }];
+
+        let e = config.validate().expect_err("too many groups accepted").to_string();
+        assert!(e.contains("at most"), "'{e}'");
+        assert!(e.contains("scene.instances[0].group: highlight group 'group 254' is past"), "'{e}'");
+    }
+
+    #[test]
+    fn every_error_is_reported() {
+        let mut config = GlowConfig::default();
+        config.renderer.sample_count = 3;
+        config.scene.model = String::new();
+        config.effects.blur_iterations = 0;
+
+        let e = config.validate().expect_err("invalid config accepted").to_string();
+        assert_eq!(e.lines().count(), 3, "'{e}'");
+        for error in ["renderer.sample_count", "scene.model is empty", "effects.blur_iterations"] {
+            assert!(e.contains(error), "'{e}' without '{error}'");
+        }
+    }
+}
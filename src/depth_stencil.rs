@@ -19,6 +19,13 @@ impl StencilTexture {
 
         let depth_stencil_format = wgpu::TextureFormat::Depth24PlusStencil8;
 
+        // Multisampled + TEXTURE_BINDING is not supported by the GL backend
+        let usage = if sample_count == 1 {
+            wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING
+        } else {
+            wgpu::TextureUsages::RENDER_ATTACHMENT
+        };
+
         let depth_stencil_texture = device.create_texture(&wgpu::TextureDescriptor {
             label: Some(label),
             size,
@@ -26,7 +33,7 @@ impl StencilTexture {
             sample_count,
             dimension: wgpu::TextureDimension::D2,
             format: depth_stencil_format,
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            usage,
             view_formats: &[],
         });
 
@@ -1,6 +1,7 @@
+use bytemuck::Zeroable;
 use wgpu::util::DeviceExt;
 
-use crate::utils;
+use crate::{config::{EffectsConfig, GlowStyle, OcclusionStyle, ToneMapOperator, Visibility}, resources::CubeLut, texture::Texture, utils};
 
 #[repr(C)]
 // This is so we can store this in a buffer
@@ -73,6 +74,188 @@ impl SpinUniform {
     }
 }
 
+// /
+// /  G L O W   S T Y L E
+// /
+// / Outline of the model, group 2 of the outline pass. Instances can override it with the
+// / same layout in their vertex attributes (InstanceRaw).
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct GlowStyleUniform {
+    // rgb * intensity, alpha
+    pub color: [f32; 4],
+    // thickness, noise, z = 1 when set (instance override), w = highlight group (instances)
+    pub params: [f32; 4],
+}
+
+impl GlowStyleUniform {
+    pub fn new(style: &GlowStyle) -> Self {
+        let [r, g, b] = style.color.map(|c| c * style.intensity);
+        Self {
+            color: [r, g, b, style.alpha],
+            params: [style.thickness, style.noise, 1.0, 0.0],
+        }
+    }
+
+    // / Instance without its own style: the model one is used
+    pub fn unset() -> Self {
+        Self::zeroed()
+    }
+
+    pub fn create_glow_style_buffer(&self, device: &wgpu::Device) -> wgpu::Buffer {
+        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Glow Style Buffer"),
+            contents: bytemuck::bytes_of(self),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        })
+    }
+
+    // / Binding 0: the model outline (outline pass). Binding 1: the outline of every
+    // / highlight group (jump flood outline), see `group_glow_styles`. Binding 2: how each
+    // / group shows behind other geometry (outline pass), see `OcclusionUniform`
+    pub fn bind_group_for_glow_style(
+        glow_style_buffer: &wgpu::Buffer,
+        group_styles_buffer: &wgpu::Buffer,
+        group_occlusion_buffer: &wgpu::Buffer,
+        device: &wgpu::Device,
+    ) -> (wgpu::BindGroupLayout, wgpu::BindGroup) {
+        let glow_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::VERTEX,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                ],
+                label: Some("glow_style_bind_group_layout"),
+            });
+
+        let glow_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &glow_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: glow_style_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: group_styles_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: group_occlusion_buffer.as_entire_binding(),
+                },
+            ],
+            label: Some("glow_style_bind_group"),
+        });
+
+        (glow_bind_group_layout, glow_bind_group)
+    }
+
+    // / One style per stencil reference (uniform array size in jump_flood.wgsl)
+    pub const GROUP_SLOTS: usize = 256;
+
+    pub fn create_group_styles_buffer(device: &wgpu::Device) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Group Glow Styles Buffer"),
+            size: (Self::GROUP_SLOTS * std::mem::size_of::<Self>()) as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    // / Styles indexed by stencil reference: unset for NO_HIGHLIGHT, the model outline for
+    // / DEFAULT_HIGHLIGHT_GROUP, then the highlight groups
+    pub fn group_glow_styles<'a>(
+        model: &GlowStyle,
+        groups: impl IntoIterator<Item = &'a GlowStyle>,
+    ) -> Vec<Self> {
+        [Self::unset(), Self::new(model)]
+            .into_iter()
+            .chain(groups.into_iter().map(Self::new))
+            .take(Self::GROUP_SLOTS)
+            .collect()
+    }
+}
+
+// / How the outline of a highlight group shows behind other geometry, indexed by stencil
+// / reference like the group glow styles (binding 2 of the glow bind group)
+
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct OcclusionUniform {
+    // Hidden parts: rgb * glow intensity, w = 1 when set (0: the glow color)
+    pub color: [f32; 4],
+    // Visibility (0 always, 1 visible, 2 occluded), dash width in pixels, unused, unused
+    pub params: [f32; 4],
+}
+
+impl OcclusionUniform {
+    pub fn new(occlusion: &OcclusionStyle, glow: &GlowStyle) -> Self {
+        let color = occlusion
+            .color
+            .map_or([0.0; 4], |color| {
+                let [r, g, b] = color.map(|c| c * glow.intensity);
+                [r, g, b, 1.0]
+            });
+        let visibility = match occlusion.visibility {
+            Visibility::Always => 0.0,
+            Visibility::Visible => 1.0,
+            Visibility::Occluded => 2.0,
+        };
+        Self { color, params: [visibility, occlusion.dash, 0.0, 0.0] }
+    }
+
+    pub fn create_group_occlusion_buffer(device: &wgpu::Device) -> wgpu::Buffer {
+        device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Group Occlusion Buffer"),
+            size: (GlowStyleUniform::GROUP_SLOTS * std::mem::size_of::<Self>()) as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        })
+    }
+
+    // / Styles indexed by stencil reference, as `GlowStyleUniform::group_glow_styles`
+    pub fn group_occlusion_styles<'a>(
+        model: (&GlowStyle, &OcclusionStyle),
+        groups: impl IntoIterator<Item = (&'a GlowStyle, &'a OcclusionStyle)>,
+    ) -> Vec<Self> {
+        let (glow, occlusion) = model;
+        [Self::zeroed(), Self::new(occlusion, glow)]
+            .into_iter()
+            .chain(groups.into_iter().map(|(glow, occlusion)| Self::new(occlusion, glow)))
+            .take(GlowStyleUniform::GROUP_SLOTS)
+            .collect()
+    }
+}
+
 pub struct Spin {
     angle: f32,
     speed: f32, // radians per second
@@ -95,6 +278,10 @@ impl Spin {
     pub fn angle(&self) -> f32 {
         self.angle
     }
+
+    pub fn set_angle(&mut self, angle: f32) {
+        self.angle = angle.rem_euclid(std::f32::consts::TAU);
+    }
 }
 //
 // Mirror plane Uniform
@@ -121,107 +308,787 @@ impl MirrorPlaneUniform {
         }
     }
 
-    pub fn create_bind_group_layout(
-        device: &wgpu::Device,
-        mirror_plane_buffer: &wgpu::Buffer,
-    ) -> (wgpu::BindGroupLayout, wgpu::BindGroup) {
-        let mirror_plane_bind_group_layout =
-            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-                label: Some("mirror_plane_bind_group_layout"),
-                entries: &[wgpu::BindGroupLayoutEntry {
+    pub fn create_bind_group_layout(
+        device: &wgpu::Device,
+        mirror_plane_buffer: &wgpu::Buffer,
+    ) -> (wgpu::BindGroupLayout, wgpu::BindGroup) {
+        let mirror_plane_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("mirror_plane_bind_group_layout"),
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: Some(
+                            std::num::NonZeroU64::new(
+                                std::mem::size_of::<MirrorPlaneUniform>() as u64
+                            )
+                            .unwrap(),
+                        ),
+                    },
+                    count: None,
+                }],
+            });
+
+        let mirror_plane_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("mirror_plane_bind_group"),
+            layout: &mirror_plane_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: mirror_plane_buffer.as_entire_binding(),
+            }],
+        });
+
+        (mirror_plane_bind_group_layout, mirror_plane_bind_group)
+    }
+
+    pub fn mirror_plane_buffer(&self, device: &wgpu::Device) -> wgpu::Buffer {
+        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Mirror Plane Buffer"),
+            contents: bytemuck::cast_slice(&[*self]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        })
+    }
+}
+
+
+
+// / Merged taps per side of the blur kernel, center included (uniform array size in blur.wgsl)
+pub const MAX_BLUR_TAPS: usize = 16;
+// / Each merged tap but the center covers two texels
+pub const MAX_BLUR_RADIUS: u32 = 2 * (MAX_BLUR_TAPS as u32 - 1);
+
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct BlurParams {
+   pub direction: [f32; 2],
+   pub tap_count: u32,
+   pub _padding: u32,
+   // x = offset in texels, y = weight (zw unused: uniform arrays have a 16 byte stride).
+   // taps[0] is the center, the others are sampled on both sides
+   pub taps: [[f32; 4]; MAX_BLUR_TAPS],
+}
+
+
+impl BlurParams {
+
+    // / Direction only, the kernel is a single tap (copy)
+    pub fn new(x:f32, y: f32) -> Self {
+        let mut taps = [[0.0; 4]; MAX_BLUR_TAPS];
+        taps[0] = [0.0, 1.0, 0.0, 0.0];
+        Self { direction: [x,y], tap_count: 1, _padding: 0, taps }
+    }
+
+    // / Normalized Gaussian kernel of `radius` texels per side. Neighbouring texels are
+    // / merged in one bilinear tap at their weighted offset (linear sampling): radius 8
+    // / takes 5 fetches per side instead of 9.
+    pub fn gaussian(radius: u32, sigma: f32) -> Self {
+        let radius = radius.min(MAX_BLUR_RADIUS);
+
+        let discrete = (0..=radius)
+            .map(|i| (-((i * i) as f32) / (2.0 * sigma * sigma)).exp())
+            .collect::<Vec<_>>();
+        let total = discrete[0] + 2.0 * discrete[1..].iter().sum::<f32>();
+
+        let mut params = Self::new(0.0, 0.0);
+        params.taps[0] = [0.0, discrete[0] / total, 0.0, 0.0];
+
+        for (k, pair) in discrete[1..].chunks(2).enumerate() {
+            // First texel of the pair: 1, 3, 5 ...
+            let texel = (2 * k + 1) as f32;
+            let weight = pair.iter().sum::<f32>();
+            let offset = texel + pair.get(1).map_or(0.0, |b| b / weight);
+
+            params.taps[k + 1] = [offset, weight / total, 0.0, 0.0];
+            params.tap_count += 1;
+        }
+
+        params
+    }
+
+    pub fn with_direction(self, x: f32, y: f32) -> Self {
+        Self { direction: [x, y], ..self }
+    }
+}
+
+// / One jump flood pass: neighbours `step` pixels apart (jump_flood.wgsl)
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct JumpFloodParams {
+    pub step: f32,
+    pub _padding: [f32; 3],
+}
+
+impl JumpFloodParams {
+    pub fn new(step: u32) -> Self {
+        Self { step: step as f32, _padding: [0.0; 3] }
+    }
+}
+
+// / Edge detection of the edge pass (normal_depth_edges.wgsl)
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct EdgeParams {
+    // rgb, unused
+    pub color: [f32; 4],
+    // Depth smoothstep low / high, normal smoothstep low / high
+    pub thresholds: [f32; 4],
+    // Depth, normal and object ID edge strength, unused
+    pub weights: [f32; 4],
+    // Width in pixels, operator (EdgeOperator::shader_index), camera znear, zfar
+    pub params: [f32; 4],
+}
+
+impl EdgeParams {
+    pub fn new(effects: &EffectsConfig) -> Self {
+        let [r, g, b] = effects.edge_color;
+        let [depth_low, depth_high] = effects.edge_depth_threshold;
+        let [normal_low, normal_high] = effects.edge_normal_threshold;
+        Self {
+            color: [r, g, b, 1.0],
+            thresholds: [depth_low, depth_high, normal_low, normal_high],
+            weights: [effects.edge_depth_weight, effects.edge_normal_weight, effects.edge_id_weight, 0.0],
+            // Clip planes from the camera of each frame (with_clip)
+            params: [effects.edge_width, effects.edge_operator.shader_index() as f32, 0.0, 0.0],
+        }
+    }
+
+    // / Clip planes of the camera that rendered the depth prepass
+    pub fn with_clip(self, znear: f32, zfar: f32) -> Self {
+        let [width, operator, ..] = self.params;
+        Self { params: [width, operator, znear, zfar], ..self }
+    }
+}
+
+// / Tone map of the last pass (hdr_lite.wgsl), written when the operator or exposure changes
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ToneMapParams {
+    // Stops (EV)
+    pub exposure: f32,
+    pub white_point: f32,
+    // ToneMapOperator::shader_index
+    pub curve: u32,
+    pub _padding: u32,
+}
+
+impl ToneMapParams {
+    pub fn new(operator: ToneMapOperator, exposure: f32, white_point: f32) -> Self {
+        Self { exposure, white_point, curve: operator.shader_index(), _padding: 0 }
+    }
+
+    pub fn create_tone_map_params_buffer(&self, device: &wgpu::Device) -> wgpu::Buffer {
+        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Tone Map Params Buffer"),
+            contents: bytemuck::bytes_of(self),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        })
+    }
+
+    // / Group 1 of the tone map pass
+    pub fn bind_group_for_tone_map_params(
+        tone_map_params_buffer: &wgpu::Buffer,
+        device: &wgpu::Device,
+    ) -> (wgpu::BindGroupLayout, wgpu::BindGroup) {
+        let tone_map_params_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                }],
+                label: Some("tone_map_params_bind_group_layout"),
+            });
+
+        let tone_map_params_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &tone_map_params_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: tone_map_params_buffer.as_entire_binding(),
+            }],
+            label: Some("tone_map_params_bind_group"),
+        });
+
+        (tone_map_params_bind_group_layout, tone_map_params_bind_group)
+    }
+}
+
+// / Color grading of the tone mapped frame (color_grade.wgsl): domain of the LUT in use and
+// / how much of it shows. Written when the LUT or its strength changes
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ColorGradeParams {
+    // DOMAIN_MIN, LUT_3D_SIZE
+    pub domain_min: [f32; 3],
+    pub size: f32,
+    // DOMAIN_MAX, 0 (tone mapped frame) to 1 (graded)
+    pub domain_max: [f32; 3],
+    pub strength: f32,
+}
+
+impl ColorGradeParams {
+    pub fn new(lut: &CubeLut, strength: f32) -> Self {
+        Self {
+            domain_min: lut.domain_min,
+            size: lut.size as f32,
+            domain_max: lut.domain_max,
+            strength,
+        }
+    }
+
+    pub fn create_color_grade_params_buffer(&self, device: &wgpu::Device) -> wgpu::Buffer {
+        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Color Grade Params Buffer"),
+            contents: bytemuck::bytes_of(self),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        })
+    }
+
+    // / Group 1 of the color grade pass: 3D LUT, its trilinear sampler and the params
+    pub fn create_color_lut_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D3,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+            label: Some("color_lut_bind_group_layout"),
+        })
+    }
+
+    // / One per loaded LUT, all sharing the params buffer
+    pub fn bind_group_for_color_lut(
+        color_grade_params_buffer: &wgpu::Buffer,
+        lut: &Texture,
+        layout: &wgpu::BindGroupLayout,
+        device: &wgpu::Device,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&lut.view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&lut.sampler),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: color_grade_params_buffer.as_entire_binding(),
+                },
+            ],
+            label: Some("color_lut_bind_group"),
+        })
+    }
+}
+
+// / Auto exposure of the frame (auto_exposure*.wgsl), written every frame
+#[repr(C)]
+#[derive(Debug, Copy, Clone, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct AutoExposureParams {
+    // 0 (keep the previous exposure) to 1 (jump to the metered one)
+    pub adaptation: f32,
+    pub _padding: [f32; 3],
+}
+
+impl AutoExposureParams {
+    // / Exponential easing at `speed` per second over a frame of `delta_time` seconds
+    pub fn new(speed: f32, delta_time: f32) -> Self {
+        Self { adaptation: 1.0 - (-speed * delta_time).exp(), _padding: [0.0; 3] }
+    }
+}
+
+// / Mesh drawn by the scene pass, the low bits of its object IDs (shader.wgsl)
+#[repr(C)]
+#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct ObjectIdParams {
+    pub mesh: u32,
+    pub _padding: [u32; 3],
+}
+
+impl ObjectIdParams {
+    pub fn new(mesh: u32) -> Self {
+        Self { mesh, _padding: [0; 3] }
+    }
+}
+
+// /
+// /  P E R - P A S S   U N I F O R M S
+// /
+// / One uniform buffer with a slot per pass, bound with a dynamic offset. Every
+// / queue.write_buffer of a frame lands before its submit: passes sharing a single
+// / uniform would all see the last value written.
+// /
+// / The bind group layout entry needs `has_dynamic_offset: true` and
+// / `min_binding_size: PassUniforms::<T>::min_binding_size()`; bind `binding()` and
+// / pass `offset(slot)` to set_bind_group.
+
+pub struct PassUniforms<T> {
+    buffer: wgpu::Buffer,
+    stride: u64,
+    len: u32,
+    _marker: std::marker::PhantomData<T>,
+}
+
+impl<T> Clone for PassUniforms<T> {
+    fn clone(&self) -> Self {
+        Self { buffer: self.buffer.clone(), stride: self.stride, len: self.len, _marker: std::marker::PhantomData }
+    }
+}
+
+impl<T: bytemuck::Pod> PassUniforms<T> {
+
+    pub fn new(device: &wgpu::Device, label: &str, len: u32) -> Self {
+        let alignment = device.limits().min_uniform_buffer_offset_alignment as u64;
+        let stride = (std::mem::size_of::<T>() as u64).next_multiple_of(alignment);
+
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some(label),
+            size: stride * len.max(1) as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Self { buffer, stride, len, _marker: std::marker::PhantomData }
+    }
+
+    pub fn len(&self) -> u32 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    // / Dynamic offset of `slot`, for set_bind_group
+    pub fn offset(&self, slot: u32) -> u32 {
+        assert!(slot < self.len, "uniform slot {slot} out of {}", self.len);
+        (slot as u64 * self.stride) as u32
+    }
+
+    pub fn write(&self, queue: &wgpu::Queue, slot: u32, value: &T) {
+        queue.write_buffer(&self.buffer, self.offset(slot) as u64, bytemuck::bytes_of(value));
+    }
+
+    // / One slot: the dynamic offset selects which
+    pub fn binding(&self) -> wgpu::BindingResource<'_> {
+        wgpu::BindingResource::Buffer(wgpu::BufferBinding {
+            buffer: &self.buffer,
+            offset: 0,
+            size: Self::min_binding_size(),
+        })
+    }
+
+    pub fn min_binding_size() -> Option<wgpu::BufferSize> {
+        wgpu::BufferSize::new(std::mem::size_of::<T>() as u64)
+    }
+}
+
+    pub fn create_blur_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("blur bind group layout"),
+            entries: &[
+                // Input texture
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                    },
+                    count: None,
+                },
+                // Sampler
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(
+                        wgpu::SamplerBindingType::Filtering
+                    ),
+                    count: None,
+                },
+                // Blur params
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: PassUniforms::<BlurParams>::min_binding_size(),
+                    },
+                    count: None,
+                },
+            ],
+        })
+
+
+    }
+
+    pub fn create_blur_bind_group(
+            device: &wgpu::Device, 
+            blur_bind_group_layout: &wgpu::BindGroupLayout,
+            texture_view: &wgpu::TextureView,
+            linear_sampler: &wgpu::Sampler,
+            blur_params: &PassUniforms<BlurParams>,
+        ) -> wgpu::BindGroup {
+
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("blur bind group"),
+            layout: &blur_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&texture_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&linear_sampler),
+                },
+                // Blur params uniform, one slot per pass (dynamic offset)
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: blur_params.binding(),
+                },
+            ],
+        })
+    }
+
+    // / Outline pass: depth of the whole scene, to tell the hidden parts of an outline
+    pub fn create_scene_depth_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("scene depth bind group layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Texture {
+                    multisampled: false,
+                    view_dimension: wgpu::TextureViewDimension::D2,
+                    // Depth32Float read as unfilterable float (works on the GL backend too)
+                    sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                },
+                count: None,
+            }],
+        })
+    }
+
+    pub fn create_scene_depth_bind_group(
+            device: &wgpu::Device,
+            layout: &wgpu::BindGroupLayout,
+            depth_view: &wgpu::TextureView,
+        ) -> wgpu::BindGroup {
+
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("scene depth bind group"),
+            layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(depth_view),
+            }],
+        })
+    }
+
+    // / Jump flood: seeds loaded texel by texel (integer texture), step uniform
+    // / Mesh of each scene draw (ObjectIdParams), one slot per mesh
+    pub fn create_object_id_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("object id bind group layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: true,
+                    min_binding_size: PassUniforms::<ObjectIdParams>::min_binding_size(),
+                },
+                count: None,
+            }],
+        })
+    }
+
+    pub fn create_object_id_bind_group(
+        device: &wgpu::Device,
+        object_id_bind_group_layout: &wgpu::BindGroupLayout,
+        params: &PassUniforms<ObjectIdParams>,
+    ) -> wgpu::BindGroup {
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("object id bind group"),
+            layout: object_id_bind_group_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: params.binding(),
+            }],
+        })
+    }
+
+    pub fn create_jump_flood_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("jump flood bind group layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Uint,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: true,
+                        min_binding_size: PassUniforms::<JumpFloodParams>::min_binding_size(),
+                    },
+                    count: None,
+                },
+            ],
+        })
+    }
+
+    pub fn create_jump_flood_bind_group(
+            device: &wgpu::Device,
+            layout: &wgpu::BindGroupLayout,
+            seeds_view: &wgpu::TextureView,
+            params: &PassUniforms<JumpFloodParams>,
+        ) -> wgpu::BindGroup {
+
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("jump flood bind group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(seeds_view),
+                },
+                // Step uniform, one slot per pass (dynamic offset)
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: params.binding(),
+                },
+            ],
+        })
+    }
+
+    // / Compute blur: input loaded texel by texel, output written as a Rgba16Float storage texture
+    pub fn create_blur_compute_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("blur compute bind group layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
                     binding: 0,
-                    visibility: wgpu::ShaderStages::VERTEX,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::StorageTexture {
+                        access: wgpu::StorageTextureAccess::WriteOnly,
+                        format: wgpu::TextureFormat::Rgba16Float,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::COMPUTE,
                     ty: wgpu::BindingType::Buffer {
                         ty: wgpu::BufferBindingType::Uniform,
-                        has_dynamic_offset: false,
-                        min_binding_size: Some(
-                            std::num::NonZeroU64::new(
-                                std::mem::size_of::<MirrorPlaneUniform>() as u64
-                            )
-                            .unwrap(),
-                        ),
+                        has_dynamic_offset: true,
+                        min_binding_size: PassUniforms::<BlurParams>::min_binding_size(),
                     },
                     count: None,
-                }],
-            });
-
-        let mirror_plane_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            label: Some("mirror_plane_bind_group"),
-            layout: &mirror_plane_bind_group_layout,
-            entries: &[wgpu::BindGroupEntry {
-                binding: 0,
-                resource: mirror_plane_buffer.as_entire_binding(),
-            }],
-        });
-
-        (mirror_plane_bind_group_layout, mirror_plane_bind_group)
-    }
-
-    pub fn mirror_plane_buffer(&self, device: &wgpu::Device) -> wgpu::Buffer {
-        device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Mirror Plane Buffer"),
-            contents: bytemuck::cast_slice(&[*self]),
-            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                },
+            ],
         })
     }
-}
-
-
-
-#[repr(C)]
-#[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable, Default)]
-pub struct BlurParams {
-   pub direction: [f32; 2],
-   pub _padding: [f32; 2], // 8 extra bytes → total 16 bytes
-   
-}
-
 
-impl BlurParams {
+    pub fn create_blur_compute_bind_group(
+            device: &wgpu::Device,
+            layout: &wgpu::BindGroupLayout,
+            input_view: &wgpu::TextureView,
+            output_view: &wgpu::TextureView,
+            blur_params: &PassUniforms<BlurParams>,
+        ) -> wgpu::BindGroup {
 
-    pub fn new(x:f32, y: f32) -> Self {
-        Self { direction: [x,y], _padding: [0.0; 2] }
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("blur compute bind group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(input_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(output_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: blur_params.binding(),
+                },
+            ],
+        })
     }
 
-    pub fn create_blurparams_uniform_buffer(&self, device: &wgpu::Device) -> wgpu::Buffer {
-            
-            device.create_buffer(&wgpu::BufferDescriptor {
-                label: Some("blur params buffer"),
-                size: std::mem::size_of::<BlurParams>() as u64,
-                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
-                mapped_at_creation: false,
-            })
-    }
-}
+    // / Histogram auto exposure: frame loaded texel by texel, histogram and adapted luminance
+    // / in storage buffers, exposure written as a 1x1 Rgba16Float storage texture
+    pub fn create_auto_exposure_compute_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
 
-    pub fn create_blur_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+        let storage_buffer = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Storage { read_only: false },
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
 
         device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
-            label: Some("blur bind group layout"),
+            label: Some("auto exposure compute bind group layout"),
             entries: &[
-                // Input texture
                 wgpu::BindGroupLayoutEntry {
                     binding: 0,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    visibility: wgpu::ShaderStages::COMPUTE,
                     ty: wgpu::BindingType::Texture {
                         multisampled: false,
                         view_dimension: wgpu::TextureViewDimension::D2,
-                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
                     },
                     count: None,
                 },
-                // Sampler
+                storage_buffer(1),
                 wgpu::BindGroupLayoutEntry {
-                    binding: 1,
-                    visibility: wgpu::ShaderStages::FRAGMENT,
-                    ty: wgpu::BindingType::Sampler(
-                        wgpu::SamplerBindingType::Filtering
-                    ),
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
                     count: None,
                 },
-                // Blur params
                 wgpu::BindGroupLayoutEntry {
+                    binding: 3,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::StorageTexture {
+                        access: wgpu::StorageTextureAccess::WriteOnly,
+                        format: wgpu::TextureFormat::Rgba16Float,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                    },
+                    count: None,
+                },
+                storage_buffer(4),
+            ],
+        })
+    }
+
+    pub fn create_auto_exposure_compute_bind_group(
+            device: &wgpu::Device,
+            layout: &wgpu::BindGroupLayout,
+            hdr_view: &wgpu::TextureView,
+            histogram_buffer: &wgpu::Buffer,
+            params_buffer: &wgpu::Buffer,
+            exposure_view: &wgpu::TextureView,
+            adapted_buffer: &wgpu::Buffer,
+        ) -> wgpu::BindGroup {
+
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("auto exposure compute bind group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(hdr_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: histogram_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
                     binding: 2,
+                    resource: params_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(exposure_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: adapted_buffer.as_entire_binding(),
+                },
+            ],
+        })
+    }
+
+    // / Downsample auto exposure: one mip (or the frame) + sampler per pass, then the
+    // / average, the previous exposure and the params for the 1x1 adapt pass
+    pub fn create_auto_exposure_bind_group_layout(device: &wgpu::Device, is_adapt: bool) -> wgpu::BindGroupLayout {
+
+        let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                sample_type: wgpu::TextureSampleType::Float { filterable: true },
+            },
+            count: None,
+        };
+
+        let entries = if is_adapt {
+            vec![
+                texture_entry(2),
+                texture_entry(3),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 4,
                     visibility: wgpu::ShaderStages::FRAGMENT,
                     ty: wgpu::BindingType::Buffer {
                         ty: wgpu::BufferBindingType::Uniform,
@@ -230,36 +1097,71 @@ impl BlurParams {
                     },
                     count: None,
                 },
-            ],
-        })
-
+            ]
+        } else {
+            vec![
+                texture_entry(0),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ]
+        };
 
+        device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some(if is_adapt { "exposure adapt bind group layout" } else { "luminance downsample bind group layout" }),
+            entries: &entries,
+        })
     }
 
-    pub fn create_blur_bind_group(
-            device: &wgpu::Device, 
-            blur_bind_group_layout: &wgpu::BindGroupLayout,
-            texture_view: &wgpu::TextureView,
+    pub fn create_luminance_downsample_bind_group(
+            device: &wgpu::Device,
+            layout: &wgpu::BindGroupLayout,
+            source_view: &wgpu::TextureView,
             linear_sampler: &wgpu::Sampler,
-            blur_params_buffer: &wgpu::Buffer,
         ) -> wgpu::BindGroup {
 
         device.create_bind_group(&wgpu::BindGroupDescriptor {
-        label: Some("blur bind group"),
-            layout: &blur_bind_group_layout,
+            label: Some("luminance downsample bind group"),
+            layout,
             entries: &[
                 wgpu::BindGroupEntry {
                     binding: 0,
-                    resource: wgpu::BindingResource::TextureView(&texture_view),
+                    resource: wgpu::BindingResource::TextureView(source_view),
                 },
                 wgpu::BindGroupEntry {
                     binding: 1,
-                    resource: wgpu::BindingResource::Sampler(&linear_sampler),
+                    resource: wgpu::BindingResource::Sampler(linear_sampler),
                 },
-                // Blur params uniform
+            ],
+        })
+    }
+
+    pub fn create_exposure_adapt_bind_group(
+            device: &wgpu::Device,
+            layout: &wgpu::BindGroupLayout,
+            average_view: &wgpu::TextureView,
+            previous_view: &wgpu::TextureView,
+            params_buffer: &wgpu::Buffer,
+        ) -> wgpu::BindGroup {
+
+        device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("exposure adapt bind group"),
+            layout,
+            entries: &[
                 wgpu::BindGroupEntry {
                     binding: 2,
-                    resource: blur_params_buffer.as_entire_binding(),
+                    resource: wgpu::BindingResource::TextureView(average_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: wgpu::BindingResource::TextureView(previous_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 4,
+                    resource: params_buffer.as_entire_binding(),
                 },
             ],
         })
@@ -407,6 +1309,17 @@ impl BlurParams {
                     ),
                     count: None,
                 },
+                // Auto exposure (1x1)
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        multisampled: false,
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                    },
+                    count: None,
+                },
             ],
         })
 
@@ -417,6 +1330,7 @@ impl BlurParams {
              tone_map_bind_group_layout: &wgpu::BindGroupLayout,
              composed_texture_view: &wgpu::TextureView,
              linear_sampler: &wgpu::Sampler,      
+             exposure_view: &wgpu::TextureView,
     ) -> wgpu::BindGroup {
 
     device.create_bind_group(&wgpu::BindGroupDescriptor {
@@ -433,11 +1347,121 @@ impl BlurParams {
                     binding: 1,
                     resource: wgpu::BindingResource::Sampler(&linear_sampler),
                 },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::TextureView(exposure_view),
+                },
             ],
         })
 
     }
 
+// /
+// / C O L O R   G R A D E   B I N D G R O U P
+
+// / Tone mapped frame, read texel by texel (the LUT is group 1, see ColorGradeParams)
+pub fn create_color_grade_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some("Color Grade Bind Group Layout"),
+        entries: &[wgpu::BindGroupLayoutEntry {
+            binding: 0,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                multisampled: false,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+            },
+            count: None,
+        }],
+    })
+}
+
+pub fn create_color_grade_bind_group(
+    device: &wgpu::Device,
+    layout: &wgpu::BindGroupLayout,
+    tone_mapped_view: &wgpu::TextureView,
+) -> wgpu::BindGroup {
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        label: Some("Color Grade Bind Group"),
+        layout,
+        entries: &[wgpu::BindGroupEntry {
+            binding: 0,
+            resource: wgpu::BindingResource::TextureView(tone_mapped_view),
+        }],
+    })
+}
+
+// /
+// / B L O O M   B I N D G R O U P 
+
+// / Source mip + sampler. The prefilter also reads the scene (bright-pass).
+pub fn create_bloom_bind_group_layout(device: &wgpu::Device, is_prefilter: bool) -> wgpu::BindGroupLayout {
+
+    let texture_entry = |binding| wgpu::BindGroupLayoutEntry {
+        binding,
+        visibility: wgpu::ShaderStages::FRAGMENT,
+        ty: wgpu::BindingType::Texture {
+            multisampled: false,
+            view_dimension: wgpu::TextureViewDimension::D2,
+            sample_type: wgpu::TextureSampleType::Float { filterable: true },
+        },
+        count: None,
+    };
+
+    let mut entries = vec![
+        // Binding 0: source (outline or previous mip)
+        texture_entry(0),
+        // Binding 1: linear sampler, the taps rely on bilinear filtering
+        wgpu::BindGroupLayoutEntry {
+            binding: 1,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+            count: None,
+        },
+    ];
+    if is_prefilter {
+        // Binding 2: scene
+        entries.push(texture_entry(2));
+    }
+
+    device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+        label: Some(if is_prefilter { "bloom prefilter bind group layout" } else { "bloom bind group layout" }),
+        entries: &entries,
+    })
+}
+
+pub fn create_bloom_bind_group(
+    device: &wgpu::Device,
+    bloom_bind_group_layout: &wgpu::BindGroupLayout,
+    input_view: &wgpu::TextureView,
+    sampler: &wgpu::Sampler,
+    scene_view: Option<&wgpu::TextureView>,
+) -> wgpu::BindGroup {
+
+    let mut entries = vec![
+        wgpu::BindGroupEntry {
+            binding: 0,
+            resource: wgpu::BindingResource::TextureView(input_view),
+        },
+        wgpu::BindGroupEntry {
+            binding: 1,
+            resource: wgpu::BindingResource::Sampler(sampler),
+        },
+    ];
+    if let Some(scene_view) = scene_view {
+        entries.push(wgpu::BindGroupEntry {
+            binding: 2,
+            resource: wgpu::BindingResource::TextureView(scene_view),
+        });
+    }
+
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout: bloom_bind_group_layout,
+        entries: &entries,
+        label: Some("bloom bind group"),
+    })
+}
+
 // /
 // / E D G E   B I N D G R O U P 
 
@@ -472,8 +1496,8 @@ pub fn create_edge_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLa
                         ty: wgpu::BindingType::Texture {
                             multisampled: false,
                             view_dimension: wgpu::TextureViewDimension::D2,
-                            // Depth textures have a unique sample type
-                            sample_type: wgpu::TextureSampleType::Depth,
+                            // Depth32Float read as unfilterable float (works on the GL backend too)
+                            sample_type: wgpu::TextureSampleType::Float { filterable: false },
                         },
                         count: None,
                     },
@@ -488,11 +1512,34 @@ pub fn create_edge_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLa
                         },
                         count: None,
                     },
+                    // Binding 4: Edge Params (EdgeParams)
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 4,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    // Binding 5: Object ID Texture (read texel by texel)
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 5,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Uint,
+                        },
+                        count: None,
+                    },
                 ],
             })
 }
 
 // Bind group 
+#[allow(clippy::too_many_arguments)]
 pub fn create_edge_bind_group(
     device: &wgpu::Device,
     edge_bind_group_layout: &wgpu::BindGroupLayout,
@@ -500,6 +1547,8 @@ pub fn create_edge_bind_group(
     edge_sampler: &wgpu::Sampler,
     depth_view: &wgpu::TextureView,
     normal_view: &wgpu::TextureView,
+    edge_params_buffer: &wgpu::Buffer,
+    object_id_view: &wgpu::TextureView,
 ) -> wgpu::BindGroup {
 
     device.create_bind_group(&wgpu::BindGroupDescriptor {
@@ -525,9 +1574,61 @@ pub fn create_edge_bind_group(
                 binding: 3,
                 resource: wgpu::BindingResource::TextureView(&normal_view),
             },
+            // Binding 4: The Edge Params
+            wgpu::BindGroupEntry {
+                binding: 4,
+                resource: edge_params_buffer.as_entire_binding(),
+            },
+            // Binding 5: The Object ID Texture
+            wgpu::BindGroupEntry {
+                binding: 5,
+                resource: wgpu::BindingResource::TextureView(object_id_view),
+            },
         ],
         label: Some("Edge Detection Bind Group"),
     })
 }
 
 
+
+// /
+// / D E P T H   D E B U G   B I N D G R O U P 
+
+
+pub fn create_depth_debug_bind_group_layout(device: &wgpu::Device) -> wgpu::BindGroupLayout {
+
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Depth Debug Bind Group Layout"),
+                entries: &[
+                    // Binding 0: Depth Texture, loaded texel by texel (no sampler)
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            multisampled: false,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                        },
+                        count: None,
+                    },
+                ],
+            })
+}
+
+pub fn create_depth_debug_bind_group(
+    device: &wgpu::Device,
+    depth_debug_bind_group_layout: &wgpu::BindGroupLayout,
+    depth_view: &wgpu::TextureView,
+) -> wgpu::BindGroup {
+
+    device.create_bind_group(&wgpu::BindGroupDescriptor {
+        layout: depth_debug_bind_group_layout,
+        entries: &[
+            wgpu::BindGroupEntry {
+                binding: 0,
+                resource: wgpu::BindingResource::TextureView(depth_view),
+            },
+        ],
+        label: Some("Depth Debug Bind Group"),
+    })
+}
@@ -1,10 +1,20 @@
 pub mod app;
 pub use app::App;
 pub mod camera;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod cli;
+pub mod config;
 pub mod depth_stencil;
 pub mod extra;
 pub mod model;
+pub mod passes;
 pub mod pipeline;
+pub mod profiler;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod readback;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod recorder;
+pub mod render_graph;
 pub mod resources;
 pub mod state;
 pub mod texture;
@@ -30,11 +30,32 @@ pub fn run() -> anyhow::Result<()> {
         console_log::init_with_level(log::Level::Info).unwrap_throw();
     }
 
+    #[cfg(not(target_arch = "wasm32"))]
+    let mut app = {
+        use clap::Parser;
+
+        let cli = glow::cli::Cli::parse();
+        let glow_config = pollster::block_on(cli.load_config())?;
+
+        if let Some(recording) = cli.recording(&glow_config) {
+            return pollster::block_on(glow::recorder::record_turntable(glow_config, &recording)).map(|_| ());
+        }
+
+        if let Some(path) = &cli.screenshot {
+            return pollster::block_on(glow::cli::render_screenshot(
+                glow_config,
+                cli.frames.unwrap_or(1),
+                cli.target,
+                path,
+            ));
+        }
+
+        App::with_config(glow_config)
+    };
+
     let event_loop = EventLoop::with_user_event().build()?;
-    let mut app = App::new(
-        #[cfg(target_arch = "wasm32")]
-        &event_loop,
-    );
+    #[cfg(target_arch = "wasm32")]
+    let mut app = App::new(&event_loop);
     event_loop.run_app(&mut app)?;
 
     Ok(())
@@ -1,7 +1,7 @@
 use cgmath::{InnerSpace, Matrix4, Rotation3, Zero};
 use wgpu::util::DeviceExt;
 
-use crate::texture;
+use crate::{config::{DEFAULT_HIGHLIGHT_GROUP, GlowStyle, InstanceConfig}, extra::GlowStyleUniform, texture};
 use std::ops::Range;
 
 // model.rs
@@ -92,6 +92,10 @@ const SPACE_BETWEEN: f32 = 3.0;
 pub struct Instance {
     position: cgmath::Vector3<f32>,
     rotation: cgmath::Quaternion<f32>,
+    // Stencil reference of its highlight group (0: no outline)
+    pub group: u8,
+    // None: the outline of its group
+    pub glow: Option<GlowStyle>,
 }
 
 impl Instance {
@@ -100,6 +104,7 @@ impl Instance {
             model: (Matrix4::from_translation(self.position)
                 * Matrix4::from(self.rotation))
             .into(),
+            glow: self.glow_raw(),
         }
     }
     pub fn to_raw_with_scale(&self, scale: f32) -> InstanceRaw {
@@ -108,8 +113,28 @@ impl Instance {
                 * Matrix4::from(self.rotation)
                 * Matrix4::from_nonuniform_scale(scale, scale, 1.0))
             .into(),
+            glow: self.glow_raw(),
         }
     }
+
+    // / Instance without its own style: the one of its group (None for the model one)
+    pub fn to_raw_in_group(&self, group_glow: Option<&GlowStyle>) -> InstanceRaw {
+        let mut raw = self.to_raw();
+        if self.glow.is_none() {
+            raw.glow = self.with_group(group_glow.map_or(GlowStyleUniform::unset(), GlowStyleUniform::new));
+        }
+        raw
+    }
+
+    // / Own style (or unset), the highlight group in params.w
+    fn glow_raw(&self) -> GlowStyleUniform {
+        self.with_group(self.glow.as_ref().map_or(GlowStyleUniform::unset(), GlowStyleUniform::new))
+    }
+
+    fn with_group(&self, mut glow: GlowStyleUniform) -> GlowStyleUniform {
+        glow.params[3] = self.group as f32;
+        glow
+    }
     pub fn generate_instances() -> Vec<Instance> {
         (0..NUM_INSTANCES_PER_ROW)
             .flat_map(|z| {
@@ -128,7 +153,7 @@ impl Instance {
                         cgmath::Quaternion::from_axis_angle(position.normalize(), cgmath::Deg(45.0))
                     };
 
-                    Instance { position, rotation }
+                    Instance { position, rotation, group: DEFAULT_HIGHLIGHT_GROUP, glow: None }
                 })
             })
             .collect::<Vec<_>>()
@@ -140,7 +165,12 @@ impl Instance {
         let rotation =
             cgmath::Quaternion::from_axis_angle(cgmath::Vector3::unit_y(), cgmath::Deg(angle));
 
-        Instance { position, rotation }
+        Instance { position, rotation, group: DEFAULT_HIGHLIGHT_GROUP, glow: None }
+    }
+
+    pub fn from_config(config: &InstanceConfig, group: u8) -> Instance {
+        let [x, y, z] = config.position;
+        Instance { group, glow: config.glow, ..Self::generate_instance(x, y, z, config.angle) }
     }
 
     pub fn translation(&self) -> cgmath::Matrix4<f32> {
@@ -156,10 +186,51 @@ impl Instance {
     }
 }
 
+// / Runs of consecutive instances in the same highlight group: (stencil reference, instances)
+pub fn highlight_runs(instances: &[Instance]) -> Vec<(u8, Range<u32>)> {
+    let mut runs: Vec<(u8, Range<u32>)> = Vec::new();
+    for (i, instance) in instances.iter().enumerate() {
+        let i = i as u32;
+        match runs.last_mut() {
+            Some((group, range)) if *group == instance.group => range.end = i + 1,
+            _ => runs.push((instance.group, i..i + 1)),
+        }
+    }
+    runs
+}
+
+// / Bits of an object ID telling the meshes of an instance apart, the instance + 1 takes
+// / the others (0 is the background)
+pub const OBJECT_ID_MESH_BITS: u32 = 8;
+
+// / Meshes with their own object ID: the next ones share the IDs of the first ones
+pub const MAX_OBJECT_ID_MESHES: u32 = 1 << OBJECT_ID_MESH_BITS;
+
+// / What a pixel of the object ID target shows (shader.wgsl)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ObjectId {
+    pub instance: u32,
+    pub mesh: u32,
+}
+
+impl ObjectId {
+    // / None for the background
+    pub fn from_raw(raw: u32) -> Option<Self> {
+        let instance = (raw >> OBJECT_ID_MESH_BITS).checked_sub(1)?;
+        Some(Self { instance, mesh: raw & (MAX_OBJECT_ID_MESHES - 1) })
+    }
+
+    pub fn to_raw(&self) -> u32 {
+        ((self.instance + 1) << OBJECT_ID_MESH_BITS) | (self.mesh % MAX_OBJECT_ID_MESHES)
+    }
+}
+
 #[repr(C)]
 #[derive(Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct InstanceRaw {
     model: [[f32; 4]; 4],
+    // Outline override, params.z = 0 when the instance uses the model one. params.w: highlight group
+    glow: GlowStyleUniform,
 }
 
 impl InstanceRaw {
@@ -196,6 +267,17 @@ impl InstanceRaw {
                     shader_location: 8,
                     format: wgpu::VertexFormat::Float32x4,
                 },
+                // Glow style: color, params (expand.wgsl)
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 16]>() as wgpu::BufferAddress,
+                    shader_location: 9,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
+                wgpu::VertexAttribute {
+                    offset: mem::size_of::<[f32; 20]>() as wgpu::BufferAddress,
+                    shader_location: 10,
+                    format: wgpu::VertexFormat::Float32x4,
+                },
             ],
         }
     }
@@ -208,6 +290,7 @@ pub fn create_instance_buffer(
     device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
         label: Some("Instance Buffer"),
         contents: bytemuck::cast_slice(instance_data),
-        usage: wgpu::BufferUsages::VERTEX,
+        // COPY_DST: per-instance glow styles change at runtime
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
     })
 }
@@ -0,0 +1,1898 @@
+// /
+// /  G L O W   P A S S E S
+// /
+// / The glow pipeline as render graph nodes:
+// / depth prepass -> stencil mask -> outline -> blur H -> blur V -> scene -> edge -> composite -> tone map
+// / With the mip-chain bloom, blur H + blur V are replaced by a bloom node reading the outline and the scene.
+// / With the jump flood outline mode, the outline node is replaced by a jump flood node reading the stencil mask.
+// / The scene writes the object IDs the edge pass reads, from an object ID node of its own when multisampled.
+// / With auto exposure (HDR), an auto exposure node meters the composite into a 1x1 target the tone map reads.
+// / With a color LUT, the tone map writes an intermediate target a color grade node reads.
+
+use crate::{
+    config::{AUTO_EXPOSURE_BINS, AutoExposure, BloomMode, BlurPath, EffectsConfig, JUMP_FLOOD_FORMAT, MAX_BLUR_ITERATIONS, NO_HIGHLIGHT, OBJECT_ID_FORMAT, OutlineMode},
+    extra::{
+        AutoExposureParams, BlurParams, EdgeParams, JumpFloodParams, ObjectIdParams, PassUniforms, create_object_id_bind_group, create_bloom_bind_group, create_bloom_bind_group_layout,
+        create_blur_bind_group, create_blur_bind_group_layout, create_blur_compute_bind_group,
+        create_blur_compute_bind_group_layout, create_composite_bind_group,
+        create_composite_bind_group_layout, create_edge_bind_group, create_edge_bind_group_layout,
+        create_jump_flood_bind_group, create_jump_flood_bind_group_layout, create_scene_depth_bind_group,
+        create_scene_depth_bind_group_layout,
+        create_linear_sampler, create_tone_map_bind_group, create_tone_map_bind_group_layout,
+        create_auto_exposure_bind_group_layout, create_auto_exposure_compute_bind_group,
+        create_auto_exposure_compute_bind_group_layout, create_exposure_adapt_bind_group,
+        create_luminance_downsample_bind_group, create_color_grade_bind_group,
+        create_color_grade_bind_group_layout,
+    },
+    model::{DrawModel, MAX_OBJECT_ID_MESHES},
+    pipeline::Pipeline,
+    render_graph::{FrameContext, OUTPUT, RenderGraph, RenderNode, ResourceId, TargetDesc, Targets},
+    texture::Texture,
+};
+
+// / Targets of the glow graph
+pub const PREPASS_DEPTH: ResourceId = "prepass_depth";
+// Depth of every mesh, for the hidden parts of the extruded outlines
+pub const OCCLUSION_DEPTH: ResourceId = "occlusion_depth";
+pub const DEPTH_STENCIL: ResourceId = "depth_stencil";
+// Single sampled stencil mask of the jump flood when multisampling
+pub const JUMP_FLOOD_STENCIL: ResourceId = "jump_flood_stencil";
+// Ping-pong seeds of the jump flood (nearest mask pixel and its group)
+pub const JUMP_FLOOD_SEEDS: [ResourceId; 2] = ["jump_flood_seeds_0", "jump_flood_seeds_1"];
+pub const OUTLINE_MSAA: ResourceId = "outline_msaa";
+pub const OUTLINE: ResourceId = "outline";
+pub const BLUR_INTERMEDIATE: ResourceId = "blur_intermediate";
+pub const BLOOM: ResourceId = "bloom";
+pub const SCENE_MSAA: ResourceId = "scene_msaa";
+pub const SCENE: ResourceId = "scene";
+pub const NORMAL_MSAA: ResourceId = "normal_msaa";
+pub const NORMAL: ResourceId = "normal";
+pub const OBJECT_ID: ResourceId = "object_id";
+pub const OBJECT_ID_DEPTH: ResourceId = "object_id_depth";
+pub const EDGE: ResourceId = "edge";
+pub const COMPOSITE: ResourceId = "composite";
+// 1x1 metered exposure (auto_exposure*.wgsl)
+pub const EXPOSURE: ResourceId = "exposure";
+// Tone mapped frame before color grading (surface format)
+pub const TONE_MAPPED: ResourceId = "tone_mapped";
+
+// / Node names of each blur iteration (horizontal, vertical): graph edits and timings tell them apart
+const BLUR_PASS_NAMES: [(&str, &str); MAX_BLUR_ITERATIONS as usize] = [
+    ("blur horizontal", "blur vertical"),
+    ("blur horizontal 2", "blur vertical 2"),
+    ("blur horizontal 3", "blur vertical 3"),
+    ("blur horizontal 4", "blur vertical 4"),
+    ("blur horizontal 5", "blur vertical 5"),
+    ("blur horizontal 6", "blur vertical 6"),
+    ("blur horizontal 7", "blur vertical 7"),
+    ("blur horizontal 8", "blur vertical 8"),
+];
+
+// / Bind group layouts of the scene data, shared by the mesh passes (and the tone map).
+pub struct SceneLayouts {
+    pub texture: wgpu::BindGroupLayout,
+    pub camera: wgpu::BindGroupLayout,
+    pub spin: wgpu::BindGroupLayout,
+    pub glow: wgpu::BindGroupLayout,
+    // Mesh of each scene draw (ObjectIdParams)
+    pub object_id: wgpu::BindGroupLayout,
+    // Operator, exposure and white point of the tone map pass (ToneMapParams)
+    pub tone_map: wgpu::BindGroupLayout,
+    // LUT of the color grade pass (ColorGradeParams)
+    pub color_lut: wgpu::BindGroupLayout,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct GlowGraphSettings {
+    pub sample_count: u32,
+    pub is_hdr: bool,
+    pub effects: EffectsConfig,
+    // A color LUT is in use: color grade pass after the tone map
+    pub is_color_graded: bool,
+}
+
+// / Build the full glow graph for the surface `config`.
+pub fn build_glow_graph(
+    device: &wgpu::Device,
+    config: &wgpu::SurfaceConfiguration,
+    layouts: &SceneLayouts,
+    settings: GlowGraphSettings,
+) -> anyhow::Result<RenderGraph> {
+
+    let mut graph = RenderGraph::new();
+
+    graph
+        .add_node(DepthPrepassNode::new(device, layouts, "depth prepass", PREPASS_DEPTH, false)?)
+        .add_node(StencilMaskNode::new(device, layouts, DEPTH_STENCIL, settings.sample_count)?);
+
+    match settings.effects.outline_mode {
+        OutlineMode::Extrude => graph
+            .add_node(DepthPrepassNode::new(device, layouts, "occlusion depth", OCCLUSION_DEPTH, true)?)
+            .add_node(OutlineNode::new(device, config, layouts, settings)?),
+        // The seeds are read texel by texel: from a single sampled mask
+        OutlineMode::JumpFlood if settings.sample_count > 1 => graph
+            .add_node(StencilMaskNode::new(device, layouts, JUMP_FLOOD_STENCIL, 1)?)
+            .add_node(JumpFloodNode::new(device, config, layouts, settings, JUMP_FLOOD_STENCIL)?),
+        OutlineMode::JumpFlood => graph.add_node(JumpFloodNode::new(device, config, layouts, settings, DEPTH_STENCIL)?),
+    };
+
+    // Mesh part of the object IDs: one params slot per mesh
+    let object_ids = PassUniforms::new(device, "object id params", MAX_OBJECT_ID_MESHES);
+
+    // The mip chain reads the scene too: added after it
+    match settings.effects.bloom {
+        BloomMode::Separable => {
+            // One params slot per pass. Further iterations blur the bloom target again
+            let iterations = settings.effects.blur_iterations;
+            let params = PassUniforms::new(device, "blur params", 2 * iterations);
+            for (iteration, (horizontal, vertical)) in BLUR_PASS_NAMES.into_iter().take(iterations as usize).enumerate() {
+                let iteration = iteration as u32;
+                let input = if iteration == 0 { OUTLINE } else { BLOOM };
+                let slot = 2 * iteration;
+                match settings.effects.blur_path {
+                    BlurPath::Render => graph
+                        .add_node(BlurNode::new(device, config, settings, horizontal, input, BLUR_INTERMEDIATE, [1.0, 0.0], &params, slot)?)
+                        .add_node(BlurNode::new(device, config, settings, vertical, BLUR_INTERMEDIATE, BLOOM, [0.0, 1.0], &params, slot + 1)?),
+                    BlurPath::Compute => graph
+                        .add_node(ComputeBlurNode::new(device, settings, horizontal, input, BLUR_INTERMEDIATE, [1.0, 0.0], &params, slot))
+                        .add_node(ComputeBlurNode::new(device, settings, vertical, BLUR_INTERMEDIATE, BLOOM, [0.0, 1.0], &params, slot + 1)),
+                };
+            }
+            graph.add_node(SceneNode::new(device, config, layouts, settings, &object_ids)?)
+        }
+        BloomMode::MipChain => graph
+            .add_node(SceneNode::new(device, config, layouts, settings, &object_ids)?)
+            .add_node(BloomNode::new(device, config, settings)?),
+    };
+
+    if settings.sample_count > 1 {
+        graph.add_node(ObjectIdNode::new(device, layouts, &object_ids)?);
+    }
+
+    graph
+        .add_node(EdgeNode::new(device, config, settings)?)
+        .add_node(CompositeNode::new(device, config, settings)?);
+
+    // Nothing to meter in SDR: the tone map is a pass-through
+    match (settings.is_hdr, settings.effects.auto_exposure) {
+        (false, _) | (_, AutoExposure::Off) => &mut graph,
+        (true, AutoExposure::Histogram) => graph.add_node(HistogramExposureNode::new(device, settings)),
+        (true, AutoExposure::Downsample) => graph.add_node(DownsampleExposureNode::new(device, settings)?),
+    }
+    .add_node(ToneMapNode::new(device, config, layouts, settings)?);
+
+    if settings.is_color_graded {
+        graph.add_node(ColorGradeNode::new(device, config, layouts)?);
+    }
+
+    graph.compile()?;
+
+    Ok(graph)
+}
+
+fn color_format(config: &wgpu::SurfaceConfiguration, settings: GlowGraphSettings) -> wgpu::TextureFormat {
+    if settings.is_hdr { wgpu::TextureFormat::Rgba16Float } else { config.format }
+}
+
+// / Color attachment drawing into `resolved`, through `msaa` + resolve when multisampled.
+fn color_attachment<'a>(
+    ctx: &'a FrameContext,
+    sample_count: u32,
+    msaa: ResourceId,
+    resolved: ResourceId,
+    load: wgpu::LoadOp<wgpu::Color>,
+    msaa_store: wgpu::StoreOp,
+) -> anyhow::Result<wgpu::RenderPassColorAttachment<'a>> {
+    if sample_count == 1 {
+        Ok(wgpu::RenderPassColorAttachment {
+            view: ctx.view(resolved)?,
+            depth_slice: None,
+            resolve_target: None,
+            ops: wgpu::Operations { load, store: wgpu::StoreOp::Store },
+        })
+    } else {
+        Ok(wgpu::RenderPassColorAttachment {
+            view: ctx.view(msaa)?,
+            depth_slice: None,
+            resolve_target: Some(ctx.view(resolved)?),
+            ops: wgpu::Operations { load, store: msaa_store },
+        })
+    }
+}
+
+// / Timestamps of a node recording several passes: begin on the first one, end on the last one.
+fn split_timestamp_writes<'a>(
+    ctx: &FrameContext<'a>,
+) -> (Option<wgpu::RenderPassTimestampWrites<'a>>, Option<wgpu::RenderPassTimestampWrites<'a>>) {
+    let Some(writes) = &ctx.timestamp_writes else {
+        return (None, None);
+    };
+
+    (
+        Some(wgpu::RenderPassTimestampWrites { end_of_pass_write_index: None, ..writes.clone() }),
+        Some(wgpu::RenderPassTimestampWrites { beginning_of_pass_write_index: None, ..writes.clone() }),
+    )
+}
+
+// / Single sample fullscreen attachment, cleared to black.
+fn fullscreen_attachment<'a>(view: &'a wgpu::TextureView) -> wgpu::RenderPassColorAttachment<'a> {
+    wgpu::RenderPassColorAttachment {
+        view,
+        depth_slice: None,
+        resolve_target: None,
+        ops: wgpu::Operations {
+            load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+            store: wgpu::StoreOp::Store,
+        },
+    }
+}
+
+// /
+// / P A R A L L E L  P A S S - D E P T H   O N L Y
+
+pub struct DepthPrepassNode {
+    pipeline: wgpu::RenderPipeline,
+    name: &'static str,
+    target: ResourceId,
+    // Every mesh, otherwise the first one only
+    is_whole_model: bool,
+}
+
+impl DepthPrepassNode {
+    pub fn new(
+        device: &wgpu::Device,
+        layouts: &SceneLayouts,
+        name: &'static str,
+        target: ResourceId,
+        is_whole_model: bool,
+    ) -> anyhow::Result<Self> {
+        let pipeline = Pipeline::parallel_depth_pipeline(device, &layouts.camera, &layouts.spin)?.pipeline;
+        Ok(Self { pipeline, name, target, is_whole_model })
+    }
+}
+
+impl RenderNode for DepthPrepassNode {
+    fn name(&self) -> &'static str { self.name }
+
+    fn reads(&self) -> Vec<ResourceId> { vec![] }
+
+    fn writes(&self) -> Vec<(ResourceId, TargetDesc)> {
+        vec![(self.target, TargetDesc::new(Texture::DEPTH_FORMAT, 1))]
+    }
+
+    fn record(&self, encoder: &mut wgpu::CommandEncoder, ctx: &FrameContext) -> anyhow::Result<()> {
+        let mut parallel_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some(self.name),
+            color_attachments: &[],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: ctx.view(self.target)?,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            occlusion_query_set: None,
+            timestamp_writes: ctx.timestamp_writes.clone(),
+            multiview_mask: None,
+        });
+
+        parallel_pass.set_pipeline(&self.pipeline);
+        parallel_pass.set_bind_group(0, ctx.scene.camera_bind_group, &[]);
+        parallel_pass.set_bind_group(1, ctx.scene.spin_bind_group, &[]);
+        parallel_pass.set_vertex_buffer(1, ctx.scene.instance_buffer.slice(..));
+        let meshes = if self.is_whole_model { &ctx.scene.model.meshes[..] } else { &ctx.scene.model.meshes[..1] };
+        for mesh in meshes {
+            parallel_pass.draw_mesh_instanced(mesh, 0..ctx.scene.instance_count);
+        }
+
+        Ok(())
+    }
+}
+
+// /
+// / S T E N C I L   P A S S
+
+pub struct StencilMaskNode {
+    pipeline: wgpu::RenderPipeline,
+    target: ResourceId,
+    sample_count: u32,
+}
+
+impl StencilMaskNode {
+    pub fn new(device: &wgpu::Device, layouts: &SceneLayouts, target: ResourceId, sample_count: u32) -> anyhow::Result<Self> {
+        let pipeline = Pipeline::mask_render_pipeline(device, &layouts.camera, &layouts.spin, sample_count)?.pipeline;
+        Ok(Self { pipeline, target, sample_count })
+    }
+}
+
+impl RenderNode for StencilMaskNode {
+    fn name(&self) -> &'static str {
+        if self.target == DEPTH_STENCIL { "stencil mask" } else { "jump flood stencil mask" }
+    }
+
+    fn reads(&self) -> Vec<ResourceId> { vec![] }
+
+    fn writes(&self) -> Vec<(ResourceId, TargetDesc)> {
+        vec![(self.target, TargetDesc::new(Texture::DEPTH_STENCIL_FORMAT, self.sample_count))]
+    }
+
+    fn record(&self, encoder: &mut wgpu::CommandEncoder, ctx: &FrameContext) -> anyhow::Result<()> {
+        let mut stencil_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("stencil pass"),
+            color_attachments: &[],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: ctx.view(self.target)?,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.),
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(0),
+                    store: wgpu::StoreOp::Store,
+                }),
+            }),
+            occlusion_query_set: None,
+            timestamp_writes: ctx.timestamp_writes.clone(),
+            multiview_mask: None,
+        });
+
+        stencil_pass.set_pipeline(&self.pipeline);
+        stencil_pass.set_bind_group(0, ctx.scene.camera_bind_group, &[]);
+        stencil_pass.set_bind_group(1, ctx.scene.spin_bind_group, &[]);
+        stencil_pass.set_vertex_buffer(1, ctx.scene.instance_buffer.slice(..));
+
+        // Each highlight group writes its own reference (the nearest object wins)
+        for (group, instances) in highlighted(ctx) {
+            stencil_pass.set_stencil_reference(group as u32);
+            for mesh in &ctx.scene.model.meshes {
+                stencil_pass.draw_mesh_instanced(mesh, instances.clone());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// / Instance runs that get an outline, with their stencil reference
+fn highlighted<'a>(ctx: &'a FrameContext) -> impl Iterator<Item = (u8, std::ops::Range<u32>)> + 'a {
+    ctx.scene
+        .highlight_runs
+        .iter()
+        .filter(|(group, _)| *group != NO_HIGHLIGHT)
+        .cloned()
+}
+
+// /
+// /   O U T L I N E   S T E N C I L
+
+pub struct OutlineNode {
+    pipeline: wgpu::RenderPipeline,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+    scene_depth_bind_group_layout: wgpu::BindGroupLayout,
+    // Occlusion depth, for the hidden parts (highlight group visibility)
+    scene_depth_bind_group: Option<wgpu::BindGroup>,
+}
+
+impl OutlineNode {
+    pub fn new(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        layouts: &SceneLayouts,
+        settings: GlowGraphSettings,
+    ) -> anyhow::Result<Self> {
+        let scene_depth_bind_group_layout = create_scene_depth_bind_group_layout(device);
+        let pipeline = Pipeline::outline_pipeline(
+            device,
+            config,
+            &layouts.camera,
+            &layouts.spin,
+            &layouts.glow,
+            &scene_depth_bind_group_layout,
+            settings.sample_count,
+            settings.is_hdr,
+        )?
+        .pipeline;
+
+        Ok(Self {
+            pipeline,
+            format: color_format(config, settings),
+            sample_count: settings.sample_count,
+            scene_depth_bind_group_layout,
+            scene_depth_bind_group: None,
+        })
+    }
+}
+
+impl RenderNode for OutlineNode {
+    fn name(&self) -> &'static str { "outline" }
+
+    fn reads(&self) -> Vec<ResourceId> { vec![DEPTH_STENCIL, OCCLUSION_DEPTH] }
+
+    fn writes(&self) -> Vec<(ResourceId, TargetDesc)> {
+        let mut writes = vec![
+            (DEPTH_STENCIL, TargetDesc::new(Texture::DEPTH_STENCIL_FORMAT, self.sample_count)),
+            (OUTLINE, TargetDesc::new(self.format, 1)),
+        ];
+        if self.sample_count > 1 {
+            writes.push((OUTLINE_MSAA, TargetDesc::new(self.format, self.sample_count)));
+        }
+        writes
+    }
+
+    fn resize(&mut self, device: &wgpu::Device, targets: &Targets) -> anyhow::Result<()> {
+        self.scene_depth_bind_group = Some(create_scene_depth_bind_group(
+            device,
+            &self.scene_depth_bind_group_layout,
+            targets.view(OCCLUSION_DEPTH)?,
+        ));
+        Ok(())
+    }
+
+    fn record(&self, encoder: &mut wgpu::CommandEncoder, ctx: &FrameContext) -> anyhow::Result<()> {
+        let color_attachment = color_attachment(
+            ctx,
+            self.sample_count,
+            OUTLINE_MSAA,
+            OUTLINE,
+            wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+            wgpu::StoreOp::Store,
+        )?;
+
+        let mut outline_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("outline Pass"),
+            color_attachments: &[Some(color_attachment)],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: ctx.view(DEPTH_STENCIL)?,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.), // <- clear depth again
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Load, // <- keep mask
+                    store: wgpu::StoreOp::Store,
+                }),
+            }),
+            occlusion_query_set: None,
+            timestamp_writes: ctx.timestamp_writes.clone(),
+            multiview_mask: None,
+        });
+
+        outline_pass.set_pipeline(&self.pipeline);
+        outline_pass.set_bind_group(0, ctx.scene.camera_bind_group, &[]);
+        outline_pass.set_bind_group(1, ctx.scene.spin_bind_group, &[]);
+        outline_pass.set_bind_group(2, ctx.scene.glow_bind_group, &[]);
+        outline_pass.set_bind_group(3, &self.scene_depth_bind_group, &[]);
+        outline_pass.set_vertex_buffer(1, ctx.scene.instance_buffer.slice(..));
+
+        // Outside of the group's own mask: groups overlap each other's objects
+        for (group, instances) in highlighted(ctx) {
+            outline_pass.set_stencil_reference(group as u32);
+            for mesh in &ctx.scene.model.meshes {
+                outline_pass.draw_mesh_instanced(mesh, instances.clone());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// /
+// /  J U M P   F L O O D   O U T L I N E
+// /
+// / Replaces the outline node: the stencil mask seeds a texture with pixel coordinates, one
+// / pass per step (GlowConfig.effects.jump_flood_steps) keeps the nearest seed of 9
+// / neighbours, then the distance to it draws an outline of exact pixel width into OUTLINE.
+
+pub struct JumpFloodNode {
+    stencil: ResourceId,
+    format: wgpu::TextureFormat,
+    steps: Vec<u32>,
+    uniforms: PassUniforms<JumpFloodParams>,
+    seed_pipeline: wgpu::RenderPipeline,
+    step_pipeline: wgpu::RenderPipeline,
+    outline_pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    // [i]: reads JUMP_FLOOD_SEEDS[i]
+    bind_groups: Vec<wgpu::BindGroup>,
+}
+
+impl JumpFloodNode {
+    pub fn new(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        layouts: &SceneLayouts,
+        settings: GlowGraphSettings,
+        stencil: ResourceId,
+    ) -> anyhow::Result<Self> {
+        let bind_group_layout = create_jump_flood_bind_group_layout(device);
+        let constants = settings.effects.jump_flood_constants();
+        let format = color_format(config, settings);
+        let steps = settings.effects.jump_flood_steps();
+
+        let pipeline = |layouts: &[&wgpu::BindGroupLayout], entry_point, format, is_stencil_tested| -> anyhow::Result<wgpu::RenderPipeline> {
+            Ok(Pipeline::jump_flood_pipeline(device, layouts, entry_point, format, is_stencil_tested, &constants)?.pipeline)
+        };
+
+        Ok(Self {
+            stencil,
+            format,
+            uniforms: PassUniforms::new(device, "jump flood params", steps.len() as u32),
+            steps,
+            seed_pipeline: pipeline(&[], "fs_seed", JUMP_FLOOD_FORMAT, true)?,
+            step_pipeline: pipeline(&[&bind_group_layout], "fs_step", JUMP_FLOOD_FORMAT, false)?,
+            outline_pipeline: pipeline(&[&bind_group_layout, &layouts.glow], "fs_outline", format, false)?,
+            bind_group_layout,
+            bind_groups: Vec::new(),
+        })
+    }
+}
+
+impl RenderNode for JumpFloodNode {
+    fn name(&self) -> &'static str { "jump flood" }
+
+    fn reads(&self) -> Vec<ResourceId> { vec![self.stencil] }
+
+    fn writes(&self) -> Vec<(ResourceId, TargetDesc)> {
+        let mut writes = vec![(OUTLINE, TargetDesc::new(self.format, 1))];
+        writes.extend(JUMP_FLOOD_SEEDS.map(|seeds| (seeds, TargetDesc::new(JUMP_FLOOD_FORMAT, 1))));
+        writes
+    }
+
+    fn resize(&mut self, device: &wgpu::Device, targets: &Targets) -> anyhow::Result<()> {
+        self.bind_groups = JUMP_FLOOD_SEEDS
+            .iter()
+            .map(|&seeds| Ok(create_jump_flood_bind_group(device, &self.bind_group_layout, targets.view(seeds)?, &self.uniforms)))
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(())
+    }
+
+    fn record(&self, encoder: &mut wgpu::CommandEncoder, ctx: &FrameContext) -> anyhow::Result<()> {
+        let (mut first_timestamps, last_timestamps) = split_timestamp_writes(ctx);
+
+        // Seeds: the pixel coordinates and group of every mask pixel, unset elsewhere
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("jump flood seed"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: ctx.view(JUMP_FLOOD_SEEDS[0])?,
+                    depth_slice: None,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                // Read-only mask
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: ctx.view(self.stencil)?,
+                    depth_ops: None,
+                    stencil_ops: None,
+                }),
+                timestamp_writes: first_timestamps.take(),
+                occlusion_query_set: None,
+                multiview_mask: None,
+            });
+            pass.set_pipeline(&self.seed_pipeline);
+
+            // One fullscreen draw per group, the instance index carries it to the shader
+            let mut groups = highlighted(ctx).map(|(group, _)| group as u32).collect::<Vec<_>>();
+            groups.sort_unstable();
+            groups.dedup();
+            for group in groups {
+                pass.set_stencil_reference(group);
+                pass.draw(0..3, group..group + 1);
+            }
+        }
+
+        // Nearest seed, `step` pixels apart then closer and closer
+        for (i, step) in self.steps.iter().enumerate() {
+            self.uniforms.write(ctx.queue, i as u32, &JumpFloodParams::new(*step));
+
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("jump flood step"),
+                color_attachments: &[Some(fullscreen_attachment(ctx.view(JUMP_FLOOD_SEEDS[(i + 1) % 2])?))],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+                multiview_mask: None,
+            });
+            pass.set_pipeline(&self.step_pipeline);
+            pass.set_bind_group(0, &self.bind_groups[i % 2], &[self.uniforms.offset(i as u32)]);
+            pass.draw(0..3, 0..1);
+        }
+
+        // Distance to the nearest seed: outline of the seed's group
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("jump flood outline"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: ctx.view(OUTLINE)?,
+                depth_slice: None,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: last_timestamps,
+            occlusion_query_set: None,
+            multiview_mask: None,
+        });
+        pass.set_pipeline(&self.outline_pipeline);
+        pass.set_bind_group(0, &self.bind_groups[self.steps.len() % 2], &[self.uniforms.offset(0)]);
+        pass.set_bind_group(1, ctx.scene.glow_bind_group, &[]);
+        pass.draw(0..3, 0..1);
+
+        Ok(())
+    }
+}
+
+// /
+// /  B L U R   O U T L I N E S
+// /
+// / One node per direction, (1,0) = horizontal, (0,1) = vertical. Gaussian kernel from
+// / GlowConfig.effects (blur_radius, blur_sigma), each node in its own uniform slot.
+
+pub struct BlurNode {
+    name: &'static str,
+    input: ResourceId,
+    output: ResourceId,
+    params: BlurParams,
+    uniforms: PassUniforms<BlurParams>,
+    slot: u32,
+    format: wgpu::TextureFormat,
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    linear_sampler: wgpu::Sampler,
+    bind_group: Option<wgpu::BindGroup>,
+}
+
+impl BlurNode {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        settings: GlowGraphSettings,
+        name: &'static str,
+        input: ResourceId,
+        output: ResourceId,
+        direction: [f32; 2],
+        uniforms: &PassUniforms<BlurParams>,
+        slot: u32,
+    ) -> anyhow::Result<Self> {
+        let bind_group_layout = create_blur_bind_group_layout(device);
+        let pipeline = Pipeline::blur_pipeline(
+            device,
+            config,
+            bind_group_layout.clone(),
+            settings.is_hdr,
+            &settings.effects.blur_constants(),
+        )?.pipeline;
+
+        Ok(Self {
+            name,
+            input,
+            output,
+            params: BlurParams::gaussian(settings.effects.blur_radius, settings.effects.blur_sigma)
+                .with_direction(direction[0], direction[1]),
+            uniforms: uniforms.clone(),
+            slot,
+            format: color_format(config, settings),
+            pipeline,
+            bind_group_layout,
+            linear_sampler: create_linear_sampler(device),
+            bind_group: None,
+        })
+    }
+}
+
+impl RenderNode for BlurNode {
+    fn name(&self) -> &'static str { self.name }
+
+    fn reads(&self) -> Vec<ResourceId> { vec![self.input] }
+
+    fn writes(&self) -> Vec<(ResourceId, TargetDesc)> {
+        vec![(self.output, TargetDesc::new(self.format, 1))]
+    }
+
+    fn resize(&mut self, device: &wgpu::Device, targets: &Targets) -> anyhow::Result<()> {
+        self.bind_group = Some(create_blur_bind_group(
+            device,
+            &self.bind_group_layout,
+            targets.view(self.input)?,
+            &self.linear_sampler,
+            &self.uniforms,
+        ));
+        Ok(())
+    }
+
+    fn record(&self, encoder: &mut wgpu::CommandEncoder, ctx: &FrameContext) -> anyhow::Result<()> {
+        self.uniforms.write(ctx.queue, self.slot, &self.params);
+
+        let mut blur_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some(self.name),
+            color_attachments: &[Some(fullscreen_attachment(ctx.view(self.output)?))],
+            depth_stencil_attachment: None,
+            timestamp_writes: ctx.timestamp_writes.clone(),
+            occlusion_query_set: None,
+            multiview_mask: None,
+        });
+        blur_pass.set_pipeline(&self.pipeline);
+        blur_pass.set_bind_group(0, &self.bind_group, &[self.uniforms.offset(self.slot)]);
+        blur_pass.draw(0..3, 0..1);
+
+        Ok(())
+    }
+}
+
+// /
+// /  C O M P U T E   B L U R
+// /
+// / Same kernel and uniform slots as BlurNode, in a compute pass: each workgroup caches a
+// / tile of a row / column (plus the apron) in workgroup memory. Writes Rgba16Float storage
+// / targets whatever the HDR setting. Needs compute shaders: not on WebGL.
+
+// Texels along the blur direction per workgroup (TILE in blur_compute.wgsl)
+const COMPUTE_BLUR_TILE: u32 = 128;
+
+pub struct ComputeBlurNode {
+    name: &'static str,
+    input: ResourceId,
+    output: ResourceId,
+    params: BlurParams,
+    uniforms: PassUniforms<BlurParams>,
+    slot: u32,
+    pipeline: wgpu::ComputePipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: Option<wgpu::BindGroup>,
+    size: (u32, u32),
+}
+
+impl ComputeBlurNode {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        device: &wgpu::Device,
+        settings: GlowGraphSettings,
+        name: &'static str,
+        input: ResourceId,
+        output: ResourceId,
+        direction: [f32; 2],
+        uniforms: &PassUniforms<BlurParams>,
+        slot: u32,
+    ) -> Self {
+        let bind_group_layout = create_blur_compute_bind_group_layout(device);
+        let pipeline = crate::pipeline::blur_compute_pipeline(
+            device,
+            &bind_group_layout,
+            &settings.effects.blur_constants(),
+        );
+
+        Self {
+            name,
+            input,
+            output,
+            params: BlurParams::gaussian(settings.effects.blur_radius, settings.effects.blur_sigma)
+                .with_direction(direction[0], direction[1]),
+            uniforms: uniforms.clone(),
+            slot,
+            pipeline,
+            bind_group_layout,
+            bind_group: None,
+            size: (0, 0),
+        }
+    }
+
+    fn is_horizontal(&self) -> bool {
+        self.params.direction[0] > 0.5
+    }
+}
+
+impl RenderNode for ComputeBlurNode {
+    fn name(&self) -> &'static str { self.name }
+
+    fn reads(&self) -> Vec<ResourceId> { vec![self.input] }
+
+    fn writes(&self) -> Vec<(ResourceId, TargetDesc)> {
+        vec![(self.output, TargetDesc::storage(wgpu::TextureFormat::Rgba16Float))]
+    }
+
+    fn resize(&mut self, device: &wgpu::Device, targets: &Targets) -> anyhow::Result<()> {
+        self.size = targets.size();
+        self.bind_group = Some(create_blur_compute_bind_group(
+            device,
+            &self.bind_group_layout,
+            targets.view(self.input)?,
+            targets.view(self.output)?,
+            &self.uniforms,
+        ));
+        Ok(())
+    }
+
+    fn record(&self, encoder: &mut wgpu::CommandEncoder, ctx: &FrameContext) -> anyhow::Result<()> {
+        self.uniforms.write(ctx.queue, self.slot, &self.params);
+
+        let timestamp_writes = ctx.timestamp_writes.as_ref().map(|t| wgpu::ComputePassTimestampWrites {
+            query_set: t.query_set,
+            beginning_of_pass_write_index: t.beginning_of_pass_write_index,
+            end_of_pass_write_index: t.end_of_pass_write_index,
+        });
+
+        // One workgroup per tile of a row (horizontal) or of a column (vertical)
+        let (width, height) = self.size;
+        let (length, lines) = if self.is_horizontal() { (width, height) } else { (height, width) };
+
+        let mut blur_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some(self.name),
+            timestamp_writes,
+        });
+        blur_pass.set_pipeline(&self.pipeline);
+        blur_pass.set_bind_group(0, &self.bind_group, &[self.uniforms.offset(self.slot)]);
+        blur_pass.dispatch_workgroups(length.div_ceil(COMPUTE_BLUR_TILE), lines, 1);
+
+        Ok(())
+    }
+}
+
+// /
+// / M I P   C H A I N   B L O O M
+
+pub struct BloomNode {
+    levels: u32,
+    format: wgpu::TextureFormat,
+    prefilter_pipeline: wgpu::RenderPipeline,
+    downsample_pipeline: wgpu::RenderPipeline,
+    // Adds the upsampled mip into the next bigger one
+    upsample_pipeline: wgpu::RenderPipeline,
+    // Last upsample into BLOOM, weighted by the blend constant (1 / levels)
+    resolve_pipeline: wgpu::RenderPipeline,
+    prefilter_bind_group_layout: wgpu::BindGroupLayout,
+    bind_group_layout: wgpu::BindGroupLayout,
+    linear_sampler: wgpu::Sampler,
+    // Half resolution and below, allocated on resize: private, the number of levels
+    // depends on the screen size (see RenderNode)
+    mips: Vec<wgpu::TextureView>,
+    // [0]: outline + scene -> mips[0], [i]: mips[i - 1] -> mips[i]
+    downsample_bind_groups: Vec<wgpu::BindGroup>,
+    // [i]: samples mips[i]
+    upsample_bind_groups: Vec<wgpu::BindGroup>,
+}
+
+impl BloomNode {
+    pub fn new(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        settings: GlowGraphSettings,
+    ) -> anyhow::Result<Self> {
+        let prefilter_bind_group_layout = create_bloom_bind_group_layout(device, true);
+        let bind_group_layout = create_bloom_bind_group_layout(device, false);
+        let constants = settings.effects.bloom_constants();
+
+        let pipeline = |layout, entry_point, is_additive| -> anyhow::Result<wgpu::RenderPipeline> {
+            Ok(Pipeline::bloom_pipeline(device, config, layout, settings.is_hdr, entry_point, is_additive, &constants)?.pipeline)
+        };
+
+        Ok(Self {
+            levels: settings.effects.bloom_levels,
+            format: color_format(config, settings),
+            prefilter_pipeline: pipeline(&prefilter_bind_group_layout, "fs_prefilter", false)?,
+            downsample_pipeline: pipeline(&bind_group_layout, "fs_downsample", false)?,
+            upsample_pipeline: pipeline(&bind_group_layout, "fs_upsample", true)?,
+            resolve_pipeline: Self::resolve_pipeline(device, config, settings, &bind_group_layout, &constants)?,
+            prefilter_bind_group_layout,
+            bind_group_layout,
+            linear_sampler: create_linear_sampler(device),
+            mips: Vec::new(),
+            downsample_bind_groups: Vec::new(),
+            upsample_bind_groups: Vec::new(),
+        })
+    }
+
+    // fs_upsample scaled by the blend constant: the levels add up, keep the energy of one
+    fn resolve_pipeline(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        settings: GlowGraphSettings,
+        layout: &wgpu::BindGroupLayout,
+        constants: &[(&str, f64)],
+    ) -> anyhow::Result<wgpu::RenderPipeline> {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("bloom resolve shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/bloom.wgsl").into()),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("bloom resolve pipeline layout"),
+            bind_group_layouts: &[layout],
+            immediate_size: 0,
+        });
+        let scaled = wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::Constant,
+            dst_factor: wgpu::BlendFactor::Zero,
+            operation: wgpu::BlendOperation::Add,
+        };
+        let compilation_options = wgpu::PipelineCompilationOptions { constants, ..Default::default() };
+
+        Ok(device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("bloom resolve pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: compilation_options.clone(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_upsample"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: color_format(config, settings),
+                    blend: Some(wgpu::BlendState { color: scaled, alpha: scaled }),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options,
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview_mask: None,
+            cache: None,
+        }))
+    }
+}
+
+impl RenderNode for BloomNode {
+    fn name(&self) -> &'static str { "bloom" }
+
+    fn reads(&self) -> Vec<ResourceId> { vec![OUTLINE, SCENE] }
+
+    fn writes(&self) -> Vec<(ResourceId, TargetDesc)> {
+        vec![(BLOOM, TargetDesc::new(self.format, 1))]
+    }
+
+    fn resize(&mut self, device: &wgpu::Device, targets: &Targets) -> anyhow::Result<()> {
+        let (width, height) = targets.size();
+
+        // Stop before the mips get smaller than a texel
+        let levels = self.levels.min(width.min(height).max(2).ilog2());
+
+        self.mips = (1..=levels)
+            .map(|level| {
+                device
+                    .create_texture(&wgpu::TextureDescriptor {
+                        label: Some("bloom mip"),
+                        size: wgpu::Extent3d {
+                            width: (width >> level).max(1),
+                            height: (height >> level).max(1),
+                            depth_or_array_layers: 1,
+                        },
+                        mip_level_count: 1,
+                        sample_count: 1,
+                        dimension: wgpu::TextureDimension::D2,
+                        format: self.format,
+                        usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+                        view_formats: &[],
+                    })
+                    .create_view(&wgpu::TextureViewDescriptor::default())
+            })
+            .collect();
+
+        let prefilter_bind_group = create_bloom_bind_group(
+            device,
+            &self.prefilter_bind_group_layout,
+            targets.view(OUTLINE)?,
+            &self.linear_sampler,
+            Some(targets.view(SCENE)?),
+        );
+        self.downsample_bind_groups = std::iter::once(prefilter_bind_group)
+            .chain(self.mips.iter().take(self.mips.len() - 1).map(|mip| {
+                create_bloom_bind_group(device, &self.bind_group_layout, mip, &self.linear_sampler, None)
+            }))
+            .collect();
+
+        self.upsample_bind_groups = self
+            .mips
+            .iter()
+            .map(|mip| create_bloom_bind_group(device, &self.bind_group_layout, mip, &self.linear_sampler, None))
+            .collect();
+
+        Ok(())
+    }
+
+    fn record(&self, encoder: &mut wgpu::CommandEncoder, ctx: &FrameContext) -> anyhow::Result<()> {
+        let (mut first_timestamps, last_timestamps) = split_timestamp_writes(ctx);
+
+        // Downsample: outline + bright scene -> 1/2 -> 1/4 -> ...
+        for (i, mip) in self.mips.iter().enumerate() {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("bloom downsample"),
+                color_attachments: &[Some(fullscreen_attachment(mip))],
+                depth_stencil_attachment: None,
+                timestamp_writes: first_timestamps.take(),
+                occlusion_query_set: None,
+                multiview_mask: None,
+            });
+            pass.set_pipeline(if i == 0 { &self.prefilter_pipeline } else { &self.downsample_pipeline });
+            pass.set_bind_group(0, &self.downsample_bind_groups[i], &[]);
+            pass.draw(0..3, 0..1);
+        }
+
+        // Upsample: add each mip, blurred, into the next bigger one
+        for i in (1..self.mips.len()).rev() {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("bloom upsample"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &self.mips[i - 1],
+                    depth_slice: None,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load, // <- keep the downsampled level
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                timestamp_writes: None,
+                occlusion_query_set: None,
+                multiview_mask: None,
+            });
+            pass.set_pipeline(&self.upsample_pipeline);
+            pass.set_bind_group(0, &self.upsample_bind_groups[i], &[]);
+            pass.draw(0..3, 0..1);
+        }
+
+        // Full resolution, normalized by the number of levels
+        let scale = 1.0 / self.mips.len() as f64;
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("bloom resolve"),
+            color_attachments: &[Some(fullscreen_attachment(ctx.view(BLOOM)?))],
+            depth_stencil_attachment: None,
+            timestamp_writes: last_timestamps,
+            occlusion_query_set: None,
+            multiview_mask: None,
+        });
+        pass.set_pipeline(&self.resolve_pipeline);
+        pass.set_blend_constant(wgpu::Color { r: scale, g: scale, b: scale, a: scale });
+        pass.set_bind_group(0, &self.upsample_bind_groups[0], &[]);
+        pass.draw(0..3, 0..1);
+
+        Ok(())
+    }
+}
+
+// /
+// / T O T A L  S C E N E
+
+pub struct SceneNode {
+    pipeline: wgpu::RenderPipeline,
+    format: wgpu::TextureFormat,
+    sample_count: u32,
+    object_ids: PassUniforms<ObjectIdParams>,
+    object_id_bind_group: wgpu::BindGroup,
+}
+
+impl SceneNode {
+    pub fn new(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        layouts: &SceneLayouts,
+        settings: GlowGraphSettings,
+        object_ids: &PassUniforms<ObjectIdParams>,
+    ) -> anyhow::Result<Self> {
+        let pipeline = Pipeline::build_render_pipeline(
+            device,
+            config,
+            settings.sample_count,
+            &layouts.texture,
+            &layouts.camera,
+            &layouts.spin,
+            &layouts.object_id,
+            settings.is_hdr,
+        )?
+        .pipeline;
+
+        Ok(Self {
+            pipeline,
+            format: color_format(config, settings),
+            sample_count: settings.sample_count,
+            object_ids: object_ids.clone(),
+            object_id_bind_group: create_object_id_bind_group(device, &layouts.object_id, object_ids),
+        })
+    }
+}
+
+// / Mesh part of the object IDs, in the slot of each mesh
+fn write_object_id_params(ctx: &FrameContext, object_ids: &PassUniforms<ObjectIdParams>) {
+    for mesh in 0..(ctx.scene.model.meshes.len() as u32).min(MAX_OBJECT_ID_MESHES) {
+        object_ids.write(ctx.queue, mesh, &ObjectIdParams::new(mesh));
+    }
+}
+
+impl RenderNode for SceneNode {
+    fn name(&self) -> &'static str { "scene" }
+
+    fn reads(&self) -> Vec<ResourceId> { vec![DEPTH_STENCIL] }
+
+    fn writes(&self) -> Vec<(ResourceId, TargetDesc)> {
+        let mut writes = vec![
+            (DEPTH_STENCIL, TargetDesc::new(Texture::DEPTH_STENCIL_FORMAT, self.sample_count)),
+            (SCENE, TargetDesc::new(self.format, 1)),
+            // Rgba16Float for high precision normals (avoids jagged edges on curves)
+            (NORMAL, TargetDesc::new(wgpu::TextureFormat::Rgba16Float, 1)),
+        ];
+        if self.sample_count > 1 {
+            writes.push((SCENE_MSAA, TargetDesc::new(self.format, self.sample_count)));
+            writes.push((NORMAL_MSAA, TargetDesc::new(wgpu::TextureFormat::Rgba16Float, self.sample_count)));
+        } else {
+            writes.push((OBJECT_ID, TargetDesc::new(OBJECT_ID_FORMAT, 1)));
+        }
+        writes
+    }
+
+    fn record(&self, encoder: &mut wgpu::CommandEncoder, ctx: &FrameContext) -> anyhow::Result<()> {
+        let clear_color = if self.sample_count == 1 {
+            wgpu::Color { r: 0.67, g: 0.27, b: 0.15, a: 1. }
+        } else {
+            wgpu::Color::BLACK
+        };
+
+        let scene_attachment = color_attachment(
+            ctx,
+            self.sample_count,
+            SCENE_MSAA,
+            SCENE,
+            wgpu::LoadOp::Clear(clear_color),
+            wgpu::StoreOp::Discard,
+        )?;
+
+        // The edge pass needs the resolved normals
+        let normal_attachment = color_attachment(
+            ctx,
+            self.sample_count,
+            NORMAL_MSAA,
+            NORMAL,
+            wgpu::LoadOp::Clear(wgpu::Color::RED),
+            wgpu::StoreOp::Store,
+        )?;
+
+        // Background 0. Multisampled: drawn by the object ID node
+        let object_id_attachment = if self.sample_count == 1 {
+            Some(fullscreen_attachment(ctx.view(OBJECT_ID)?))
+        } else {
+            None
+        };
+
+        write_object_id_params(ctx, &self.object_ids);
+
+        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Render Total Scene Pass"),
+            color_attachments: &[Some(scene_attachment), Some(normal_attachment), object_id_attachment],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: ctx.view(DEPTH_STENCIL)?,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.), // <- the mask depth is only for the groups
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Load, // <- keep mask
+                    store: wgpu::StoreOp::Store,
+                }),
+            }),
+            occlusion_query_set: None,
+            timestamp_writes: ctx.timestamp_writes.clone(),
+            multiview_mask: None,
+        });
+
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(1, ctx.scene.camera_bind_group, &[]);
+        render_pass.set_bind_group(2, ctx.scene.spin_bind_group, &[]);
+        render_pass.set_vertex_buffer(1, ctx.scene.instance_buffer.slice(..));
+
+        for (i, mesh) in ctx.scene.model.meshes.iter().enumerate() {
+            let slot = i as u32 % MAX_OBJECT_ID_MESHES;
+            render_pass.set_bind_group(0, &ctx.scene.model.materials[mesh.material].bind_group, &[]);
+            render_pass.set_bind_group(3, &self.object_id_bind_group, &[self.object_ids.offset(slot)]);
+            render_pass.draw_mesh_instanced(mesh, 0..ctx.scene.instance_count);
+        }
+
+        Ok(())
+    }
+}
+
+// /
+// / O B J E C T  I D S  (multisampled scene)
+
+pub struct ObjectIdNode {
+    pipeline: wgpu::RenderPipeline,
+    object_ids: PassUniforms<ObjectIdParams>,
+    object_id_bind_group: wgpu::BindGroup,
+}
+
+impl ObjectIdNode {
+    pub fn new(
+        device: &wgpu::Device,
+        layouts: &SceneLayouts,
+        object_ids: &PassUniforms<ObjectIdParams>,
+    ) -> anyhow::Result<Self> {
+        let pipeline = Pipeline::object_id_pipeline(
+            device,
+            &layouts.texture,
+            &layouts.camera,
+            &layouts.spin,
+            &layouts.object_id,
+        )?
+        .pipeline;
+
+        Ok(Self {
+            pipeline,
+            object_ids: object_ids.clone(),
+            object_id_bind_group: create_object_id_bind_group(device, &layouts.object_id, object_ids),
+        })
+    }
+}
+
+impl RenderNode for ObjectIdNode {
+    fn name(&self) -> &'static str { "object id" }
+
+    fn reads(&self) -> Vec<ResourceId> { vec![] }
+
+    fn writes(&self) -> Vec<(ResourceId, TargetDesc)> {
+        vec![
+            (OBJECT_ID, TargetDesc::new(OBJECT_ID_FORMAT, 1)),
+            (OBJECT_ID_DEPTH, TargetDesc::new(Texture::DEPTH_FORMAT, 1)),
+        ]
+    }
+
+    fn record(&self, encoder: &mut wgpu::CommandEncoder, ctx: &FrameContext) -> anyhow::Result<()> {
+        write_object_id_params(ctx, &self.object_ids);
+
+        let mut object_id_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("object id pass"),
+            color_attachments: &[Some(fullscreen_attachment(ctx.view(OBJECT_ID)?))],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: ctx.view(OBJECT_ID_DEPTH)?,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.),
+                    store: wgpu::StoreOp::Discard,
+                }),
+                stencil_ops: None,
+            }),
+            timestamp_writes: ctx.timestamp_writes.clone(),
+            ..Default::default()
+        });
+
+        object_id_pass.set_pipeline(&self.pipeline);
+        object_id_pass.set_bind_group(1, ctx.scene.camera_bind_group, &[]);
+        object_id_pass.set_bind_group(2, ctx.scene.spin_bind_group, &[]);
+        object_id_pass.set_vertex_buffer(1, ctx.scene.instance_buffer.slice(..));
+
+        for (i, mesh) in ctx.scene.model.meshes.iter().enumerate() {
+            let slot = i as u32 % MAX_OBJECT_ID_MESHES;
+            object_id_pass.set_bind_group(0, &ctx.scene.model.materials[mesh.material].bind_group, &[]);
+            object_id_pass.set_bind_group(3, &self.object_id_bind_group, &[self.object_ids.offset(slot)]);
+            object_id_pass.draw_mesh_instanced(mesh, 0..ctx.scene.instance_count);
+        }
+
+        Ok(())
+    }
+}
+
+// /
+// / E D G E  P A S S
+
+pub struct EdgeNode {
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    edge_sampler: wgpu::Sampler,
+    bind_group: Option<wgpu::BindGroup>,
+    format: wgpu::TextureFormat,
+    // Written every frame with the camera clip planes
+    params: EdgeParams,
+    params_buffer: wgpu::Buffer,
+}
+
+impl EdgeNode {
+    pub fn new(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        settings: GlowGraphSettings,
+    ) -> anyhow::Result<Self> {
+        let bind_group_layout = create_edge_bind_group_layout(device);
+        let pipeline = Pipeline::edge_pipeline(device, config, &bind_group_layout, settings.is_hdr)?.pipeline;
+
+        // Nearest + clamp: pixel-perfect edges, no bleeding from the screen border
+        let edge_sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Edge Detection Sampler"),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Nearest,
+            min_filter: wgpu::FilterMode::Nearest,
+            mipmap_filter: wgpu::MipmapFilterMode::Nearest,
+            compare: None,
+            ..Default::default()
+        });
+
+        let params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Edge Params Buffer"),
+            size: std::mem::size_of::<EdgeParams>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        Ok(Self {
+            pipeline,
+            bind_group_layout,
+            edge_sampler,
+            bind_group: None,
+            format: color_format(config, settings),
+            params: EdgeParams::new(&settings.effects),
+            params_buffer,
+        })
+    }
+}
+
+impl RenderNode for EdgeNode {
+    fn name(&self) -> &'static str { "edge" }
+
+    fn reads(&self) -> Vec<ResourceId> { vec![SCENE, PREPASS_DEPTH, NORMAL, OBJECT_ID] }
+
+    fn writes(&self) -> Vec<(ResourceId, TargetDesc)> {
+        vec![(EDGE, TargetDesc::new(self.format, 1))]
+    }
+
+    fn resize(&mut self, device: &wgpu::Device, targets: &Targets) -> anyhow::Result<()> {
+        self.bind_group = Some(create_edge_bind_group(
+            device,
+            &self.bind_group_layout,
+            targets.view(SCENE)?,
+            &self.edge_sampler,
+            targets.view(PREPASS_DEPTH)?,
+            targets.view(NORMAL)?,
+            &self.params_buffer,
+            targets.view(OBJECT_ID)?,
+        ));
+        Ok(())
+    }
+
+    fn record(&self, encoder: &mut wgpu::CommandEncoder, ctx: &FrameContext) -> anyhow::Result<()> {
+        let params = self.params.with_clip(ctx.scene.camera.znear, ctx.scene.camera.zfar);
+        ctx.queue.write_buffer(&self.params_buffer, 0, bytemuck::bytes_of(&params));
+
+        let mut edge_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("edge pass"),
+            color_attachments: &[Some(fullscreen_attachment(ctx.view(EDGE)?))],
+            depth_stencil_attachment: None,
+            timestamp_writes: ctx.timestamp_writes.clone(),
+            ..Default::default()
+        });
+        edge_pass.set_pipeline(&self.pipeline);
+        edge_pass.set_bind_group(0, &self.bind_group, &[]);
+        edge_pass.draw(0..3, 0..1);
+
+        Ok(())
+    }
+}
+
+// /
+// /  C O M P O S I T E   P A S S
+
+pub struct CompositeNode {
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    linear_sampler: wgpu::Sampler,
+    bind_group: Option<wgpu::BindGroup>,
+    format: wgpu::TextureFormat,
+}
+
+impl CompositeNode {
+    pub fn new(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        settings: GlowGraphSettings,
+    ) -> anyhow::Result<Self> {
+        let bind_group_layout = create_composite_bind_group_layout(device);
+        let pipeline = Pipeline::composite_pipeline(
+            device,
+            config,
+            bind_group_layout.clone(),
+            settings.is_hdr,
+            &settings.effects.composite_constants(),
+        )?.pipeline;
+
+        Ok(Self {
+            pipeline,
+            bind_group_layout,
+            linear_sampler: create_linear_sampler(device),
+            bind_group: None,
+            format: color_format(config, settings),
+        })
+    }
+}
+
+impl RenderNode for CompositeNode {
+    fn name(&self) -> &'static str { "composite" }
+
+    fn reads(&self) -> Vec<ResourceId> { vec![EDGE, BLOOM, OUTLINE] }
+
+    fn writes(&self) -> Vec<(ResourceId, TargetDesc)> {
+        vec![(COMPOSITE, TargetDesc::new(self.format, 1))]
+    }
+
+    fn resize(&mut self, device: &wgpu::Device, targets: &Targets) -> anyhow::Result<()> {
+        self.bind_group = Some(create_composite_bind_group(
+            device,
+            &self.bind_group_layout,
+            targets.view(EDGE)?, // scene + edges
+            targets.view(BLOOM)?,
+            targets.view(OUTLINE)?,
+            &self.linear_sampler,
+        ));
+        Ok(())
+    }
+
+    fn record(&self, encoder: &mut wgpu::CommandEncoder, ctx: &FrameContext) -> anyhow::Result<()> {
+        let mut composite_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("composite pass"),
+            color_attachments: &[Some(fullscreen_attachment(ctx.view(COMPOSITE)?))],
+            depth_stencil_attachment: None,
+            timestamp_writes: ctx.timestamp_writes.clone(),
+            occlusion_query_set: None,
+            multiview_mask: None,
+        });
+
+        composite_pass.set_pipeline(&self.pipeline);
+        composite_pass.set_bind_group(0, &self.bind_group, &[]);
+        composite_pass.draw(0..3, 0..1);
+
+        Ok(())
+    }
+}
+
+// /
+// / A U T O   E X P O S U R E
+// /
+// / Both write EXPOSURE: r = exposure to apply (EV), g = adapted log2 luminance, b = 1 once
+// / metered. The adapted luminance of the previous frame is kept by the node, so a rebuilt
+// / graph meters again from scratch.
+
+// / Compute passes: log luminance histogram of the composite, then its average.
+pub struct HistogramExposureNode {
+    speed: f32,
+    histogram_pipeline: wgpu::ComputePipeline,
+    average_pipeline: wgpu::ComputePipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: Option<wgpu::BindGroup>,
+    // AUTO_EXPOSURE_BINS counts, cleared by the average pass
+    histogram_buffer: wgpu::Buffer,
+    // Written every frame with the adaptation of the frame time
+    params_buffer: wgpu::Buffer,
+    // Adapted log2 luminance, 1 once metered
+    adapted_buffer: wgpu::Buffer,
+    size: (u32, u32),
+}
+
+impl HistogramExposureNode {
+    pub fn new(device: &wgpu::Device, settings: GlowGraphSettings) -> Self {
+        let bind_group_layout = create_auto_exposure_compute_bind_group_layout(device);
+        let constants = settings.effects.auto_exposure_constants();
+        let pipeline = |entry_point| {
+            crate::pipeline::auto_exposure_compute_pipeline(device, &bind_group_layout, entry_point, &constants)
+        };
+
+        let storage_buffer = |label, size| {
+            device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some(label),
+                size,
+                usage: wgpu::BufferUsages::STORAGE,
+                mapped_at_creation: false,
+            })
+        };
+
+        Self {
+            speed: settings.effects.auto_exposure_speed,
+            histogram_pipeline: pipeline("cs_histogram"),
+            average_pipeline: pipeline("cs_average"),
+            histogram_buffer: storage_buffer("Luminance Histogram Buffer", AUTO_EXPOSURE_BINS as u64 * 4),
+            adapted_buffer: storage_buffer("Adapted Luminance Buffer", 8),
+            params_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Auto Exposure Params Buffer"),
+                size: std::mem::size_of::<AutoExposureParams>() as u64,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+            bind_group_layout,
+            bind_group: None,
+            size: (0, 0),
+        }
+    }
+}
+
+impl RenderNode for HistogramExposureNode {
+    fn name(&self) -> &'static str { "auto exposure" }
+
+    fn reads(&self) -> Vec<ResourceId> { vec![COMPOSITE] }
+
+    fn writes(&self) -> Vec<(ResourceId, TargetDesc)> {
+        vec![(EXPOSURE, TargetDesc::storage(wgpu::TextureFormat::Rgba16Float).with_size(1, 1))]
+    }
+
+    fn resize(&mut self, device: &wgpu::Device, targets: &Targets) -> anyhow::Result<()> {
+        self.size = targets.size();
+        self.bind_group = Some(create_auto_exposure_compute_bind_group(
+            device,
+            &self.bind_group_layout,
+            targets.view(COMPOSITE)?,
+            &self.histogram_buffer,
+            &self.params_buffer,
+            targets.view(EXPOSURE)?,
+            &self.adapted_buffer,
+        ));
+        Ok(())
+    }
+
+    fn record(&self, encoder: &mut wgpu::CommandEncoder, ctx: &FrameContext) -> anyhow::Result<()> {
+        let params = AutoExposureParams::new(self.speed, ctx.scene.delta_time);
+        ctx.queue.write_buffer(&self.params_buffer, 0, bytemuck::bytes_of(&params));
+
+        let timestamp_writes = ctx.timestamp_writes.as_ref().map(|t| wgpu::ComputePassTimestampWrites {
+            query_set: t.query_set,
+            beginning_of_pass_write_index: t.beginning_of_pass_write_index,
+            end_of_pass_write_index: t.end_of_pass_write_index,
+        });
+
+        let (width, height) = self.size;
+        let mut exposure_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("auto exposure"),
+            timestamp_writes,
+        });
+        exposure_pass.set_bind_group(0, &self.bind_group, &[]);
+        // 16x16 tiles, then one workgroup over the bins
+        exposure_pass.set_pipeline(&self.histogram_pipeline);
+        exposure_pass.dispatch_workgroups(width.div_ceil(16), height.div_ceil(16), 1);
+        exposure_pass.set_pipeline(&self.average_pipeline);
+        exposure_pass.dispatch_workgroups(1, 1, 1);
+
+        Ok(())
+    }
+}
+
+// / Render passes (WebGL): log luminance of the composite averaged down to one texel.
+pub struct DownsampleExposureNode {
+    luminance_pipeline: wgpu::RenderPipeline,
+    downsample_pipeline: wgpu::RenderPipeline,
+    adapt_pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    adapt_bind_group_layout: wgpu::BindGroupLayout,
+    linear_sampler: wgpu::Sampler,
+    speed: f32,
+    params_buffer: wgpu::Buffer,
+    // Half resolution down to 1x1, allocated on resize: private, the number of levels
+    // depends on the screen size (see RenderNode)
+    mips: Vec<wgpu::TextureView>,
+    // [0]: composite -> mips[0], [i]: mips[i - 1] -> mips[i]
+    downsample_bind_groups: Vec<wgpu::BindGroup>,
+    // Copy of EXPOSURE, read by the next frame's adapt pass: kept across resizes
+    previous: wgpu::Texture,
+    adapt_bind_group: Option<wgpu::BindGroup>,
+}
+
+impl DownsampleExposureNode {
+    pub fn new(device: &wgpu::Device, settings: GlowGraphSettings) -> anyhow::Result<Self> {
+        let bind_group_layout = create_auto_exposure_bind_group_layout(device, false);
+        let adapt_bind_group_layout = create_auto_exposure_bind_group_layout(device, true);
+        let constants = settings.effects.auto_exposure_constants();
+
+        let pipeline = |layout, entry_point| -> anyhow::Result<wgpu::RenderPipeline> {
+            Ok(Pipeline::auto_exposure_pipeline(device, layout, entry_point, &constants)?.pipeline)
+        };
+
+        Ok(Self {
+            luminance_pipeline: pipeline(&bind_group_layout, "fs_luminance")?,
+            downsample_pipeline: pipeline(&bind_group_layout, "fs_downsample")?,
+            adapt_pipeline: pipeline(&adapt_bind_group_layout, "fs_adapt")?,
+            linear_sampler: create_linear_sampler(device),
+            speed: settings.effects.auto_exposure_speed,
+            params_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Auto Exposure Params Buffer"),
+                size: std::mem::size_of::<AutoExposureParams>() as u64,
+                usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                mapped_at_creation: false,
+            }),
+            mips: Vec::new(),
+            downsample_bind_groups: Vec::new(),
+            previous: Self::mip_texture(device, "previous exposure", 1, 1, wgpu::TextureUsages::COPY_DST),
+            adapt_bind_group: None,
+            bind_group_layout,
+            adapt_bind_group_layout,
+        })
+    }
+
+    fn mip_texture(device: &wgpu::Device, label: &str, width: u32, height: u32, usage: wgpu::TextureUsages) -> wgpu::Texture {
+        device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba16Float,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | usage,
+            view_formats: &[],
+        })
+    }
+}
+
+impl RenderNode for DownsampleExposureNode {
+    fn name(&self) -> &'static str { "auto exposure" }
+
+    fn reads(&self) -> Vec<ResourceId> { vec![COMPOSITE] }
+
+    fn writes(&self) -> Vec<(ResourceId, TargetDesc)> {
+        vec![(EXPOSURE, TargetDesc::new(wgpu::TextureFormat::Rgba16Float, 1).with_size(1, 1))]
+    }
+
+    fn resize(&mut self, device: &wgpu::Device, targets: &Targets) -> anyhow::Result<()> {
+        let (width, height) = targets.size();
+
+        // Halving until 1x1 (at least one mip)
+        let levels = width.max(height).max(2).ilog2();
+        self.mips = (1..=levels)
+            .map(|level| {
+                Self::mip_texture(
+                    device,
+                    "luminance mip",
+                    (width >> level).max(1),
+                    (height >> level).max(1),
+                    wgpu::TextureUsages::RENDER_ATTACHMENT,
+                )
+                .create_view(&wgpu::TextureViewDescriptor::default())
+            })
+            .collect();
+
+        self.downsample_bind_groups = std::iter::once(targets.view(COMPOSITE)?)
+            .chain(self.mips.iter().take(self.mips.len() - 1))
+            .map(|source| create_luminance_downsample_bind_group(device, &self.bind_group_layout, source, &self.linear_sampler))
+            .collect();
+
+        self.adapt_bind_group = Some(create_exposure_adapt_bind_group(
+            device,
+            &self.adapt_bind_group_layout,
+            &self.mips[self.mips.len() - 1],
+            &self.previous.create_view(&wgpu::TextureViewDescriptor::default()),
+            &self.params_buffer,
+        ));
+        Ok(())
+    }
+
+    fn record(&self, encoder: &mut wgpu::CommandEncoder, ctx: &FrameContext) -> anyhow::Result<()> {
+        let params = AutoExposureParams::new(self.speed, ctx.scene.delta_time);
+        ctx.queue.write_buffer(&self.params_buffer, 0, bytemuck::bytes_of(&params));
+
+        let (mut first_timestamps, last_timestamps) = split_timestamp_writes(ctx);
+
+        // Log luminance at 1/2 -> 1/4 -> ... -> 1x1
+        for (i, mip) in self.mips.iter().enumerate() {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("luminance downsample"),
+                color_attachments: &[Some(fullscreen_attachment(mip))],
+                depth_stencil_attachment: None,
+                timestamp_writes: first_timestamps.take(),
+                occlusion_query_set: None,
+                multiview_mask: None,
+            });
+            pass.set_pipeline(if i == 0 { &self.luminance_pipeline } else { &self.downsample_pipeline });
+            pass.set_bind_group(0, &self.downsample_bind_groups[i], &[]);
+            pass.draw(0..3, 0..1);
+        }
+
+        let exposure = ctx.targets.get(EXPOSURE)?;
+        {
+            let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("exposure adapt"),
+                color_attachments: &[Some(fullscreen_attachment(&exposure.view))],
+                depth_stencil_attachment: None,
+                timestamp_writes: last_timestamps,
+                occlusion_query_set: None,
+                multiview_mask: None,
+            });
+            pass.set_pipeline(&self.adapt_pipeline);
+            pass.set_bind_group(0, &self.adapt_bind_group, &[]);
+            pass.draw(0..3, 0..1);
+        }
+
+        // Graph targets can be copied (COPY_SRC)
+        encoder.copy_texture_to_texture(
+            exposure.texture.as_image_copy(),
+            self.previous.as_image_copy(),
+            wgpu::Extent3d { width: 1, height: 1, depth_or_array_layers: 1 },
+        );
+
+        Ok(())
+    }
+}
+
+// /
+// / T O N E  M A P   P A S S
+
+pub struct ToneMapNode {
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    linear_sampler: wgpu::Sampler,
+    bind_group: Option<wgpu::BindGroup>,
+    output_format: wgpu::TextureFormat,
+    // OUTPUT, or TONE_MAPPED for the color grade pass
+    output: ResourceId,
+    // Reads EXPOSURE, otherwise a black texel stands in for it
+    is_auto_exposed: bool,
+    unused_exposure: wgpu::TextureView,
+}
+
+impl ToneMapNode {
+    pub fn new(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        layouts: &SceneLayouts,
+        settings: GlowGraphSettings,
+    ) -> anyhow::Result<Self> {
+        let bind_group_layout = create_tone_map_bind_group_layout(device);
+        let is_auto_exposed = settings.is_hdr && settings.effects.auto_exposure != AutoExposure::Off;
+        let pipeline = Pipeline::tone_map_pipeline(
+            device,
+            config,
+            bind_group_layout.clone(),
+            &layouts.tone_map,
+            &[
+                // SDR: pass-through
+                ("is_hdr", settings.is_hdr as u8 as f64),
+                // Bgra8Unorm & co. (Rgba16Float / Rgb10a2Unorm surfaces stay linear)
+                ("encode_srgb", (config.format.add_srgb_suffix() != config.format) as u8 as f64),
+                ("auto_exposure", is_auto_exposed as u8 as f64),
+            ],
+        )?
+        .pipeline;
+
+        let unused_exposure = device
+            .create_texture(&wgpu::TextureDescriptor {
+                label: Some("unused exposure"),
+                size: wgpu::Extent3d { width: 1, height: 1, depth_or_array_layers: 1 },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba16Float,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING,
+                view_formats: &[],
+            })
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        Ok(Self {
+            pipeline,
+            bind_group_layout,
+            linear_sampler: create_linear_sampler(device),
+            bind_group: None,
+            output_format: config.format,
+            output: if settings.is_color_graded { TONE_MAPPED } else { OUTPUT },
+            is_auto_exposed,
+            unused_exposure,
+        })
+    }
+}
+
+impl RenderNode for ToneMapNode {
+    fn name(&self) -> &'static str { "tone map" }
+
+    fn reads(&self) -> Vec<ResourceId> {
+        if self.is_auto_exposed { vec![COMPOSITE, EXPOSURE] } else { vec![COMPOSITE] }
+    }
+
+    fn writes(&self) -> Vec<(ResourceId, TargetDesc)> {
+        vec![(self.output, TargetDesc::new(self.output_format, 1))]
+    }
+
+    fn resize(&mut self, device: &wgpu::Device, targets: &Targets) -> anyhow::Result<()> {
+        self.bind_group = Some(create_tone_map_bind_group(
+            device,
+            &self.bind_group_layout,
+            targets.view(COMPOSITE)?,
+            &self.linear_sampler,
+            if self.is_auto_exposed { targets.view(EXPOSURE)? } else { &self.unused_exposure },
+        ));
+        Ok(())
+    }
+
+    fn record(&self, encoder: &mut wgpu::CommandEncoder, ctx: &FrameContext) -> anyhow::Result<()> {
+        let mut tone_map_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Tone Map Pass"),
+            // ⬅ swapchain or offscreen target (or the color grade input)
+            color_attachments: &[Some(fullscreen_attachment(ctx.view(self.output)?))],
+            depth_stencil_attachment: None,
+            timestamp_writes: ctx.timestamp_writes.clone(),
+            ..Default::default()
+        });
+
+        tone_map_pass.set_pipeline(&self.pipeline);
+        tone_map_pass.set_bind_group(0, &self.bind_group, &[]);
+        tone_map_pass.set_bind_group(1, ctx.scene.tone_map_bind_group, &[]);
+        tone_map_pass.draw(0..3, 0..1);
+
+        Ok(())
+    }
+}
+
+// /
+// / C O L O R   G R A D E   P A S S
+
+// / 3D LUT of the scene data (hot swapped without a rebuild) over the tone mapped frame.
+pub struct ColorGradeNode {
+    pipeline: wgpu::RenderPipeline,
+    bind_group_layout: wgpu::BindGroupLayout,
+    bind_group: Option<wgpu::BindGroup>,
+    output_format: wgpu::TextureFormat,
+}
+
+impl ColorGradeNode {
+    pub fn new(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        layouts: &SceneLayouts,
+    ) -> anyhow::Result<Self> {
+        let bind_group_layout = create_color_grade_bind_group_layout(device);
+        let pipeline = Pipeline::color_grade_pipeline(
+            device,
+            config,
+            &bind_group_layout,
+            &layouts.color_lut,
+            // Same condition as encode_srgb in the tone map
+            &[("is_encoded", (config.format.add_srgb_suffix() != config.format) as u8 as f64)],
+        )?
+        .pipeline;
+
+        Ok(Self {
+            pipeline,
+            bind_group_layout,
+            bind_group: None,
+            output_format: config.format,
+        })
+    }
+}
+
+impl RenderNode for ColorGradeNode {
+    fn name(&self) -> &'static str { "color grade" }
+
+    fn reads(&self) -> Vec<ResourceId> { vec![TONE_MAPPED] }
+
+    fn writes(&self) -> Vec<(ResourceId, TargetDesc)> {
+        vec![(OUTPUT, TargetDesc::new(self.output_format, 1))]
+    }
+
+    fn resize(&mut self, device: &wgpu::Device, targets: &Targets) -> anyhow::Result<()> {
+        self.bind_group = Some(create_color_grade_bind_group(
+            device,
+            &self.bind_group_layout,
+            targets.view(TONE_MAPPED)?,
+        ));
+        Ok(())
+    }
+
+    fn record(&self, encoder: &mut wgpu::CommandEncoder, ctx: &FrameContext) -> anyhow::Result<()> {
+        let lut = ctx
+            .scene
+            .color_lut_bind_group
+            .ok_or(anyhow::anyhow!("color grade pass without a color LUT"))?;
+
+        let mut color_grade_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("color grade pass"),
+            // ⬅ swapchain or offscreen target
+            color_attachments: &[Some(fullscreen_attachment(ctx.view(OUTPUT)?))],
+            depth_stencil_attachment: None,
+            timestamp_writes: ctx.timestamp_writes.clone(),
+            ..Default::default()
+        });
+
+        color_grade_pass.set_pipeline(&self.pipeline);
+        color_grade_pass.set_bind_group(0, &self.bind_group, &[]);
+        color_grade_pass.set_bind_group(1, lut, &[]);
+        color_grade_pass.draw(0..3, 0..1);
+
+        Ok(())
+    }
+}
@@ -1,11 +1,12 @@
 use anyhow::*;
 
-use crate::model::{InstanceRaw, ModelVertex, Vertex};
+use crate::{config::OBJECT_ID_FORMAT, model::{InstanceRaw, ModelVertex, Vertex}, texture::Texture};
 pub struct Pipeline {
     pub pipeline: wgpu::RenderPipeline,
 }
 
 impl Pipeline {
+    #[allow(clippy::too_many_arguments)]
     pub fn build_render_pipeline(
         device: &wgpu::Device,
         config: &wgpu::SurfaceConfiguration,
@@ -13,6 +14,7 @@ impl Pipeline {
         texture_bind_group_layout: &wgpu::BindGroupLayout,
         camera_uniform_bind_group_layout: &wgpu::BindGroupLayout,
         spin_uniform_bind_group_layout: &wgpu::BindGroupLayout,
+        object_id_bind_group_layout: &wgpu::BindGroupLayout,
         is_hdr: bool,
     ) -> Result<Pipeline> {
 
@@ -30,10 +32,18 @@ impl Pipeline {
                     texture_bind_group_layout,
                     camera_uniform_bind_group_layout,
                     spin_uniform_bind_group_layout,
+                    object_id_bind_group_layout,
                 ],
                 immediate_size: 0,
             });
 
+        // Integer targets can't be resolved: multisampled, the IDs get their own pass
+        let object_id_target = (sample_count == 1).then_some(wgpu::ColorTargetState {
+            format: OBJECT_ID_FORMAT,
+            blend: None,
+            write_mask: wgpu::ColorWrites::ALL,
+        });
+
         //Pipeline
 
         let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
@@ -59,10 +69,12 @@ impl Pipeline {
                     write_mask: wgpu::ColorWrites::ALL,
                 }),
                     Some(wgpu::ColorTargetState { // Location 1
-                        format: texture_format,
+                        // Normals keep their precision in SDR too (the normal target is always Rgba16Float)
+                        format: wgpu::TextureFormat::Rgba16Float,
                         blend: None,
                         write_mask: wgpu::ColorWrites::ALL,
                     }), 
+                    object_id_target, // Location 2
                 ],
                 compilation_options: wgpu::PipelineCompilationOptions::default(),
             }),
@@ -83,9 +95,10 @@ impl Pipeline {
                 depth_write_enabled: true,
                 depth_compare: wgpu::CompareFunction::Less,
                 //stencil: wgpu::StencilState::default(),
+                // Every instance drawn whatever its group, the mask is kept for the outline passes
                 stencil: wgpu::StencilState {
                     front: wgpu::StencilFaceState {
-                        compare: wgpu::CompareFunction::Equal,
+                        compare: wgpu::CompareFunction::Always,
                         fail_op: wgpu::StencilOperation::Keep,
                         depth_fail_op: wgpu::StencilOperation::Keep,
                         pass_op: wgpu::StencilOperation::Keep,
@@ -142,7 +155,8 @@ impl Pipeline {
             primitive: wgpu::PrimitiveState::default(),
             depth_stencil: Some(wgpu::DepthStencilState {
                 format: wgpu::TextureFormat::Depth24PlusStencil8,
-                depth_write_enabled: false,
+                // Depth written: where highlight groups overlap, the nearest one's reference stays
+                depth_write_enabled: true,
                 depth_compare: wgpu::CompareFunction::Less,
                 stencil: wgpu::StencilState {
                     front: wgpu::StencilFaceState {
@@ -172,11 +186,14 @@ impl Pipeline {
     }
 
 
+    #[allow(clippy::too_many_arguments)]
     pub fn outline_pipeline(
         device: &wgpu::Device,
         config: &wgpu::SurfaceConfiguration,
         camera_uniform_bind_group_layout: &wgpu::BindGroupLayout,
         spin_uniform_bind_group_layout: &wgpu::BindGroupLayout,
+        glow_style_bind_group_layout: &wgpu::BindGroupLayout,
+        scene_depth_bind_group_layout: &wgpu::BindGroupLayout,
         sample_count: u32,
         is_hdr: bool,
     ) -> Result<Pipeline> {
@@ -191,7 +208,12 @@ impl Pipeline {
 
         let mask_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: Some("outline_pipeline_layout"),
-            bind_group_layouts: &[camera_uniform_bind_group_layout, spin_uniform_bind_group_layout],
+            bind_group_layouts: &[
+                camera_uniform_bind_group_layout,
+                spin_uniform_bind_group_layout,
+                glow_style_bind_group_layout,
+                scene_depth_bind_group_layout, // occluded parts
+            ],
             immediate_size: 0,
             //push_constant_ranges: &[],
         });
@@ -214,7 +236,7 @@ impl Pipeline {
                 module: &shader,
                 entry_point: Some("vs_main"), // 1.
                 buffers: &[ModelVertex::desc(), InstanceRaw::desc()], // 2.
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                compilation_options: Default::default(),
             },
             fragment: Some(wgpu::FragmentState {
                 // 3.
@@ -229,7 +251,7 @@ impl Pipeline {
                     //blend: None,
                     write_mask: wgpu::ColorWrites::ALL,
                 })],
-            compilation_options: wgpu::PipelineCompilationOptions::default(),
+            compilation_options: Default::default(),
             }),
             primitive: wgpu::PrimitiveState {
                 cull_mode: Some(wgpu::Face::Back), // important
@@ -279,6 +301,7 @@ impl Pipeline {
         config: &wgpu::SurfaceConfiguration,
         blur_bind_group_layout:wgpu::BindGroupLayout,
         is_hdr: bool,
+        constants: &[(&str, f64)], // pipeline-overridable constants (GlowConfig.effects)
         ) -> Result<Pipeline> {
 
         let texture_format = if is_hdr {wgpu::TextureFormat::Rgba16Float} else {config.format };
@@ -301,7 +324,10 @@ impl Pipeline {
                     module: &shader,
                     entry_point: Some("vs_main"),
                     buffers: &[], // fullscreen triangle
-                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                    compilation_options: wgpu::PipelineCompilationOptions {
+                        constants,
+                        ..Default::default()
+                    },
 
                 },
                 fragment: Some(wgpu::FragmentState {
@@ -313,7 +339,10 @@ impl Pipeline {
                         blend: None,
                         write_mask: wgpu::ColorWrites::ALL,
                     })],
-                    compilation_options: wgpu::PipelineCompilationOptions::default(),
+                    compilation_options: wgpu::PipelineCompilationOptions {
+                        constants,
+                        ..Default::default()
+                    },
                 }),
                 primitive: wgpu::PrimitiveState {
                     topology: wgpu::PrimitiveTopology::TriangleList,
@@ -329,6 +358,76 @@ impl Pipeline {
 
     }
 
+  // / One pass of the mip-chain bloom: `entry_point` is fs_prefilter, fs_downsample or fs_upsample.
+  // / Additive passes add onto the mip they draw into (upsample chain).
+  pub fn bloom_pipeline (
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        bloom_bind_group_layout: &wgpu::BindGroupLayout,
+        is_hdr: bool,
+        entry_point: &str,
+        is_additive: bool,
+        constants: &[(&str, f64)], // pipeline-overridable constants (GlowConfig.effects)
+        ) -> Result<Pipeline> {
+
+        let texture_format = if is_hdr {wgpu::TextureFormat::Rgba16Float} else {config.format };
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("bloom shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/bloom.wgsl").into()),
+        });
+
+        let bloom_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("bloom pipeline layout"),
+            bind_group_layouts: &[bloom_bind_group_layout],
+            immediate_size: 0,
+        });
+
+        let additive = wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::One,
+            dst_factor: wgpu::BlendFactor::One,
+            operation: wgpu::BlendOperation::Add,
+        };
+
+        let bloom_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+                label: Some(entry_point),
+                layout: Some(&bloom_pipeline_layout),
+                vertex: wgpu::VertexState {
+                    module: &shader,
+                    entry_point: Some("vs_main"),
+                    buffers: &[], // fullscreen triangle
+                    compilation_options: wgpu::PipelineCompilationOptions {
+                        constants,
+                        ..Default::default()
+                    },
+                },
+                fragment: Some(wgpu::FragmentState {
+                    module: &shader,
+                    entry_point: Some(entry_point),
+                    targets: &[Some(wgpu::ColorTargetState {
+                        format: texture_format,
+                        blend: is_additive.then_some(wgpu::BlendState { color: additive, alpha: additive }),
+                        write_mask: wgpu::ColorWrites::ALL,
+                    })],
+                    compilation_options: wgpu::PipelineCompilationOptions {
+                        constants,
+                        ..Default::default()
+                    },
+                }),
+                primitive: wgpu::PrimitiveState {
+                    topology: wgpu::PrimitiveTopology::TriangleList,
+                    ..Default::default()
+                },
+                depth_stencil: None,
+                multisample: wgpu::MultisampleState::default(),
+            multiview_mask: None,
+            cache: None,
+            });
+
+        Ok(Self { pipeline: bloom_pipeline })
+
+    }
+
 pub fn edge_pipeline(
     device: &wgpu::Device,
     config: &wgpu::SurfaceConfiguration,
@@ -395,7 +494,8 @@ pub fn edge_pipeline(
         device: &wgpu::Device,
         config: &wgpu::SurfaceConfiguration,
         composite_bind_group_layout:wgpu::BindGroupLayout,
-        is_hdr: bool, 
+        is_hdr: bool,
+        constants: &[(&str, f64)], // pipeline-overridable constants (GlowConfig.effects)
         ) -> Result<Pipeline> {
 
         let texture_format = if is_hdr {wgpu::TextureFormat::Rgba16Float} else {config.format };
@@ -421,7 +521,10 @@ pub fn edge_pipeline(
                 module: &shader,
                 entry_point: Some("vs_main"),
                 buffers: &[],
-                compilation_options: wgpu::PipelineCompilationOptions::default(), // fullscreen triangle
+                compilation_options: wgpu::PipelineCompilationOptions {
+                    constants,
+                    ..Default::default()
+                }, // fullscreen triangle
             },
             fragment: Some(wgpu::FragmentState {
                 module: &shader,
@@ -434,7 +537,10 @@ pub fn edge_pipeline(
                     //blend: Some(wgpu::BlendState::ALPHA_BLENDING),
                     write_mask: wgpu::ColorWrites::ALL,
                 })],
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                compilation_options: wgpu::PipelineCompilationOptions {
+                    constants,
+                    ..Default::default()
+                },
             }),
             primitive: wgpu::PrimitiveState {
                 topology: wgpu::PrimitiveTopology::TriangleList,
@@ -453,6 +559,8 @@ pub fn edge_pipeline(
         device: &wgpu::Device,
         config: &wgpu::SurfaceConfiguration,
         tone_map_bind_group_layout:wgpu::BindGroupLayout,
+        tone_map_params_bind_group_layout: &wgpu::BindGroupLayout, // operator, exposure, white point
+        constants: &[(&str, f64)], // pipeline-overridable constants (SDR pass-through, sRGB encoding)
 
     ) -> Result<Pipeline> {
         let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
@@ -463,7 +571,7 @@ pub fn edge_pipeline(
 
         let tone_map_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
             label: Some("tone map pipeline layout"),
-            bind_group_layouts: &[&tone_map_bind_group_layout],
+            bind_group_layouts: &[&tone_map_bind_group_layout, tone_map_params_bind_group_layout],
             immediate_size: 0,
         });
 
@@ -484,7 +592,10 @@ pub fn edge_pipeline(
                     blend: None,            // IMPORTANT
                     write_mask: wgpu::ColorWrites::ALL,
                 })],
-                compilation_options: wgpu::PipelineCompilationOptions::default(),
+                compilation_options: wgpu::PipelineCompilationOptions {
+                    constants,
+                    ..Default::default()
+                },
             }),
             primitive: wgpu::PrimitiveState::default(),
             depth_stencil: None,
@@ -496,6 +607,299 @@ pub fn edge_pipeline(
         Ok(Self{ pipeline: tone_map_pipeline})
     }
 
+    // / 3D LUT grading of the tone mapped frame (color_grade.wgsl), into the surface format.
+    pub fn color_grade_pipeline(
+        device: &wgpu::Device,
+        config: &wgpu::SurfaceConfiguration,
+        color_grade_bind_group_layout: &wgpu::BindGroupLayout,
+        color_lut_bind_group_layout: &wgpu::BindGroupLayout, // LUT, sampler, ColorGradeParams
+        constants: &[(&str, f64)], // pipeline-overridable constants (sRGB encoded frame)
+    ) -> Result<Pipeline> {
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("color grade shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/color_grade.wgsl").into()),
+        });
+
+        let color_grade_pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("color grade pipeline layout"),
+            bind_group_layouts: &[color_grade_bind_group_layout, color_lut_bind_group_layout],
+            immediate_size: 0,
+        });
+
+        let color_grade_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Color Grade Pipeline"),
+            layout: Some(&color_grade_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: config.format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions {
+                    constants,
+                    ..Default::default()
+                },
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview_mask: None,
+            cache: None,
+        });
+
+        Ok(Self { pipeline: color_grade_pipeline })
+    }
+
+    // / One pass of the jump flood outline (jump_flood.wgsl): `entry_point` is fs_seed,
+    // / fs_step or fs_outline. The seed pass draws where the stencil equals its reference.
+    pub fn jump_flood_pipeline(
+        device: &wgpu::Device,
+        bind_group_layouts: &[&wgpu::BindGroupLayout],
+        entry_point: &str,
+        format: wgpu::TextureFormat,
+        is_stencil_tested: bool,
+        constants: &[(&str, f64)], // pipeline-overridable constants (GlowConfig.effects)
+    ) -> Result<Pipeline> {
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("jump flood shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/jump_flood.wgsl").into()),
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("jump flood pipeline layout"),
+            bind_group_layouts,
+            immediate_size: 0,
+        });
+
+        let stencil_face = wgpu::StencilFaceState {
+            compare: wgpu::CompareFunction::Equal,
+            fail_op: wgpu::StencilOperation::Keep,
+            depth_fail_op: wgpu::StencilOperation::Keep,
+            pass_op: wgpu::StencilOperation::Keep,
+        };
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(entry_point),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[], // fullscreen triangle
+                compilation_options: wgpu::PipelineCompilationOptions {
+                    constants,
+                    ..Default::default()
+                },
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some(entry_point),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions {
+                    constants,
+                    ..Default::default()
+                },
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            // Read-only stencil mask
+            depth_stencil: is_stencil_tested.then_some(wgpu::DepthStencilState {
+                format: wgpu::TextureFormat::Depth24PlusStencil8,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState {
+                    front: stencil_face,
+                    back: stencil_face,
+                    read_mask: 0xFF,
+                    write_mask: 0x00,
+                },
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview_mask: None,
+            cache: None,
+        });
+
+        Ok(Self { pipeline })
+    }
+
+    // / One render pass of the downsample auto exposure (auto_exposure_downsample.wgsl):
+    // / `entry_point` is fs_luminance, fs_downsample or fs_adapt, into Rgba16Float.
+    pub fn auto_exposure_pipeline(
+        device: &wgpu::Device,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        entry_point: &str,
+        constants: &[(&str, f64)], // pipeline-overridable constants (GlowConfig.effects)
+    ) -> Result<Pipeline> {
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("auto exposure downsample shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/auto_exposure_downsample.wgsl").into()),
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("auto exposure downsample pipeline layout"),
+            bind_group_layouts: &[bind_group_layout],
+            immediate_size: 0,
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some(entry_point),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[], // fullscreen triangle
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some(entry_point),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: wgpu::TextureFormat::Rgba16Float,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions {
+                    constants,
+                    ..Default::default()
+                },
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview_mask: None,
+            cache: None,
+        });
+
+        Ok(Self { pipeline })
+    }
+
+    // / Fullscreen pass drawing white wherever the stencil is set.
+    // / Lets the stencil mask be read back on backends that can't copy stencil to a buffer (GL).
+    pub fn stencil_debug_pipeline(
+        device: &wgpu::Device,
+        sample_count: u32,
+    ) -> Result<Pipeline> {
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("stencil debug shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/debug.wgsl").into()),
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("stencil debug pipeline layout"),
+            bind_group_layouts: &[],
+            immediate_size: 0,
+        });
+
+        let stencil_face = wgpu::StencilFaceState {
+            compare: wgpu::CompareFunction::NotEqual, // reference 0
+            fail_op: wgpu::StencilOperation::Keep,
+            depth_fail_op: wgpu::StencilOperation::Keep,
+            pass_op: wgpu::StencilOperation::Keep,
+        };
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Stencil Debug Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_mask"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: wgpu::TextureFormat::Rgba8Unorm,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: wgpu::TextureFormat::Depth24PlusStencil8,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState {
+                    front: stencil_face,
+                    back: stencil_face,
+                    read_mask: 0xFF,
+                    write_mask: 0x00,
+                },
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: sample_count,
+                ..Default::default()
+            },
+            multiview_mask: None,
+            cache: None,
+        });
+
+        Ok(Self { pipeline })
+    }
+
+    // / Packs a depth target into an Rgba8Unorm color target, 24 bits in rgb (see `State::capture`).
+    pub fn depth_debug_pipeline(
+        device: &wgpu::Device,
+        depth_debug_bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Result<Pipeline> {
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("depth debug shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/debug.wgsl").into()),
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("depth debug pipeline layout"),
+            bind_group_layouts: &[depth_debug_bind_group_layout],
+            immediate_size: 0,
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Depth Debug Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_depth"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: wgpu::TextureFormat::Rgba8Unorm,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview_mask: None,
+            cache: None,
+        });
+
+        Ok(Self { pipeline })
+    }
+
     pub fn parallel_depth_pipeline(
         device: &wgpu::Device,
         camera_uniform_bind_group_layout: &wgpu::BindGroupLayout,
@@ -551,6 +955,70 @@ pub fn edge_pipeline(
         })       
     }
 
+    // / Object IDs of the scene in a single sampled pass (shader.wgsl fs_object_id), for
+    // / the multisampled scene: integer targets can't be resolved
+    pub fn object_id_pipeline(
+        device: &wgpu::Device,
+        texture_bind_group_layout: &wgpu::BindGroupLayout,
+        camera_uniform_bind_group_layout: &wgpu::BindGroupLayout,
+        spin_uniform_bind_group_layout: &wgpu::BindGroupLayout,
+        object_id_bind_group_layout: &wgpu::BindGroupLayout,
+    ) -> Result<Pipeline> {
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Object ID Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/shader.wgsl").into()),
+        });
+
+        // Same bind groups as the scene pass
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Object ID Pipeline Layout"),
+            bind_group_layouts: &[
+                texture_bind_group_layout,
+                camera_uniform_bind_group_layout,
+                spin_uniform_bind_group_layout,
+                object_id_bind_group_layout,
+            ],
+            immediate_size: 0,
+        });
+
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Object ID Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[ModelVertex::desc(), InstanceRaw::desc()],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_object_id"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: OBJECT_ID_FORMAT,
+                    blend: None,
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                cull_mode: Some(wgpu::Face::Back),
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: Texture::DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview_mask: None,
+            cache: None,
+        });
+
+        Ok(Self { pipeline })
+    }
 
 }
 
@@ -571,4 +1039,67 @@ pub fn edge_pipeline(
             cache: Default::default(),
         })
 
-    }
\ No newline at end of file
+    }
+    // / Separable blur in compute passes, blur_compute.wgsl (tile + apron in workgroup memory)
+    pub fn blur_compute_pipeline(
+        device: &wgpu::Device,
+        blur_bind_group_layout: &wgpu::BindGroupLayout,
+        constants: &[(&str, f64)], // pipeline-overridable constants (GlowConfig.effects)
+    ) -> wgpu::ComputePipeline {
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("blur compute shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/blur_compute.wgsl").into()),
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("blur compute pipeline layout"),
+            bind_group_layouts: &[blur_bind_group_layout],
+            immediate_size: 0,
+        });
+
+        device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("blur compute pipeline"),
+            layout: Some(&layout),
+            module: &shader,
+            entry_point: Some("cs_main"),
+            compilation_options: wgpu::PipelineCompilationOptions {
+                constants,
+                ..Default::default()
+            },
+            cache: None,
+        })
+    }
+
+    // / Histogram auto exposure in compute passes, auto_exposure.wgsl: `entry_point` is
+    // / cs_histogram or cs_average
+    pub fn auto_exposure_compute_pipeline(
+        device: &wgpu::Device,
+        bind_group_layout: &wgpu::BindGroupLayout,
+        entry_point: &str,
+        constants: &[(&str, f64)], // pipeline-overridable constants (GlowConfig.effects)
+    ) -> wgpu::ComputePipeline {
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("auto exposure compute shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/auto_exposure.wgsl").into()),
+        });
+
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("auto exposure compute pipeline layout"),
+            bind_group_layouts: &[bind_group_layout],
+            immediate_size: 0,
+        });
+
+        device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some(entry_point),
+            layout: Some(&layout),
+            module: &shader,
+            entry_point: Some(entry_point),
+            compilation_options: wgpu::PipelineCompilationOptions {
+                constants,
+                ..Default::default()
+            },
+            cache: None,
+        })
+    }
@@ -0,0 +1,269 @@
+// /
+// /  P R O F I L E R
+// /
+// / Per-pass timings of the render graph, averaged over the last frames.
+// / With Features::TIMESTAMP_QUERY every pass writes a begin/end timestamp, resolved into a
+// / small ring of readback buffers and mapped asynchronously (no stall, results lag a few frames).
+// / Without it (WebGL) the CPU time spent recording each pass is measured instead.
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use instant::Duration;
+
+// Passes with a timestamp pair per frame, the rest of a bigger graph is not timed
+const MAX_PASSES: u32 = 32;
+// Frames in flight before a frame is skipped (readback not mapped yet)
+const READBACK_COUNT: usize = 3;
+// Rolling window
+const SAMPLE_COUNT: usize = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProfilerMode {
+    GpuTimestamps,
+    // Fallback: command encoding time on the CPU, not GPU time
+    CpuEncoding,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PassTiming {
+    pub name: &'static str,
+    pub average_ms: f64,
+    pub last_ms: f64,
+}
+
+struct Readback {
+    buffer: wgpu::Buffer,
+    // Pass names of the frame in flight, None when the buffer is free
+    passes: Option<Vec<&'static str>>,
+    is_mapping: bool,
+    is_mapped: Arc<AtomicBool>,
+    // The mapping failed: the buffer is freed, its frame dropped
+    is_failed: Arc<AtomicBool>,
+}
+
+struct Timestamps {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    readbacks: Vec<Readback>,
+    // Nanoseconds per tick
+    period: f64,
+}
+
+pub struct GpuProfiler {
+    timestamps: Option<Timestamps>,
+    // Readback used by the frame being recorded
+    frame_readback: Option<usize>,
+    frame_passes: Vec<&'static str>,
+    samples: Vec<(&'static str, VecDeque<f64>)>,
+}
+
+impl GpuProfiler {
+    // / GPU timestamps when the device has TIMESTAMP_QUERY, CPU timings otherwise.
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Self {
+        let timestamps = device.features().contains(wgpu::Features::TIMESTAMP_QUERY).then(|| {
+            let size = (MAX_PASSES * 2) as u64 * wgpu::QUERY_SIZE as u64;
+
+            Timestamps {
+                query_set: device.create_query_set(&wgpu::QuerySetDescriptor {
+                    label: Some("profiler timestamps"),
+                    ty: wgpu::QueryType::Timestamp,
+                    count: MAX_PASSES * 2,
+                }),
+                resolve_buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("profiler resolve buffer"),
+                    size,
+                    usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+                    mapped_at_creation: false,
+                }),
+                readbacks: (0..READBACK_COUNT)
+                    .map(|_| Readback {
+                        buffer: device.create_buffer(&wgpu::BufferDescriptor {
+                            label: Some("profiler readback buffer"),
+                            size,
+                            usage: wgpu::BufferUsages::MAP_READ | wgpu::BufferUsages::COPY_DST,
+                            mapped_at_creation: false,
+                        }),
+                        passes: None,
+                        is_mapping: false,
+                        is_mapped: Arc::new(AtomicBool::new(false)),
+                        is_failed: Arc::new(AtomicBool::new(false)),
+                    })
+                    .collect(),
+                period: queue.get_timestamp_period() as f64,
+            }
+        });
+
+        Self {
+            timestamps,
+            frame_readback: None,
+            frame_passes: Vec::new(),
+            samples: Vec::new(),
+        }
+    }
+
+    pub fn mode(&self) -> ProfilerMode {
+        if self.timestamps.is_some() {
+            ProfilerMode::GpuTimestamps
+        } else {
+            ProfilerMode::CpuEncoding
+        }
+    }
+
+    // / Rolling average per pass, in first-seen (execution) order.
+    pub fn timings(&self) -> Vec<PassTiming> {
+        self.samples
+            .iter()
+            .filter_map(|(name, samples)| {
+                Some(PassTiming {
+                    name,
+                    average_ms: samples.iter().sum::<f64>() / samples.len() as f64,
+                    last_ms: *samples.back()?,
+                })
+            })
+            .collect()
+    }
+
+    pub fn begin_frame(&mut self) {
+        self.frame_passes.clear();
+        self.frame_readback = self
+            .timestamps
+            .as_ref()
+            .and_then(|t| t.readbacks.iter().position(|r| r.passes.is_none()));
+    }
+
+    // / Timestamp writes for the `index`-th pass of the frame (None: not timed on the GPU).
+    pub fn timestamp_writes(&self, index: usize) -> Option<wgpu::RenderPassTimestampWrites<'_>> {
+        let timestamps = self.timestamps.as_ref()?;
+        self.frame_readback?;
+
+        let index = u32::try_from(index).ok().filter(|&i| i < MAX_PASSES)?;
+
+        Some(wgpu::RenderPassTimestampWrites {
+            query_set: &timestamps.query_set,
+            beginning_of_pass_write_index: Some(index * 2),
+            end_of_pass_write_index: Some(index * 2 + 1),
+        })
+    }
+
+    // / A pass was recorded, `encoding` is the CPU time it took.
+    pub fn end_pass(&mut self, name: &'static str, encoding: Duration) {
+        if self.timestamps.is_none() {
+            self.push_sample(name, encoding.as_secs_f64() * 1000.0);
+        } else if self.frame_passes.len() < MAX_PASSES as usize {
+            self.frame_passes.push(name);
+        }
+    }
+
+    // / Resolve the timestamps of the frame into its readback buffer (before `encoder.finish`).
+    pub fn end_frame(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        let (Some(timestamps), Some(slot)) = (&mut self.timestamps, self.frame_readback.take()) else {
+            return;
+        };
+        if self.frame_passes.is_empty() {
+            return;
+        }
+
+        let count = self.frame_passes.len() as u32 * 2;
+        encoder.resolve_query_set(&timestamps.query_set, 0..count, &timestamps.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(
+            &timestamps.resolve_buffer,
+            0,
+            &timestamps.readbacks[slot].buffer,
+            0,
+            count as u64 * wgpu::QUERY_SIZE as u64,
+        );
+
+        timestamps.readbacks[slot].passes = Some(std::mem::take(&mut self.frame_passes));
+    }
+
+    // / After `queue.submit`: start mapping the new readbacks and collect the finished ones.
+    pub fn after_submit(&mut self, device: &wgpu::Device) {
+        let Some(timestamps) = &mut self.timestamps else {
+            return;
+        };
+
+        for readback in timestamps.readbacks.iter_mut() {
+            if readback.passes.is_some() && !readback.is_mapping {
+                readback.is_mapping = true;
+                let is_mapped = readback.is_mapped.clone();
+                let is_failed = readback.is_failed.clone();
+                readback.buffer.slice(..).map_async(wgpu::MapMode::Read, move |result| match result {
+                    Ok(()) => is_mapped.store(true, Ordering::Release),
+                    Err(e) => {
+                        log::warn!("profiler: cannot map the timestamps: {e}");
+                        is_failed.store(true, Ordering::Release);
+                    }
+                });
+            }
+        }
+
+        if let Err(e) = device.poll(wgpu::PollType::Poll) {
+            log::warn!("profiler: device poll failed: {e}");
+        }
+
+        let period = timestamps.period;
+        let mut finished = Vec::new();
+
+        for readback in timestamps.readbacks.iter_mut() {
+            if readback.is_failed.swap(false, Ordering::Acquire) {
+                log::warn!("profiler: timings of {} passes dropped", readback.passes.take().map_or(0, |p| p.len()));
+                readback.is_mapping = false;
+                continue;
+            }
+            if !readback.is_mapped.swap(false, Ordering::Acquire) {
+                continue;
+            }
+
+            let passes = readback.passes.take().unwrap_or_default();
+            {
+                let data = readback.buffer.slice(..).get_mapped_range();
+                let ticks: &[u64] = bytemuck::cast_slice(&data);
+
+                for (i, name) in passes.iter().enumerate() {
+                    let (begin, end) = (ticks[i * 2], ticks[i * 2 + 1]);
+                    finished.push((*name, end.saturating_sub(begin) as f64 * period / 1_000_000.0));
+                }
+            }
+            readback.buffer.unmap();
+            readback.is_mapping = false;
+        }
+
+        for (name, ms) in finished {
+            self.push_sample(name, ms);
+        }
+    }
+
+    // / One line per pass, for the log.
+    pub fn report(&self) -> String {
+        let unit = match self.mode() {
+            ProfilerMode::GpuTimestamps => "GPU",
+            ProfilerMode::CpuEncoding => "CPU encoding",
+        };
+        let passes = self
+            .timings()
+            .iter()
+            .map(|t| format!("{} {:.3}", t.name, t.average_ms))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let total = self.timings().iter().map(|t| t.average_ms).sum::<f64>();
+
+        format!("{unit} ms: {passes} (total {total:.3})")
+    }
+
+    fn push_sample(&mut self, name: &'static str, ms: f64) {
+        let samples = match self.samples.iter().position(|(n, _)| *n == name) {
+            Some(i) => &mut self.samples[i].1,
+            None => {
+                self.samples.push((name, VecDeque::with_capacity(SAMPLE_COUNT)));
+                &mut self.samples.last_mut().expect("just pushed").1
+            }
+        };
+
+        if samples.len() == SAMPLE_COUNT {
+            samples.pop_front();
+        }
+        samples.push_back(ms);
+    }
+}
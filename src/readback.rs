@@ -0,0 +1,325 @@
+// /
+// /  R E A D B A C K
+// /
+// / Copy a GPU texture into a mapped buffer and hand it back to the CPU.
+// / Rows of a texture -> buffer copy must be padded to COPY_BYTES_PER_ROW_ALIGNMENT (256 bytes).
+
+use std::path::Path;
+
+use anyhow::{anyhow, Context};
+
+// / None when a row doesn't fit the u32 `bytes_per_row` of a copy.
+pub fn padded_bytes_per_row(width: u32, bytes_per_pixel: u32) -> Option<u32> {
+    let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    width.checked_mul(bytes_per_pixel)?.checked_next_multiple_of(align)
+}
+
+// / Read one aspect of a single sampled 2D texture. Returns tightly packed rows.
+pub fn read_texture_bytes(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+    aspect: wgpu::TextureAspect,
+    bytes_per_pixel: u32,
+) -> anyhow::Result<Vec<u8>> {
+    let size = (texture.width(), texture.height());
+    read_texture_region_bytes(device, queue, texture, aspect, bytes_per_pixel, (0, 0), size)
+}
+
+// / Same for the `size` texels from `origin` (a picked pixel).
+pub fn read_texture_region_bytes(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+    aspect: wgpu::TextureAspect,
+    bytes_per_pixel: u32,
+    origin: (u32, u32),
+    size: (u32, u32),
+) -> anyhow::Result<Vec<u8>> {
+    if texture.sample_count() != 1 {
+        return Err(anyhow!("cannot read back a multisampled texture"));
+    }
+    // checked: a picked pixel far outside the window must not wrap around
+    let fits = |start: u32, len: u32, limit: u32| start.checked_add(len).is_some_and(|end| end <= limit);
+    if !fits(origin.0, size.0, texture.width()) || !fits(origin.1, size.1, texture.height()) {
+        return Err(anyhow!(
+            "region {size:?} at {origin:?} is outside the {}x{} texture",
+            texture.width(),
+            texture.height()
+        ));
+    }
+
+    let (width, height) = size;
+    let padded_row = padded_bytes_per_row(width, bytes_per_pixel)
+        .ok_or_else(|| anyhow!("rows of {width} texels of {bytes_per_pixel} bytes are too large to read back"))?;
+    let unpadded_row = width as usize * bytes_per_pixel as usize;
+
+    let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("readback buffer"),
+        // u64: a large float texture is past u32 bytes
+        size: padded_row as u64 * height as u64,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("readback encoder"),
+    });
+
+    encoder.copy_texture_to_buffer(
+        wgpu::TexelCopyTextureInfo {
+            texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d { x: origin.0, y: origin.1, z: 0 },
+            aspect,
+        },
+        wgpu::TexelCopyBufferInfo {
+            buffer: &buffer,
+            layout: wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_row),
+                rows_per_image: Some(height),
+            },
+        },
+        wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        },
+    );
+
+    queue.submit(std::iter::once(encoder.finish()));
+
+    // Block until the copy is done (native only, on the web poll() does not wait)
+    let slice = buffer.slice(..);
+    let (sender, receiver) = std::sync::mpsc::channel();
+    slice.map_async(wgpu::MapMode::Read, move |result| {
+        let _ = sender.send(result);
+    });
+    device.poll(wgpu::PollType::wait_indefinitely())?;
+    receiver.recv()??;
+
+    let mut pixels = Vec::with_capacity(unpadded_row * height as usize);
+    {
+        let data = slice.get_mapped_range();
+        for row in data.chunks(padded_row as usize) {
+            pixels.extend_from_slice(&row[..unpadded_row]);
+        }
+    }
+    buffer.unmap();
+
+    Ok(pixels)
+}
+
+// / Read an 8 bit color texture (Rgba8 or Bgra8, sRGB or not) as an RGBA image.
+pub fn read_texture_rgba8(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+) -> anyhow::Result<image::RgbaImage> {
+    let format = texture.format();
+    let is_bgra = match format {
+        wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => false,
+        wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => true,
+        _ => return Err(anyhow!("unsupported readback format {:?}", format)),
+    };
+
+    let mut pixels = read_texture_bytes(device, queue, texture, wgpu::TextureAspect::All, 4)?;
+
+    if is_bgra {
+        for pixel in pixels.chunks_exact_mut(4) {
+            pixel.swap(0, 2);
+        }
+    }
+
+    image::RgbaImage::from_raw(texture.width(), texture.height(), pixels)
+        .ok_or(anyhow!("readback size mismatch"))
+}
+
+// / Read any color target as an RGBA image. Float (HDR) targets are clamped to [0, 1]
+// / and sRGB encoded, so they can be stored as PNG.
+pub fn read_texture_rgba(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+) -> anyhow::Result<image::RgbaImage> {
+    Ok(Capture::read(device, queue, texture)?.to_rgba8())
+}
+
+// / Read an R32Uint target (object IDs).
+pub fn read_texture_u32(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+) -> anyhow::Result<ObjectIdImage> {
+    if texture.format() != wgpu::TextureFormat::R32Uint {
+        return Err(anyhow!("unsupported integer readback format {:?}", texture.format()));
+    }
+
+    let ids = read_texture_bytes(device, queue, texture, wgpu::TextureAspect::All, 4)?
+        .chunks_exact(4)
+        .map(|id| u32::from_le_bytes([id[0], id[1], id[2], id[3]]))
+        .collect::<Vec<u32>>();
+
+    ObjectIdImage::from_raw(texture.width(), texture.height(), ids).ok_or(anyhow!("readback size mismatch"))
+}
+
+// / Read an Rgba16Float target as linear RGBA, values above 1 kept.
+pub fn read_texture_rgba32f(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+) -> anyhow::Result<image::Rgba32FImage> {
+    let pixels = match texture.format() {
+        wgpu::TextureFormat::Rgba16Float => read_texture_bytes(device, queue, texture, wgpu::TextureAspect::All, 8)?
+            .chunks_exact(2)
+            .map(|half| f16_to_f32(u16::from_le_bytes([half[0], half[1]])))
+            .collect::<Vec<f32>>(),
+        format => return Err(anyhow!("unsupported float readback format {:?}", format)),
+    };
+
+    image::Rgba32FImage::from_raw(texture.width(), texture.height(), pixels)
+        .ok_or(anyhow!("readback size mismatch"))
+}
+
+// /
+// /  C A P T U R E
+// /
+// / A target read back to the CPU, saved by file extension:
+// / .png for every target, .exr (OpenEXR) and .hdr (Radiance) keep the float values.
+
+pub enum Capture {
+    // 8 bit target, as stored (sRGB targets are already encoded)
+    Rgba8(image::RgbaImage),
+    // Float (HDR) target, linear
+    Float(image::Rgba32FImage),
+    // Depth buffer, 0 (near) .. 1 (far / cleared) in every color channel
+    Depth(image::Rgba32FImage),
+    // Object IDs (model::ObjectId), 0 for the background
+    ObjectId(ObjectIdImage),
+}
+
+pub type ObjectIdImage = image::ImageBuffer<image::Luma<u32>, Vec<u32>>;
+
+impl Capture {
+    // / Read any single sampled color target.
+    pub fn read(device: &wgpu::Device, queue: &wgpu::Queue, texture: &wgpu::Texture) -> anyhow::Result<Self> {
+        match texture.format() {
+            wgpu::TextureFormat::Rgba16Float => {
+                Ok(Capture::Float(read_texture_rgba32f(device, queue, texture)?))
+            }
+            wgpu::TextureFormat::R32Uint => Ok(Capture::ObjectId(read_texture_u32(device, queue, texture)?)),
+            _ => Ok(Capture::Rgba8(read_texture_rgba8(device, queue, texture)?)),
+        }
+    }
+
+    pub fn is_hdr(&self) -> bool {
+        matches!(self, Capture::Float(_))
+    }
+
+    pub fn dimensions(&self) -> (u32, u32) {
+        match self {
+            Capture::Rgba8(image) => image.dimensions(),
+            Capture::Float(image) | Capture::Depth(image) => image.dimensions(),
+            Capture::ObjectId(image) => image.dimensions(),
+        }
+    }
+
+    // / 8 bit version, for PNG. Float targets are clamped to [0, 1] and sRGB encoded,
+    // / depth is stretched over the depth range of the geometry (near = white, background black),
+    // / each object ID gets a color of its own (background black).
+    pub fn to_rgba8(&self) -> image::RgbaImage {
+        match self {
+            Capture::Rgba8(image) => image.clone(),
+            Capture::Float(image) => {
+                let pixels = image
+                    .as_raw()
+                    .iter()
+                    .enumerate()
+                    .map(|(i, &value)| {
+                        // Alpha stays linear
+                        if i % 4 == 3 {
+                            unit_to_u8(value)
+                        } else {
+                            unit_to_u8(crate::utils::linear_to_srgb(value.clamp(0.0, 1.0) as f64) as f32)
+                        }
+                    })
+                    .collect::<Vec<u8>>();
+
+                image::RgbaImage::from_raw(image.width(), image.height(), pixels).expect("same size")
+            }
+            Capture::Depth(image) => {
+                let (near, far) = image
+                    .pixels()
+                    .map(|p| p[0])
+                    .filter(|&depth| depth < 1.0)
+                    .fold((1.0f32, 0.0f32), |(near, far), depth| (near.min(depth), far.max(depth)));
+                let range = (far - near).max(f32::EPSILON);
+
+                image::RgbaImage::from_fn(image.width(), image.height(), |x, y| {
+                    let depth = image.get_pixel(x, y)[0];
+                    let v = if depth < 1.0 { unit_to_u8(1.0 - 0.8 * (depth - near) / range) } else { 0 };
+                    image::Rgba([v, v, v, 255])
+                })
+            }
+            Capture::ObjectId(image) => image::RgbaImage::from_fn(image.width(), image.height(), |x, y| {
+                object_id_color(image.get_pixel(x, y)[0])
+            }),
+        }
+    }
+
+    // / Float version, for OpenEXR / Radiance HDR. 8 bit targets are converted as stored (value / 255).
+    pub fn to_rgba32f(&self) -> image::Rgba32FImage {
+        match self {
+            Capture::Float(image) | Capture::Depth(image) => image.clone(),
+            Capture::Rgba8(image) => image::DynamicImage::ImageRgba8(image.clone()).into_rgba32f(),
+            Capture::ObjectId(_) => image::DynamicImage::ImageRgba8(self.to_rgba8()).into_rgba32f(),
+        }
+    }
+
+    // / Write a .png, .exr or .hdr file (format from the extension).
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase())
+            .unwrap_or_default();
+
+        let result = match extension.as_str() {
+            "png" => self.to_rgba8().save(path),
+            "exr" => self.to_rgba32f().save(path),
+            // Radiance HDR has no alpha
+            "hdr" => image::DynamicImage::ImageRgba32F(self.to_rgba32f()).into_rgb32f().save(path),
+            _ => return Err(anyhow!("unsupported capture format '{}', expected .png, .exr or .hdr", path.display())),
+        };
+
+        result.with_context(|| format!("cannot write capture '{}'", path.display()))
+    }
+}
+
+// / Hashed, never too dark so neighbouring IDs stand out
+fn object_id_color(id: u32) -> image::Rgba<u8> {
+    if id == 0 {
+        return image::Rgba([0, 0, 0, 255]);
+    }
+    let [r, g, b, _] = id.wrapping_mul(0x9e37_79b1).to_be_bytes();
+    image::Rgba([r | 0x40, g | 0x40, b | 0x40, 255])
+}
+
+fn unit_to_u8(value: f32) -> u8 {
+    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+// / IEEE 754 half -> single precision.
+pub fn f16_to_f32(bits: u16) -> f32 {
+    let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((bits >> 10) & 0x1f) as i32;
+    let mantissa = (bits & 0x3ff) as f32;
+
+    match exponent {
+        0 => sign * mantissa * 2f32.powi(-24),
+        0x1f if mantissa == 0.0 => sign * f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => sign * (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+    }
+}
@@ -0,0 +1,153 @@
+// /
+// /  T U R N T A B L E   R E C O R D E R
+// /
+// / Native only. Renders the spinning model offscreen with a fixed time step, so the
+// / frames don't depend on the real frame rate, and writes them as an animated GIF
+// / (`*.gif`) or as a PNG sequence (any other path: a directory of frame_0000.png ...).
+// /
+// /     glow_web --record fly_glow.gif --window-size 480x270 --fps 25
+// /     glow_web --record frames/ --record-seconds 4
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{anyhow, Context};
+use image::codecs::gif::{GifEncoder, Repeat};
+
+use crate::{config::GlowConfig, state::State};
+
+pub const DEFAULT_RECORD_FPS: u32 = 30;
+
+// NeuQuant sampling of the GIF palette: 1 (best, slowest) .. 30
+const GIF_QUANTIZER_SPEED: i32 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RecordLength {
+    // One full turn of the model; the last frame joins the first (seamless loop)
+    Revolution,
+    Seconds(f32),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recording {
+    pub output: PathBuf,
+    pub width: u32,
+    pub height: u32,
+    pub fps: u32,
+    pub length: RecordLength,
+}
+
+impl Recording {
+    pub fn is_gif(&self) -> bool {
+        self.output
+            .extension()
+            .is_some_and(|e| e.eq_ignore_ascii_case("gif"))
+    }
+
+    // / Frame count and time step (seconds). A revolution is split in whole frames, the
+    // / step is adjusted (from 1 / fps) so they add up to exactly one turn.
+    pub fn frames(&self, spin_speed: f32) -> anyhow::Result<(u32, f64)> {
+        if self.fps == 0 {
+            return Err(anyhow!("recording: fps must be at least 1"));
+        }
+        let frame_time = 1.0 / self.fps as f64;
+
+        match self.length {
+            RecordLength::Revolution => {
+                if spin_speed == 0.0 {
+                    return Err(anyhow!("recording: spin_speed is 0, the model never completes a revolution"));
+                }
+                let period = std::f64::consts::TAU / spin_speed.abs() as f64;
+                let frames = ((period / frame_time).round() as u32).max(1);
+                Ok((frames, period / frames as f64))
+            }
+            RecordLength::Seconds(seconds) if seconds > 0.0 => {
+                Ok((((seconds as f64 / frame_time).round() as u32).max(1), frame_time))
+            }
+            RecordLength::Seconds(seconds) => Err(anyhow!("recording: length must be positive, got {seconds}s")),
+        }
+    }
+}
+
+// / Render the turntable offscreen and write it. Returns the number of frames.
+pub async fn record_turntable(config: GlowConfig, recording: &Recording) -> anyhow::Result<u32> {
+    let spin_speed = config.scene.spin_speed;
+    let (frames, dt) = recording.frames(spin_speed)?;
+
+    let mut state = State::new_headless(recording.width, recording.height, config).await?;
+
+    let mut sink = FrameSink::new(recording)?;
+
+    for frame in 0..frames {
+        // Angle from the frame index, not accumulated: no drift over long recordings
+        let angle = frame as f64 * dt * spin_speed as f64;
+        state.set_spin_angle(angle as f32);
+        // The spin stays pinned, the time step still drives the auto exposure
+        state.advance(dt as f32);
+
+        let image = state.render_to_image()?;
+        sink.write(frame, image)?;
+    }
+
+    // The GIF trailer is written when the encoder is dropped
+    drop(sink);
+
+    log::info!(
+        "recorded {frames} frames ({}x{}, {} fps) to {}",
+        recording.width,
+        recording.height,
+        recording.fps,
+        recording.output.display()
+    );
+
+    Ok(frames)
+}
+
+enum FrameSink {
+    Gif(Box<GifEncoder<std::io::BufWriter<std::fs::File>>>, image::Delay),
+    Png(PathBuf),
+}
+
+impl FrameSink {
+    fn new(recording: &Recording) -> anyhow::Result<Self> {
+        let path = &recording.output;
+
+        if recording.is_gif() {
+            if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                std::fs::create_dir_all(parent)?;
+            }
+            let file = std::fs::File::create(path)
+                .with_context(|| format!("cannot create '{}'", path.display()))?;
+
+            let mut encoder = GifEncoder::new_with_speed(std::io::BufWriter::new(file), GIF_QUANTIZER_SPEED);
+            encoder.set_repeat(Repeat::Infinite)?;
+
+            // GIF delays are in 1/100 s, the frame rate is rounded to that
+            let delay = image::Delay::from_numer_denom_ms(1000, recording.fps);
+
+            Ok(FrameSink::Gif(Box::new(encoder), delay))
+        } else {
+            std::fs::create_dir_all(path)
+                .with_context(|| format!("cannot create frame directory '{}'", path.display()))?;
+            Ok(FrameSink::Png(path.clone()))
+        }
+    }
+
+    fn write(&mut self, index: u32, image: image::RgbaImage) -> anyhow::Result<()> {
+        match self {
+            FrameSink::Gif(encoder, delay) => {
+                encoder.encode_frame(image::Frame::from_parts(image, 0, 0, *delay))?;
+            }
+            FrameSink::Png(dir) => {
+                let path = frame_path(dir, index);
+                image
+                    .save(&path)
+                    .with_context(|| format!("cannot write frame '{}'", path.display()))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn frame_path(dir: &Path, index: u32) -> PathBuf {
+    dir.join(format!("frame_{index:04}.png"))
+}
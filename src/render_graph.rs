@@ -0,0 +1,496 @@
+// /
+// /  R E N D E R   G R A P H
+// /
+// / Passes (nodes) declare the targets they read and write, with format and sample count.
+// / The graph allocates the transient targets at screen size (or a fixed one), reallocates them on resize
+// / and records the passes in dependency order.
+// /
+// / Ordering rule: a pass runs after the previous writer of every target it touches
+// / (and after the earlier readers of a target it overwrites). A target read before any
+// / writer in insertion order waits for all of its writers.
+
+use std::collections::HashMap;
+
+use anyhow::{anyhow, bail};
+
+use instant::Instant;
+
+use crate::{camera::Camera, model::Model, profiler::GpuProfiler};
+
+pub type ResourceId = &'static str;
+
+// / The swapchain (or the headless output texture). Owned by the caller, never allocated here.
+pub const OUTPUT: ResourceId = "output";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TargetDesc {
+    pub format: wgpu::TextureFormat,
+    pub sample_count: u32,
+    // Written by compute nodes as a storage texture
+    pub is_storage: bool,
+    // Fixed size (width, height) instead of the screen size
+    pub size: Option<(u32, u32)>,
+}
+
+impl TargetDesc {
+    pub fn new(format: wgpu::TextureFormat, sample_count: u32) -> Self {
+        Self { format, sample_count, is_storage: false, size: None }
+    }
+
+    // / Single sampled, also bindable as a storage texture
+    pub fn storage(format: wgpu::TextureFormat) -> Self {
+        Self { format, sample_count: 1, is_storage: true, size: None }
+    }
+
+    // / Same size whatever the screen (1x1 exposure)
+    pub fn with_size(self, width: u32, height: u32) -> Self {
+        Self { size: Some((width, height)), ..self }
+    }
+
+    fn usage(&self) -> wgpu::TextureUsages {
+        // Multisampled + TEXTURE_BINDING is not supported by the GL backend
+        if self.sample_count > 1 {
+            return wgpu::TextureUsages::RENDER_ATTACHMENT;
+        }
+
+        let mut usage = wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING;
+        if self.is_storage {
+            usage |= wgpu::TextureUsages::STORAGE_BINDING;
+        }
+
+        // COPY_SRC so intermediates can be read back (tests, captures)
+        if self.format.is_depth_stencil_format() {
+            usage
+        } else {
+            usage | wgpu::TextureUsages::COPY_SRC
+        }
+    }
+}
+
+pub struct Target {
+    pub desc: TargetDesc,
+    pub texture: wgpu::Texture,
+    pub view: wgpu::TextureView,
+}
+
+// / Transient targets of the graph, by name.
+#[derive(Default)]
+pub struct Targets {
+    targets: HashMap<ResourceId, Target>,
+    width: u32,
+    height: u32,
+}
+
+impl Targets {
+    pub fn get(&self, id: ResourceId) -> anyhow::Result<&Target> {
+        self.targets
+            .get(id)
+            .ok_or(anyhow!("render graph target '{id}' is not allocated"))
+    }
+
+    pub fn view(&self, id: ResourceId) -> anyhow::Result<&wgpu::TextureView> {
+        Ok(&self.get(id)?.view)
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+}
+
+// / What the passes draw. Borrowed from `State` for the duration of a frame.
+pub struct SceneData<'a> {
+    pub model: &'a Model,
+    pub instance_buffer: &'a wgpu::Buffer,
+    pub instance_count: u32,
+    pub camera_bind_group: &'a wgpu::BindGroup,
+    pub spin_bind_group: &'a wgpu::BindGroup,
+    // Outline of the model (GlowStyleUniform)
+    pub glow_bind_group: &'a wgpu::BindGroup,
+    // Runs of instances in the same highlight group: (stencil reference, instances)
+    pub highlight_runs: &'a [(u8, std::ops::Range<u32>)],
+    // Clip planes of the depth prepass (edge pass)
+    pub camera: &'a Camera,
+    // Operator, exposure and white point (ToneMapParams)
+    pub tone_map_bind_group: &'a wgpu::BindGroup,
+    // Seconds since the previous frame (auto exposure adaptation)
+    pub delta_time: f32,
+    // LUT of the color grade pass (ColorGradeParams), None without color grading
+    pub color_lut_bind_group: Option<&'a wgpu::BindGroup>,
+}
+
+pub struct FrameContext<'a> {
+    pub queue: &'a wgpu::Queue,
+    pub targets: &'a Targets,
+    pub output: &'a wgpu::TextureView,
+    pub scene: &'a SceneData<'a>,
+    // Set when the profiler times this pass on the GPU
+    pub timestamp_writes: Option<wgpu::RenderPassTimestampWrites<'a>>,
+}
+
+impl FrameContext<'_> {
+    // / View of a graph target, or of the output for `OUTPUT`.
+    pub fn view(&self, id: ResourceId) -> anyhow::Result<&wgpu::TextureView> {
+        if id == OUTPUT {
+            Ok(self.output)
+        } else {
+            self.targets.view(id)
+        }
+    }
+}
+
+// / A pass of the graph. Every texture it renders into or samples is a declared target,
+// / allocated by the graph, with two exceptions a node may keep private:
+// / - mip chains whose number of levels depends on the screen size (bloom, luminance
+// /   downsample), which a fixed list of `writes` can't declare. Rebuilt in `resize`.
+// / - textures that outlive a frame (previous exposure) or never change (placeholders),
+// /   which transient targets reallocated on resize can't hold.
+pub trait RenderNode {
+    fn name(&self) -> &'static str;
+
+    fn reads(&self) -> Vec<ResourceId>;
+
+    fn writes(&self) -> Vec<(ResourceId, TargetDesc)>;
+
+    // / Targets were (re)allocated: rebuild the bind groups that point at them
+    // / (and the private mip chains, at the new size).
+    fn resize(&mut self, _device: &wgpu::Device, _targets: &Targets) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    fn record(&self, encoder: &mut wgpu::CommandEncoder, ctx: &FrameContext) -> anyhow::Result<()>;
+}
+
+#[derive(Default)]
+pub struct RenderGraph {
+    nodes: Vec<Box<dyn RenderNode>>,
+    order: Vec<usize>,
+    descs: HashMap<ResourceId, TargetDesc>,
+    targets: Targets,
+    is_compiled: bool,
+}
+
+impl RenderGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_node(&mut self, node: impl RenderNode + 'static) -> &mut Self {
+        self.nodes.push(Box::new(node));
+        self.is_compiled = false;
+        self
+    }
+
+    // / Insert a node before the node called `before` (its inputs may then come from it).
+    pub fn insert_node_before(
+        &mut self,
+        before: &str,
+        node: impl RenderNode + 'static,
+    ) -> anyhow::Result<&mut Self> {
+        let index = self
+            .position(before)
+            .ok_or(anyhow!("no render graph node called '{before}'"))?;
+        self.nodes.insert(index, Box::new(node));
+        self.is_compiled = false;
+        Ok(self)
+    }
+
+    pub fn remove_node(&mut self, name: &str) -> Option<Box<dyn RenderNode>> {
+        let index = self.position(name)?;
+        self.is_compiled = false;
+        Some(self.nodes.remove(index))
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.nodes.iter().position(|node| node.name() == name)
+    }
+
+    // / Node names in execution order (after compile).
+    pub fn pass_names(&self) -> Vec<&'static str> {
+        self.order.iter().map(|&i| self.nodes[i].name()).collect()
+    }
+
+    pub fn targets(&self) -> &Targets {
+        &self.targets
+    }
+
+    // / Collect the target descriptions and sort the nodes.
+    pub fn compile(&mut self) -> anyhow::Result<()> {
+        let mut descs: HashMap<ResourceId, TargetDesc> = HashMap::new();
+        let mut writers: HashMap<ResourceId, Vec<usize>> = HashMap::new();
+
+        for (i, node) in self.nodes.iter().enumerate() {
+            // Nodes are found by name (edits, timings)
+            if self.nodes[..i].iter().any(|other| other.name() == node.name()) {
+                bail!("two render graph nodes called '{}'", node.name());
+            }
+            for (id, desc) in node.writes() {
+                if let Some(previous) = descs.insert(id, desc)
+                    && previous != desc
+                {
+                    bail!(
+                        "render graph target '{id}' declared as {previous:?} and {desc:?} (node '{}')",
+                        node.name()
+                    );
+                }
+                writers.entry(id).or_default().push(i);
+            }
+        }
+
+        let mut readers: HashMap<ResourceId, Vec<usize>> = HashMap::new();
+        for (i, node) in self.nodes.iter().enumerate() {
+            for id in node.reads() {
+                if !writers.contains_key(id) {
+                    bail!("render graph node '{}' reads '{id}' that nobody writes", node.name());
+                }
+                readers.entry(id).or_default().push(i);
+            }
+        }
+
+        // dependencies[i] = nodes that must run before i
+        let mut dependencies: Vec<Vec<usize>> = vec![Vec::new(); self.nodes.len()];
+
+        for (i, node) in self.nodes.iter().enumerate() {
+            let written = node.writes().into_iter().map(|(id, _)| id).collect::<Vec<_>>();
+
+            for id in node.reads().into_iter().chain(written.iter().copied()) {
+                let all_writers = &writers[id];
+                match all_writers.iter().rev().find(|&&w| w < i) {
+                    Some(&w) => dependencies[i].push(w),
+                    // Read before any writer in insertion order: wait for all of them
+                    None if !written.contains(&id) => dependencies[i].extend(all_writers.iter().copied()),
+                    None => {}
+                }
+            }
+
+            // Don't overwrite a target before the earlier readers are done with it
+            // (a reader before any writer waits for this one instead)
+            for id in &written {
+                if let Some(earlier) = readers.get(id) {
+                    let all_writers = &writers[id];
+                    dependencies[i].extend(
+                        earlier.iter().copied().filter(|&r| r < i && all_writers.iter().any(|&w| w <= r)),
+                    );
+                }
+            }
+
+            dependencies[i].retain(|&d| d != i);
+        }
+
+        self.order = topological_order(&dependencies).ok_or_else(|| {
+            anyhow!("render graph has a cycle")
+        })?;
+        self.descs = descs;
+        self.is_compiled = true;
+
+        Ok(())
+    }
+
+    // / (Re)allocate every target at the new size and let the nodes rebuild their bind groups.
+    pub fn resize(&mut self, device: &wgpu::Device, width: u32, height: u32) -> anyhow::Result<()> {
+        if !self.is_compiled {
+            self.compile()?;
+        }
+
+        let size = wgpu::Extent3d {
+            width: width.max(1),
+            height: height.max(1),
+            depth_or_array_layers: 1,
+        };
+
+        self.targets = Targets {
+            targets: self
+                .descs
+                .iter()
+                .filter(|(id, _)| **id != OUTPUT)
+                .map(|(&id, &desc)| {
+                    let size = match desc.size {
+                        Some((width, height)) => wgpu::Extent3d { width, height, depth_or_array_layers: 1 },
+                        None => size,
+                    };
+                    let texture = device.create_texture(&wgpu::TextureDescriptor {
+                        label: Some(id),
+                        size,
+                        mip_level_count: 1,
+                        sample_count: desc.sample_count,
+                        dimension: wgpu::TextureDimension::D2,
+                        format: desc.format,
+                        usage: desc.usage(),
+                        view_formats: &[],
+                    });
+                    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+                    (id, Target { desc, texture, view })
+                })
+                .collect(),
+            width: size.width,
+            height: size.height,
+        };
+
+        for node in self.nodes.iter_mut() {
+            node.resize(device, &self.targets)?;
+        }
+
+        Ok(())
+    }
+
+    // / Record every pass. Targets must have been allocated by `resize`.
+    // / With a profiler, each pass is timed (GPU timestamps or CPU encoding time).
+    pub fn execute(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        queue: &wgpu::Queue,
+        scene: &SceneData,
+        output: &wgpu::TextureView,
+        mut profiler: Option<&mut GpuProfiler>,
+    ) -> anyhow::Result<()> {
+        if !self.is_compiled {
+            bail!("render graph changed since the last resize");
+        }
+
+        if let Some(profiler) = profiler.as_deref_mut() {
+            profiler.begin_frame();
+        }
+
+        for (index, &i) in self.order.iter().enumerate() {
+            let ctx = FrameContext {
+                queue,
+                targets: &self.targets,
+                output,
+                scene,
+                timestamp_writes: profiler.as_deref().and_then(|p| p.timestamp_writes(index)),
+            };
+
+            let start = Instant::now();
+            self.nodes[i].record(encoder, &ctx)?;
+            let encoding = start.elapsed();
+
+            if let Some(profiler) = profiler.as_deref_mut() {
+                profiler.end_pass(self.nodes[i].name(), encoding);
+            }
+        }
+
+        if let Some(profiler) = profiler {
+            profiler.end_frame(encoder);
+        }
+
+        Ok(())
+    }
+}
+
+// / Kahn's algorithm, lowest insertion index first so independent passes keep their order.
+fn topological_order(dependencies: &[Vec<usize>]) -> Option<Vec<usize>> {
+    let count = dependencies.len();
+    let mut remaining = dependencies.iter().map(|d| {
+        let mut d = d.clone();
+        d.sort_unstable();
+        d.dedup();
+        d.len()
+    }).collect::<Vec<_>>();
+    let mut done = vec![false; count];
+    let mut order = Vec::with_capacity(count);
+
+    while order.len() < count {
+        let next = (0..count).find(|&i| !done[i] && remaining[i] == 0)?;
+        done[next] = true;
+        order.push(next);
+
+        for (i, deps) in dependencies.iter().enumerate() {
+            if !done[i] && deps.contains(&next) {
+                remaining[i] -= 1;
+            }
+        }
+    }
+
+    Some(order)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+
+    // Declares its targets, records nothing
+    struct Stub {
+        name: &'static str,
+        reads: Vec<ResourceId>,
+        writes: Vec<ResourceId>,
+    }
+
+    impl RenderNode for Stub {
+        fn name(&self) -> &'static str { self.name }
+
+        fn reads(&self) -> Vec<ResourceId> { self.reads.clone() }
+
+        fn writes(&self) -> Vec<(ResourceId, TargetDesc)> {
+            self.writes.iter().map(|&id| (id, TargetDesc::new(FORMAT, 1))).collect()
+        }
+
+        fn record(&self, _encoder: &mut wgpu::CommandEncoder, _ctx: &FrameContext) -> anyhow::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn stub(name: &'static str, reads: &[ResourceId], writes: &[ResourceId]) -> Stub {
+        Stub { name, reads: reads.to_vec(), writes: writes.to_vec() }
+    }
+
+    fn compiled(nodes: Vec<Stub>) -> anyhow::Result<Vec<&'static str>> {
+        let mut graph = RenderGraph::new();
+        for node in nodes {
+            graph.add_node(node);
+        }
+        graph.compile()?;
+        Ok(graph.pass_names())
+    }
+
+    #[test]
+    fn passes_run_after_their_inputs() {
+        let order = compiled(vec![
+            stub("tone map", &["scene", "bloom"], &[OUTPUT]),
+            stub("bloom", &["outline"], &["bloom"]),
+            stub("scene", &[], &["scene"]),
+            stub("outline", &[], &["outline"]),
+        ])
+        .expect("valid graph");
+        assert_eq!(order, ["scene", "outline", "bloom", "tone map"]);
+
+        // Independent passes keep their insertion order
+        let order = compiled(vec![stub("b", &[], &["b"]), stub("a", &[], &["a"])]).expect("valid graph");
+        assert_eq!(order, ["b", "a"]);
+    }
+
+    #[test]
+    fn invalid_graphs_are_rejected() {
+        let e = compiled(vec![stub("a", &["y"], &["x"]), stub("b", &["x"], &["y"])]).expect_err("cycle");
+        assert!(e.to_string().contains("cycle"), "'{e}'");
+
+        let e = compiled(vec![stub("a", &["missing"], &["x"])]).expect_err("read of an unwritten target");
+        assert!(e.to_string().contains("nobody writes"), "'{e}'");
+
+        let e = compiled(vec![stub("blur", &[], &["x"]), stub("blur", &["x"], &["y"])]).expect_err("duplicate name");
+        assert!(e.to_string().contains("two render graph nodes called 'blur'"), "'{e}'");
+    }
+
+    #[test]
+    fn targets_are_not_overwritten_before_their_readers() {
+        // "reuse" is free to run right after "scratch" but "draw" still reads the scratch target
+        let order = compiled(vec![
+            stub("scratch", &[], &["scratch"]),
+            stub("draw", &["scratch", "source"], &["color"]),
+            stub("reuse", &[], &["scratch"]),
+            stub("source", &[], &["source"]),
+        ])
+        .expect("valid graph");
+        assert_eq!(order, ["scratch", "source", "draw", "reuse"]);
+
+        // Read then written by the same pass: after the previous writer, before the next reader
+        let order = compiled(vec![
+            stub("clear", &[], &["blur"]),
+            stub("blur", &["blur", "source"], &["blur"]),
+            stub("present", &["blur"], &[OUTPUT]),
+            stub("source", &[], &["source"]),
+        ])
+        .expect("valid graph");
+        assert_eq!(order, ["clear", "source", "blur", "present"]);
+    }
+}
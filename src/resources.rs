@@ -1,5 +1,7 @@
 use std::io::{self, BufReader, Cursor};
 
+use anyhow::{anyhow, bail, Context};
+
 use wgpu::util::DeviceExt;
 
 use crate::{model, texture::{self, Texture}, utils::rgba_f32_to_u8};
@@ -61,6 +63,118 @@ pub async fn load_texture(
     texture::Texture::get_texture_from_image(device, queue, file_name).await
 }
 
+// / Largest LUT_3D_SIZE accepted (the 3D texture limit of WebGL 2)
+pub const MAX_LUT_SIZE: u32 = 256;
+
+// / 3D color LUT of an Adobe .cube file: size³ output colors, red varying fastest, then
+// / green, then blue. Inputs in [domain_min, domain_max] span the whole table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CubeLut {
+    pub title: Option<String>,
+    pub size: u32,
+    pub domain_min: [f32; 3],
+    pub domain_max: [f32; 3],
+    pub data: Vec<[f32; 3]>,
+}
+
+impl CubeLut {
+    // / The LUT that leaves every color as is
+    pub fn identity(size: u32) -> Self {
+        let step = 1.0 / (size.max(2) - 1) as f32;
+        let data = (0..size * size * size)
+            .map(|i| [i % size, i / size % size, i / (size * size)].map(|c| c as f32 * step))
+            .collect();
+
+        Self { title: None, size, domain_min: [0.0; 3], domain_max: [1.0; 3], data }
+    }
+
+    // / Parse the text of a .cube file. 1D LUTs (LUT_1D_SIZE) are not supported.
+    pub fn parse(text: &str) -> anyhow::Result<Self> {
+        let mut title = None;
+        let mut size = None;
+        let mut domain_min = [0.0; 3];
+        let mut domain_max = [1.0; 3];
+        let mut data = Vec::new();
+
+        let number = |v: &str, line: usize| {
+            v.parse::<f32>()
+                .ok()
+                .filter(|v| v.is_finite())
+                .ok_or_else(|| anyhow!("line {line}: '{v}' is not a number"))
+        };
+        let triple = |values: &[&str], line: usize| -> anyhow::Result<[f32; 3]> {
+            match values {
+                [r, g, b] => Ok([number(r, line)?, number(g, line)?, number(b, line)?]),
+                _ => bail!("line {line}: expected 3 values, got {}", values.len()),
+            }
+        };
+
+        for (i, line) in text.lines().enumerate() {
+            let line_number = i + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let mut words = line.split_whitespace();
+            let keyword = words.next().unwrap_or_default();
+            let values = words.collect::<Vec<_>>();
+            match keyword {
+                "TITLE" => {
+                    let rest = line["TITLE".len()..].trim();
+                    title = Some(rest.trim_matches('"').to_string());
+                }
+                "LUT_3D_SIZE" => {
+                    let n = match values.as_slice() {
+                        [n] => n.parse::<u32>().ok(),
+                        _ => None,
+                    };
+                    match n {
+                        Some(n) if (2..=MAX_LUT_SIZE).contains(&n) => size = Some(n),
+                        _ => bail!("line {line_number}: LUT_3D_SIZE must be in [2, {MAX_LUT_SIZE}], got '{}'", values.join(" ")),
+                    }
+                }
+                "LUT_1D_SIZE" => bail!("line {line_number}: 1D LUTs are not supported"),
+                "DOMAIN_MIN" => domain_min = triple(&values, line_number)?,
+                "DOMAIN_MAX" => domain_max = triple(&values, line_number)?,
+                // Same domain on every channel
+                "LUT_3D_INPUT_RANGE" => match values.as_slice() {
+                    [min, max] => {
+                        domain_min = [number(min, line_number)?; 3];
+                        domain_max = [number(max, line_number)?; 3];
+                    }
+                    _ => bail!("line {line_number}: LUT_3D_INPUT_RANGE expects 2 values, got {}", values.len()),
+                },
+                // Other keywords are not used
+                _ if keyword.starts_with(|c: char| c.is_ascii_alphabetic()) => {
+                    log::warn!("cube LUT line {line_number}: '{keyword}' ignored");
+                }
+                _ => {
+                    let mut row = vec![keyword];
+                    row.extend(values);
+                    data.push(triple(&row, line_number)?);
+                }
+            }
+        }
+
+        let size = size.ok_or(anyhow!("missing LUT_3D_SIZE"))?;
+        if data.len() != (size * size * size) as usize {
+            bail!("LUT_3D_SIZE {size} needs {} colors, got {}", size * size * size, data.len());
+        }
+        if (0..3).any(|c| domain_min[c] >= domain_max[c]) {
+            bail!("DOMAIN_MIN {domain_min:?} must be below DOMAIN_MAX {domain_max:?}");
+        }
+
+        Ok(Self { title, size, domain_min, domain_max, data })
+    }
+}
+
+// / Load and parse a .cube LUT from res/ (fetched on the web).
+pub async fn load_cube_lut(file_name: &str) -> anyhow::Result<CubeLut> {
+    let text = load_string(file_name).await?;
+    CubeLut::parse(&text).with_context(|| format!("invalid cube LUT '{file_name}'"))
+}
+
 pub enum ModelFile<'a> {
     Obj(&'a str),
     Gltf(&'a str),
@@ -225,8 +339,12 @@ pub async fn load_model_gtlf(
     let document = gltf.clone().document;
     let blob = gltf.clone().blob;
 
-    let base_path = Some(std::path::Path::new(env!("OUT_DIR"))
-                            .join("res/models"));
+    // .bin buffers sit next to the .gltf (file_name may also be an absolute path)
+    let base_path = std::path::Path::new(env!("OUT_DIR"))
+                            .join("res")
+                            .join(file_name)
+                            .parent()
+                            .map(std::path::Path::to_path_buf);
     
     let buffers = gltf::import_buffers(&document, base_path.as_deref(), blob.clone())?;
     
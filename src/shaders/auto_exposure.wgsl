@@ -0,0 +1,95 @@
+// Auto exposure in compute passes: a histogram of the log2 luminance of the frame (bin 0
+// for black pixels, the others spread over the metering range), then its average eased
+// towards over time.
+// Exposure texel (Rgba16Float): r = exposure to apply (EV), g = adapted log2 luminance,
+// b = 1 once metered. Same layout as auto_exposure_downsample.wgsl.
+
+struct AutoExposureParams {
+    adaptation: f32, // 0 (keep the previous value) to 1 (jump to the metered one)
+    // 16 bytes (uniform buffer size on WebGL)
+    _padding0: f32,
+    _padding1: f32,
+    _padding2: f32,
+};
+
+// Metering range, log2 luminance (GlowConfig.effects.auto_exposure_range)
+override min_ev: f32 = -8.0;
+override max_ev: f32 = 8.0;
+
+const BINS: u32 = 256u;
+// The average is exposed to middle grey
+const MIDDLE_GREY: f32 = 0.18;
+// Below: black, not metered
+const BLACK: f32 = 1e-6;
+
+@group(0) @binding(0) var t_hdr: texture_2d<f32>;
+// Cleared by cs_average for the next frame
+@group(0) @binding(1) var<storage, read_write> histogram: array<atomic<u32>, BINS>;
+@group(0) @binding(2) var<uniform> params: AutoExposureParams;
+@group(0) @binding(3) var t_exposure: texture_storage_2d<rgba16float, write>;
+// Adapted log2 luminance of the previous frame, 1 once metered
+@group(0) @binding(4) var<storage, read_write> adapted: vec2<f32>;
+
+var<workgroup> local_bins: array<atomic<u32>, BINS>;
+// f32: a u32 sum of bin * count overflows past 16M pixels
+var<workgroup> weighted: array<f32, BINS>;
+
+fn luminance_bin(color: vec3<f32>) -> u32 {
+    let luminance = dot(color, vec3(0.2126, 0.7152, 0.0722));
+    if luminance < BLACK {
+        return 0u;
+    }
+    let t = saturate((log2(luminance) - min_ev) / (max_ev - min_ev));
+    return u32(t * f32(BINS - 2u)) + 1u;
+}
+
+// One 16x16 tile per workgroup, counted in workgroup memory then added to the histogram
+@compute @workgroup_size(16, 16)
+fn cs_histogram(
+    @builtin(global_invocation_id) id: vec3<u32>,
+    @builtin(local_invocation_index) index: u32,
+) {
+    atomicStore(&local_bins[index], 0u);
+    workgroupBarrier();
+
+    if all(id.xy < textureDimensions(t_hdr)) {
+        let color = textureLoad(t_hdr, id.xy, 0).rgb;
+        atomicAdd(&local_bins[luminance_bin(color)], 1u);
+    }
+    workgroupBarrier();
+
+    atomicAdd(&histogram[index], atomicLoad(&local_bins[index]));
+}
+
+// One workgroup: sum of bin * count by parallel reduction, averaged over the lit pixels
+@compute @workgroup_size(256)
+fn cs_average(@builtin(local_invocation_index) index: u32) {
+    let count = atomicLoad(&histogram[index]);
+    weighted[index] = f32(count) * f32(index);
+    atomicStore(&histogram[index], 0u);
+    workgroupBarrier();
+
+    for (var stride = BINS / 2u; stride > 0u; stride >>= 1u) {
+        if index < stride {
+            weighted[index] += weighted[index + stride];
+        }
+        workgroupBarrier();
+    }
+
+    if index == 0u {
+        // `count` is the black bin here
+        let size = textureDimensions(t_hdr);
+        let lit = max(size.x * size.y - count, 1u);
+        let mean_bin = weighted[0] / f32(lit);
+        // Bin k covers [k - 1, k[ of the range, in 1 / (BINS - 2) steps
+        let t = saturate((mean_bin - 0.5) / f32(BINS - 2u));
+        let metered = mix(min_ev, max_ev, t);
+
+        // The first frame jumps to the metered value
+        let previous = adapted;
+        let eased = select(metered, mix(previous.x, metered, params.adaptation), previous.y > 0.5);
+        adapted = vec2(eased, 1.0);
+
+        textureStore(t_exposure, vec2(0), vec4(log2(MIDDLE_GREY) - eased, eased, 1.0, 1.0));
+    }
+}
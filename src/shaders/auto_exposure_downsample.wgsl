@@ -0,0 +1,91 @@
+// Auto exposure in render passes (WebGL): the log2 luminance of the frame averaged down
+// to one texel, then eased towards over time.
+// Mips (Rgba16Float): r = log2 luminance * g, g = 1 for lit pixels (black ones are not
+// metered, as in the histogram of auto_exposure.wgsl), averaged by each downsample.
+// Exposure texel: r = exposure to apply (EV), g = adapted log2 luminance, b = 1 once metered.
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+struct AutoExposureParams {
+    adaptation: f32, // 0 (keep the previous value) to 1 (jump to the metered one)
+    // 16 bytes (uniform buffer size on WebGL)
+    _padding0: f32,
+    _padding1: f32,
+    _padding2: f32,
+};
+
+// Metering range, log2 luminance (GlowConfig.effects.auto_exposure_range)
+override min_ev: f32 = -8.0;
+override max_ev: f32 = 8.0;
+
+// The average is exposed to middle grey
+const MIDDLE_GREY: f32 = 0.18;
+// Below: black, not metered
+const BLACK: f32 = 1e-6;
+
+// fs_luminance, fs_downsample
+@group(0) @binding(0) var t_source: texture_2d<f32>;
+@group(0) @binding(1) var s: sampler;
+
+// fs_adapt
+@group(0) @binding(2) var t_average: texture_2d<f32>;
+@group(0) @binding(3) var t_previous: texture_2d<f32>;
+@group(0) @binding(4) var<uniform> params: AutoExposureParams;
+
+@vertex
+fn vs_main(@builtin(vertex_index) idx: u32) -> VertexOutput {
+    let positions = array<vec2<f32>, 3>(
+        vec2(-1.0, -1.0),
+        vec2( 3.0, -1.0),
+        vec2(-1.0,  3.0),
+    );
+
+    var out: VertexOutput;
+    out.position = vec4(positions[idx], 0.0, 1.0);
+    out.uv = positions[idx] * vec2(0.5, -0.5) + vec2(0.5);
+    return out;
+}
+
+fn metered(color: vec3<f32>) -> vec2<f32> {
+    let luminance = dot(color, vec3(0.2126, 0.7152, 0.0722));
+    if luminance < BLACK {
+        return vec2(0.0);
+    }
+    return vec2(clamp(log2(luminance), min_ev, max_ev), 1.0);
+}
+
+// Half resolution: the 2x2 pixels of the frame under each texel
+@fragment
+fn fs_luminance(in: VertexOutput) -> @location(0) vec4<f32> {
+    let size = vec2<i32>(textureDimensions(t_source));
+    let pixel = vec2<i32>(in.position.xy) * 2;
+
+    var sum = vec2(0.0);
+    for (var i = 0; i < 4; i++) {
+        let texel = min(pixel + vec2(i & 1, i >> 1u), size - 1);
+        sum += metered(textureLoad(t_source, texel, 0).rgb);
+    }
+    return vec4(sum * 0.25, 0.0, 1.0);
+}
+
+// Bilinear tap between 2x2 texels of the previous mip
+@fragment
+fn fs_downsample(in: VertexOutput) -> @location(0) vec4<f32> {
+    return vec4(textureSample(t_source, s, in.uv).rg, 0.0, 1.0);
+}
+
+// 1x1: the average eased from the previous frame's
+@fragment
+fn fs_adapt(in: VertexOutput) -> @location(0) vec4<f32> {
+    let average = textureLoad(t_average, vec2(0), 0).rg;
+    let metered = select(min_ev, average.x / average.y, average.y > 0.0);
+
+    // The first frame jumps to the metered value
+    let previous = textureLoad(t_previous, vec2(0), 0);
+    let eased = select(metered, mix(previous.g, metered, params.adaptation), previous.b > 0.5);
+
+    return vec4(log2(MIDDLE_GREY) - eased, eased, 1.0, 1.0);
+}
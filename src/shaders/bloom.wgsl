@@ -0,0 +1,108 @@
+// Mip-chain bloom (Jimenez, "Next Generation Post Processing in Call of Duty: Advanced Warfare")
+// prefilter: outline + bright-pass of the scene -> 1/2 res
+// downsample: 13-tap filter, 1/2 -> 1/4 -> ...
+// upsample: 3x3 tent, added (blend One/One) into the next bigger mip, the last one into the bloom
+// target, scaled by the blend constant (1 / levels)
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+};
+
+// Tent radius, in texels of the mip being upsampled. Set from GlowConfig.effects
+override bloom_radius: f32 = 1.0;
+// Scene brightness where the bloom starts (soft knee below it)
+override bloom_threshold: f32 = 1.0;
+
+// Module scope: naga rejects function-scope constants in a module with overrides
+const KNEE: f32 = 0.5;
+const CENTER_WEIGHT: f32 = 0.125;
+const CORNER_WEIGHT: f32 = 0.03125;
+const EDGE_WEIGHT: f32 = 0.0625;
+const INNER_WEIGHT: f32 = 0.125;
+
+@group(0) @binding(0) var t_input: texture_2d<f32>;
+@group(0) @binding(1) var s_linear: sampler;
+// Prefilter only
+@group(0) @binding(2) var t_scene: texture_2d<f32>;
+
+@vertex
+fn vs_main(@builtin(vertex_index) idx: u32) -> VertexOutput {
+    let positions = array<vec2<f32>, 3>(
+        vec2(-1.0, -1.0),
+        vec2( 3.0, -1.0),
+        vec2(-1.0,  3.0),
+    );
+
+    let pos = positions[idx];
+    var out: VertexOutput;
+    out.position = vec4(pos, 0.0, 1.0);
+    out.uv = pos * 0.5 + 0.5;
+    out.uv.y = 1.0 - out.uv.y;
+    return out;
+}
+
+// 13 bilinear taps over a 4x4 texel area of the source: 5 overlapping 2x2 boxes
+fn downsample13(t: texture_2d<f32>, uv: vec2<f32>) -> vec4<f32> {
+    let size = textureDimensions(t);
+    let texel = 1.0 / vec2<f32>(f32(size.x), f32(size.y));
+
+    let a = textureSample(t, s_linear, uv + texel * vec2(-2.0,  2.0));
+    let b = textureSample(t, s_linear, uv + texel * vec2( 0.0,  2.0));
+    let c = textureSample(t, s_linear, uv + texel * vec2( 2.0,  2.0));
+    let d = textureSample(t, s_linear, uv + texel * vec2(-2.0,  0.0));
+    let e = textureSample(t, s_linear, uv);
+    let f = textureSample(t, s_linear, uv + texel * vec2( 2.0,  0.0));
+    let g = textureSample(t, s_linear, uv + texel * vec2(-2.0, -2.0));
+    let h = textureSample(t, s_linear, uv + texel * vec2( 0.0, -2.0));
+    let i = textureSample(t, s_linear, uv + texel * vec2( 2.0, -2.0));
+    let j = textureSample(t, s_linear, uv + texel * vec2(-1.0,  1.0));
+    let k = textureSample(t, s_linear, uv + texel * vec2( 1.0,  1.0));
+    let l = textureSample(t, s_linear, uv + texel * vec2(-1.0, -1.0));
+    let m = textureSample(t, s_linear, uv + texel * vec2( 1.0, -1.0));
+
+    return e * CENTER_WEIGHT
+        + (a + c + g + i) * CORNER_WEIGHT
+        + (b + d + f + h) * EDGE_WEIGHT
+        + (j + k + l + m) * INNER_WEIGHT;
+}
+
+// Soft threshold: quadratic ramp over [threshold - knee, threshold + knee]
+fn bright_pass(color: vec3<f32>) -> vec3<f32> {
+    let brightness = max(color.r, max(color.g, color.b));
+    var ramp = clamp(brightness - bloom_threshold + KNEE, 0.0, 2.0 * KNEE);
+    ramp = ramp * ramp / (4.0 * KNEE + 0.0001);
+    let weight = max(ramp, brightness - bloom_threshold) / max(brightness, 0.0001);
+    return color * weight;
+}
+
+@fragment
+fn fs_prefilter(in: VertexOutput) -> @location(0) vec4<f32> {
+    let outline = downsample13(t_input, in.uv);
+    let scene = downsample13(t_scene, in.uv);
+    return outline + vec4(bright_pass(scene.rgb), 0.0);
+}
+
+@fragment
+fn fs_downsample(in: VertexOutput) -> @location(0) vec4<f32> {
+    return downsample13(t_input, in.uv);
+}
+
+// 3x3 tent: 1 2 1 / 2 4 2 / 1 2 1 (/ 16)
+@fragment
+fn fs_upsample(in: VertexOutput) -> @location(0) vec4<f32> {
+    let size = textureDimensions(t_input);
+    let r = bloom_radius / vec2<f32>(f32(size.x), f32(size.y));
+
+    var sum = textureSample(t_input, s_linear, in.uv) * 4.0;
+    sum += (textureSample(t_input, s_linear, in.uv + vec2(-r.x, 0.0))
+        + textureSample(t_input, s_linear, in.uv + vec2( r.x, 0.0))
+        + textureSample(t_input, s_linear, in.uv + vec2(0.0, -r.y))
+        + textureSample(t_input, s_linear, in.uv + vec2(0.0,  r.y))) * 2.0;
+    sum += textureSample(t_input, s_linear, in.uv + vec2(-r.x, -r.y))
+        + textureSample(t_input, s_linear, in.uv + vec2( r.x, -r.y))
+        + textureSample(t_input, s_linear, in.uv + vec2(-r.x,  r.y))
+        + textureSample(t_input, s_linear, in.uv + vec2( r.x,  r.y));
+
+    return sum / 16.0;
+}
@@ -3,11 +3,20 @@ struct VertexOutput {
     @location(0) uv: vec2<f32>,
 };
 
+// Gaussian kernel computed on the CPU (BlurParams::gaussian), linear sampling:
+// each tap but the center reads two texels at once with the bilinear filter
 struct BlurParams {
     direction: vec2<f32>, // (1,0) = horizontal, (0,1) = vertical
-    _padding: vec2<f32>,
+    tap_count: u32,
+    _padding: u32,
+    // x: offset in texels, y: weight. taps[0] is the center, the others are mirrored
+    taps: array<vec4<f32>, 16>, // MAX_BLUR_TAPS
 };
 
+// Scale of the tap offsets, set from GlowConfig.effects. 1 = exact kernel, above
+// spreads it wider with gaps between the taps
+override blur_spread: f32 = 1.0;
+
 @group(0) @binding(0) var t_input: texture_2d<f32>;
 @group(0) @binding(1) var s_sampler: sampler;
 @group(0) @binding(2) var<uniform> params: BlurParams;
@@ -32,15 +41,16 @@ fn vs_main(@builtin(vertex_index) idx: u32) -> VertexOutput {
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     let size_u = textureDimensions(t_input);
     let size = vec2<f32>(f32(size_u.x), f32(size_u.y));
-    let texel = params.direction * 2.5 / size;
+    let texel = params.direction * blur_spread / size;
 
-    var sum = vec4<f32>(0.0);
+    var sum = textureSample(t_input, s_sampler, in.uv) * params.taps[0].y;
 
-    sum += textureSample(t_input, s_sampler, in.uv - 4.0 * texel) * 0.05;
-    sum += textureSample(t_input, s_sampler, in.uv - 2.0 * texel) * 0.09;
-    sum += textureSample(t_input, s_sampler, in.uv)              * 0.62;
-    sum += textureSample(t_input, s_sampler, in.uv + 2.0 * texel) * 0.09;
-    sum += textureSample(t_input, s_sampler, in.uv + 4.0 * texel) * 0.05;
+    for (var i = 1u; i < params.tap_count; i++) {
+        let tap = params.taps[i];
+        let offset = tap.x * texel;
+        sum += (textureSample(t_input, s_sampler, in.uv - offset)
+            + textureSample(t_input, s_sampler, in.uv + offset)) * tap.y;
+    }
 
     return sum;
 }
@@ -0,0 +1,73 @@
+// Separable Gaussian blur in compute passes. A workgroup blurs TILE texels of one row
+// (horizontal) or column (vertical): it first caches them with APRON texels on each side
+// in workgroup memory, so every input texel is loaded once instead of once per tap.
+// Same BlurParams as blur.wgsl: the merged taps are read between two cached texels
+// with a lerp, as the bilinear sampler does in the render path.
+
+const TILE: u32 = 128u;
+const APRON: u32 = 64u; // COMPUTE_BLUR_APRON
+const CACHE_SIZE: u32 = 256u; // TILE + 2 * APRON
+
+struct BlurParams {
+    direction: vec2<f32>, // (1,0) = horizontal, (0,1) = vertical
+    tap_count: u32,
+    _padding: u32,
+    // x: offset in texels, y: weight. taps[0] is the center, the others are mirrored
+    taps: array<vec4<f32>, 16>, // MAX_BLUR_TAPS
+};
+
+// Scale of the tap offsets, set from GlowConfig.effects (blur_radius * blur_spread < APRON)
+override blur_spread: f32 = 1.0;
+
+@group(0) @binding(0) var t_input: texture_2d<f32>;
+@group(0) @binding(1) var t_output: texture_storage_2d<rgba16float, write>;
+@group(0) @binding(2) var<uniform> params: BlurParams;
+
+var<workgroup> cache: array<vec4<f32>, CACHE_SIZE>;
+
+// Texel `along` the blur direction in row / column `across`
+fn texel_coord(along: i32, across: i32, horizontal: bool) -> vec2<i32> {
+    return select(vec2(across, along), vec2(along, across), horizontal);
+}
+
+// Cached texels at a fractional position, linearly filtered
+fn fetch(position: f32) -> vec4<f32> {
+    let i = u32(floor(position));
+    return mix(cache[i], cache[min(i + 1u, CACHE_SIZE - 1u)], fract(position));
+}
+
+// Dispatch: (ceil(length / TILE), rows or columns, 1)
+@compute @workgroup_size(128, 1, 1)
+fn cs_main(
+    @builtin(workgroup_id) group: vec3<u32>,
+    @builtin(local_invocation_index) local: u32,
+) {
+    let size = vec2<i32>(textureDimensions(t_input));
+    let horizontal = params.direction.x > 0.5;
+    let length = select(size.y, size.x, horizontal);
+    let across = i32(group.y);
+    let tile_start = i32(group.x * TILE);
+
+    // Tile + aprons, clamped to the edge like the render path sampler
+    for (var i = local; i < CACHE_SIZE; i += TILE) {
+        let along = clamp(tile_start + i32(i) - i32(APRON), 0, length - 1);
+        cache[i] = textureLoad(t_input, texel_coord(along, across, horizontal), 0);
+    }
+    workgroupBarrier();
+
+    let along = tile_start + i32(local);
+    if along >= length {
+        return;
+    }
+
+    let center = f32(local + APRON);
+    var sum = cache[local + APRON] * params.taps[0].y;
+
+    for (var i = 1u; i < params.tap_count; i++) {
+        let tap = params.taps[i];
+        let offset = min(tap.x * blur_spread, f32(APRON - 1u));
+        sum += (fetch(center - offset) + fetch(center + offset)) * tap.y;
+    }
+
+    textureStore(t_output, texel_coord(along, across, horizontal), sum);
+}
@@ -0,0 +1,71 @@
+// Color grading of the tone mapped frame with a 3D LUT (.cube), after hdr_lite.wgsl.
+// The LUT maps display (sRGB encoded) colors, red along x, green along y, blue along z.
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+};
+
+struct ColorGradeParams {
+    domain_min: vec3<f32>,
+    size: f32, // LUT_3D_SIZE
+    domain_max: vec3<f32>,
+    strength: f32, // 0 (tone mapped frame) to 1 (graded)
+};
+
+@group(0) @binding(0) var t_frame: texture_2d<f32>;
+
+@group(1) @binding(0) var t_lut: texture_3d<f32>;
+@group(1) @binding(1) var s_lut: sampler;
+@group(1) @binding(2) var<uniform> grade: ColorGradeParams;
+
+// The frame is stored sRGB encoded (the tone map encoded it for a non-*Srgb output):
+// graded as is. Otherwise it is linear, encoded for the lookup and decoded back
+override is_encoded: bool = false;
+
+@vertex
+fn vs_main(@builtin(vertex_index) idx: u32) -> VertexOutput {
+    // Fullscreen triangle
+    let positions = array<vec2<f32>, 3>(
+        vec2(-1.0, -1.0),
+        vec2( 3.0, -1.0),
+        vec2(-1.0,  3.0),
+    );
+
+    var out: VertexOutput;
+    out.position = vec4(positions[idx], 0.0, 1.0);
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let frame = textureLoad(t_frame, vec2<i32>(in.position.xy), 0).rgb;
+
+    var color = saturate(frame);
+    if !is_encoded {
+        color = linear_to_srgb(color);
+    }
+
+    // Texel centres: the domain ends land on the first and last LUT entries
+    let t = saturate((color - grade.domain_min) / (grade.domain_max - grade.domain_min));
+    let uvw = (t * (grade.size - 1.0) + 0.5) / grade.size;
+    let graded = textureSampleLevel(t_lut, s_lut, uvw, 0.0).rgb;
+
+    color = mix(color, saturate(graded), grade.strength);
+    if !is_encoded {
+        color = srgb_to_linear(color);
+    }
+    return vec4(color, 1.0);
+}
+
+// Linear -> sRGB transfer function (IEC 61966-2-1)
+fn linear_to_srgb(linear: vec3<f32>) -> vec3<f32> {
+    let low = linear * 12.92;
+    let high = 1.055 * pow(linear, vec3(1.0 / 2.4)) - 0.055;
+    return select(high, low, linear <= vec3(0.0031308));
+}
+
+fn srgb_to_linear(srgb: vec3<f32>) -> vec3<f32> {
+    let low = srgb / 12.92;
+    let high = pow((srgb + 0.055) / 1.055, vec3(2.4));
+    return select(high, low, srgb <= vec3(0.04045));
+}
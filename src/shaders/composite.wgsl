@@ -24,6 +24,9 @@ fn vs_main(@builtin(vertex_index) idx: u32) -> VertexOutput {
     return out;
 }
 
+// Set from GlowConfig.effects
+override bloom_strength: f32 = 3.7;
+
 @group(0) @binding(0) var t_scene: texture_2d<f32>;
 @group(0) @binding(1) var t_outline: texture_2d<f32>;
 @group(0) @binding(2) var t_bloom: texture_2d<f32>;
@@ -44,8 +47,6 @@ fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
     //return    scene + bloom * 1. ;
     //return    scene  ;
 
-    let bloom_strength = 3.7 ;
-
     let scene_rgb = scene.rgb;
     let bloom_rgb = bloom.rgb + vec3(0.0, 0.0, 0.0);
 
@@ -14,3 +14,25 @@ fn vs_main(@builtin(vertex_index) idx: u32) -> @builtin(position) vec4<f32> {
 fn fs_main() -> @location(0) vec4<f32> {
     return vec4<f32>(0.0, 0.75, 0.0, 1.0); // solid green
 }
+
+// Stencil mask visualization: the pipeline stencil test lets only masked pixels through
+@fragment
+fn fs_mask() -> @location(0) vec4<f32> {
+    return vec4<f32>(1.0, 1.0, 1.0, 1.0);
+}
+
+// Depth copy: depth targets can't be copied to a buffer everywhere (GL) and R32Float is not
+// always renderable, so the depth is packed as 24 bits into the rgb of an Rgba8Unorm target
+@group(0) @binding(0) var t_depth: texture_2d<f32>;
+
+@fragment
+fn fs_depth(@builtin(position) position: vec4<f32>) -> @location(0) vec4<f32> {
+    let depth = textureLoad(t_depth, vec2<i32>(position.xy), 0).r;
+    let bits = u32(round(saturate(depth) * 16777215.0));
+    return vec4<f32>(
+        f32((bits >> 16u) & 255u),
+        f32((bits >> 8u) & 255u),
+        f32(bits & 255u),
+        255.0,
+    ) / 255.0;
+}
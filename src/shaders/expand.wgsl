@@ -12,6 +12,35 @@ struct SpinUniform {
 @group(1) @binding(0)
 var<uniform> spin: SpinUniform;
 
+// Outline of an object (GlowStyleUniform)
+struct GlowStyle {
+    color: vec4<f32>, // rgb * intensity, alpha
+    params: vec4<f32>, // thickness, noise, 1 when set, unused
+};
+// Outline of the model, used by the instances without their own
+@group(2) @binding(0)
+var<uniform> model_glow: GlowStyle;
+
+// How a highlight group shows behind other geometry (OcclusionUniform)
+struct Occlusion {
+    color: vec4<f32>, // hidden parts: rgb * intensity, 1 when set
+    params: vec4<f32>, // visibility, dash width in pixels, unused, unused
+};
+// Indexed by highlight group (stencil reference)
+@group(2) @binding(2)
+var<uniform> group_occlusion: array<Occlusion, 256>;
+
+// Depth of the whole scene (depth prepass), read as unfilterable float
+@group(3) @binding(0)
+var t_scene_depth: texture_2d<f32>;
+
+// Visibility
+const ALWAYS: u32 = 0u;
+const VISIBLE: u32 = 1u;
+const OCCLUDED: u32 = 2u;
+// Behind the scene depth by more than this: hidden
+const OCCLUSION_BIAS: f32 = 1e-4;
+
 
 
 struct VertexInput {
@@ -24,13 +53,18 @@ struct InstanceInput {
     @location(6) model_matrix_1: vec4<f32>,
     @location(7) model_matrix_2: vec4<f32>,
     @location(8) model_matrix_3: vec4<f32>,
+    @location(9) glow_color: vec4<f32>,
+    @location(10) glow_params: vec4<f32>,
 };
 
 struct VertexOutput {
     @builtin(position) position : vec4<f32>,
+    @location(0) @interpolate(flat) color: vec4<f32>,
+    @location(1) @interpolate(flat) noise: f32,
+    @location(2) @interpolate(flat) group: u32,
 };
 
-const OUTLINE_THICKNESS : f32 = 0.07;
+const NOISE_SCALE: f32 = 5.0;
 
 @vertex
 fn vs_main(input: VertexInput,  instance: InstanceInput,) -> VertexOutput {
@@ -44,45 +78,66 @@ fn vs_main(input: VertexInput,  instance: InstanceInput,) -> VertexOutput {
         instance.model_matrix_3,
     );
 
-    let expanded = input.position + input.normal * OUTLINE_THICKNESS;
+    var style = model_glow;
+    if instance.glow_params.z > 0.5 {
+        style = GlowStyle(instance.glow_color, instance.glow_params);
+    }
+
+    let expanded = input.position + input.normal * style.params.x;
 
     out.position = camera.view_proj * model_matrix * spin.model   * vec4<f32>(expanded, 1.0);
+    out.color = style.color;
+    out.noise = style.params.y;
+    out.group = u32(instance.glow_params.w);
     return out;
 }
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // Hidden behind other geometry: kept, recolored, dashed or dropped per group
+    let occlusion = group_occlusion[in.group];
+    let scene_depth = textureLoad(t_scene_depth, vec2<i32>(in.position.xy), 0).r;
+    let is_occluded = in.position.z > scene_depth + OCCLUSION_BIAS;
+
+    let visibility = u32(occlusion.params.x);
+    if (visibility == VISIBLE && is_occluded) || (visibility == OCCLUDED && !is_occluded) {
+        discard;
+    }
+
+    var color = in.color;
+    if is_occluded {
+        if occlusion.color.w > 0.5 {
+            color = vec4(occlusion.color.rgb, color.a);
+        }
+        // Diagonal stripes, `dash` pixels wide
+        let dash = occlusion.params.y;
+        if dash > 0.0 && fract((in.position.x + in.position.y) / (2.0 * dash)) >= 0.5 {
+            discard;
+        }
+    }
+
     // Convert from clip space to NDC
     let ndc = in.position.xyz / in.position.w;
 
-    // Optional: scale noise frequency
-    let noise_scale = 5.0;
-    let noise = simplex_noise_3d(ndc * noise_scale); 
+    let noise = simplex_noise_3d(ndc * NOISE_SCALE);
 
-    // Map noise from [-1,1] → [0,1] if needed
+    // Map noise from [-1,1] → [0,1], faded in by the style noise amount
     let noise01 = noise * 0.5 + 0.5;
-   
-    // Subtle edge modulation
-    //let noisy_edge = clamp(noise01, 0.0, 1.0);
-    let noisy_edge = clamp(noise * 0.2 + 0.8, 0.0, 1.0);
-    //let alpha = noisy_edge;
-    let alpha = 0.7 ;
-    let edge_color = vec3<f32>(0.0, 0.5, 0.5); //* alpha; // black edge
-
-    // let threshold = 0.5;
-    // let noisy_threshold = threshold + (noise - 0.5) * 0.5;
-    // let edge_mask = step(noisy_threshold, 0.4);
-
+    let alpha = color.a * mix(1.0, noise01, in.noise);
 
-    return vec4<f32>(edge_color, alpha) ; // black outline
-    //return vec4<f32>(edge_color, edge_mask);
+    return vec4<f32>(color.rgb, alpha);
 }
 
 
 
+// Module scope and used directly: naga's override pass rejects a `let` bound to a constant
+// expression inside the function (ExpressionAlreadyInScope)
+const C = vec2(1. / 6., 1. / 3.);
+const D = vec4(0., 0.5, 1., 2.);
+const NOISE_N = 1. / 7.; // N=7
+const NOISE_NS = NOISE_N * D.wyz - D.xzx;
+
 fn simplex_noise_3d(v: vec3<f32>) -> f32 {
-    let C = vec2(1. / 6., 1. / 3.);
-    let D = vec4(0., 0.5, 1., 2.);
 
     // first corner
     var i = floor(v + dot(v, C.yyy));
@@ -108,16 +163,13 @@ fn simplex_noise_3d(v: vec3<f32>) -> f32 {
     );
 
     // gradients (NxN points uniformly over a square, mapped onto an octahedron)
-    let n_ = 1. / 7.; // N=7
-    let ns = n_ * D.wyz - D.xzx;
-
-    let j = p - 49. * floor(p * ns.z * ns.z); // mod(p, N*N)
+    let j = p - 49. * floor(p * NOISE_NS.z * NOISE_NS.z); // mod(p, N*N)
 
-    let x_ = floor(j * ns.z);
+    let x_ = floor(j * NOISE_NS.z);
     let y_ = floor(j - 7. * x_); // mod(j, N)
 
-    let x = x_ * ns.x + ns.yyyy;
-    let y = y_ * ns.x + ns.yyyy;
+    let x = x_ * NOISE_NS.x + NOISE_NS.yyyy;
+    let y = y_ * NOISE_NS.x + NOISE_NS.yyyy;
     let h = 1. - abs(x) - abs(y);
 
     let b0 = vec4(x.xy, y.xy);
@@ -24,20 +24,68 @@ fn vs_main(@builtin(vertex_index) idx: u32) -> VertexOutput {
     return out;
 }
 
+// Tone map of the frame (ToneMapParams)
+struct ToneMapParams {
+    exposure: f32, // stops (EV)
+    white_point: f32, // extended Reinhard, Uncharted 2
+    curve: u32, // ToneMapOperator::shader_index (`operator` is reserved)
+    _padding: u32,
+};
+
 @group(0) @binding(0) var t_hdr: texture_2d<f32>;
 @group(0) @binding(1) var s: sampler;
+// Auto exposure (auto_exposure*.wgsl): r = metered exposure (EV)
+@group(0) @binding(2) var t_exposure: texture_2d<f32>;
+
+@group(1) @binding(0) var<uniform> tone_map: ToneMapParams;
+
+// SDR source (is_hdr = false): already in [0, 1], no tone mapping
+override is_hdr: bool = true;
+// Output is not an *Srgb format: the hardware won't encode, do it here
+override encode_srgb: bool = false;
+// The metered exposure adds to the manual one
+override auto_exposure: bool = false;
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    // let hdr = textureSample(t_hdr, s, in.uv).rgb;
+    let hdr = textureSample(t_hdr, s, in.uv);
 
-    // // Simple Reinhard tone mapping
-    // let mapped = hdr / (hdr + vec3(1.0));
+    var sdr = saturate(hdr.rgb);
+    if is_hdr {
+        var exposure = tone_map.exposure;
+        if auto_exposure {
+            exposure += textureLoad(t_exposure, vec2(0), 0).r;
+        }
+        let exposed = max(hdr.rgb * exp2(exposure), vec3(0.0));
+        switch tone_map.curve {
+            case 1u: {
+                sdr = aces_narkowicz_tone_map(exposed);
+            }
+            case 2u: {
+                // Simple Reinhard tone mapping
+                sdr = exposed / (exposed + vec3(1.0));
+            }
+            case 3u: {
+                sdr = reinhard_extended_tone_map(exposed, tone_map.white_point);
+            }
+            case 4u: {
+                sdr = uncharted2_tone_map(exposed, tone_map.white_point);
+            }
+            case 5u: {
+                sdr = agx_tone_map(exposed);
+            }
+            case 6u: {
+                sdr = pbr_neutral_tone_map(exposed);
+            }
+            default: {
+                sdr = aces_tone_map(exposed);
+            }
+        }
+    }
 
-    // // Output to sRGB surface
-    // return vec4(mapped, 1.0);
-    let hdr = textureSample(t_hdr, s, in.uv);
-    let sdr = aces_tone_map(hdr.rgb);
+    if encode_srgb {
+        sdr = linear_to_srgb(sdr);
+    }
     //return vec4(sdr, hdr.a);
     return vec4(sdr, 1.0);
 }
@@ -62,7 +110,7 @@ fn aces_tone_map(hdr: vec3<f32>) -> vec3<f32> {
     return clamp(m2 * (a / b), vec3(0.0), vec3(1.0));
 }
 
-fn _aces_tone_map(v: vec3<f32>) -> vec3<f32> {
+fn aces_narkowicz_tone_map(v: vec3<f32>) -> vec3<f32> {
 
     let a = 2.51;
     let b = 0.03;
@@ -71,4 +119,86 @@ fn _aces_tone_map(v: vec3<f32>) -> vec3<f32> {
     let e = 0.14;
     
     return saturate((v * (a * v + b)) / (v * (c * v + d) + e));
-}
\ No newline at end of file
+}
+
+// Reinhard reaching 1 at the white point instead of infinity
+fn reinhard_extended_tone_map(v: vec3<f32>, white_point: f32) -> vec3<f32> {
+    let numerator = v * (vec3(1.0) + v / (white_point * white_point));
+    return saturate(numerator / (vec3(1.0) + v));
+}
+
+// John Hable's filmic curve, http://filmicworlds.com/blog/filmic-tonemapping-operators/
+fn uncharted2_curve(x: vec3<f32>) -> vec3<f32> {
+    let a = 0.15; // shoulder strength
+    let b = 0.50; // linear strength
+    let c = 0.10; // linear angle
+    let d = 0.20; // toe strength
+    let e = 0.02; // toe numerator
+    let f = 0.30; // toe denominator
+
+    return ((x * (a * x + c * b) + d * e) / (x * (a * x + b) + d * f)) - e / f;
+}
+
+fn uncharted2_tone_map(v: vec3<f32>, white_point: f32) -> vec3<f32> {
+    let exposure_bias = 2.0;
+    let white_scale = vec3(1.0) / uncharted2_curve(vec3(white_point));
+    return saturate(uncharted2_curve(v * exposure_bias) * white_scale);
+}
+
+// AgX base look (Troy Sobotka), fitted by Benjamin Wrensch:
+// https://iolite-engine.com/blog_posts/minimal_agx_implementation
+fn agx_tone_map(v: vec3<f32>) -> vec3<f32> {
+    let inset = mat3x3(
+        0.842479062253094, 0.0423282422610123, 0.0423756549057051,
+        0.0784335999999992, 0.878468636469772, 0.0784336,
+        0.0792237451477643, 0.0791661274605434, 0.879142973793104,
+    );
+    let outset = mat3x3(
+        1.19687900512017, -0.0528968517574562, -0.0529716355144438,
+        -0.0980208811401368, 1.15190312990417, -0.0980434501171241,
+        -0.0990297440797205, -0.0989611768448433, 1.15107367264116,
+    );
+    let min_ev = -12.47393;
+    let max_ev = 4.026069;
+
+    // Log2 encoding of the inset color
+    var x = clamp(log2(max(inset * v, vec3(1e-10))), vec3(min_ev), vec3(max_ev));
+    x = (x - min_ev) / (max_ev - min_ev);
+
+    // Sigmoid contrast curve
+    let x2 = x * x;
+    let x4 = x2 * x2;
+    x = 15.5 * x4 * x2 - 40.14 * x4 * x + 31.96 * x4 - 6.868 * x2 * x + 0.4298 * x2 + 0.1191 * x - 0.00232;
+
+    // Back to linear
+    return saturate(pow(max(outset * x, vec3(0.0)), vec3(2.2)));
+}
+
+// Khronos PBR Neutral: https://github.com/KhronosGroup/ToneMapping/tree/main/PBR_Neutral
+fn pbr_neutral_tone_map(v: vec3<f32>) -> vec3<f32> {
+    let start_compression = 0.8 - 0.04;
+    let desaturation = 0.15;
+
+    let x = min(v.r, min(v.g, v.b));
+    let offset = select(0.04, x - 6.25 * x * x, x < 0.08);
+    var color = v - offset;
+
+    let peak = max(color.r, max(color.g, color.b));
+    if peak < start_compression {
+        return color;
+    }
+
+    let d = 1.0 - start_compression;
+    let new_peak = 1.0 - d * d / (peak + d - start_compression);
+    color *= new_peak / peak;
+
+    let g = 1.0 - 1.0 / (desaturation * (peak - new_peak) + 1.0);
+    return mix(color, vec3(new_peak), g);
+}
+
+// Linear -> sRGB transfer function (IEC 61966-2-1)
+fn linear_to_srgb(linear: vec3<f32>) -> vec3<f32> {
+    let low = linear * 12.92;
+    let high = 1.055 * pow(linear, vec3(1.0 / 2.4)) - 0.055;
+    return select(high, low, linear <= vec3(0.0031308));
+}
@@ -0,0 +1,106 @@
+// Jump flood outline: the stencil mask seeds a texture with the pixel coordinates of each
+// mask pixel, log2 passes spread the nearest seed to every pixel, then the distance to it
+// gives an outline of exact pixel width.
+// Seed texels (Rgba16Uint): xy = pixel coordinates of the nearest seed, z = its highlight
+// group, w = 1 when set.
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) @interpolate(flat) group: u32,
+};
+
+// Outline of an object (GlowStyleUniform)
+struct GlowStyle {
+    color: vec4<f32>, // rgb * intensity, alpha
+    params: vec4<f32>, // thickness, noise, 1 when set, unused
+};
+
+struct JumpFloodParams {
+    step: f32, // pixels
+    // 16 bytes like JumpFloodParams (a vec3 would align to 16)
+    _padding0: f32,
+    _padding1: f32,
+    _padding2: f32,
+};
+
+// Solid width then fade out distance, in pixels (GlowConfig.effects)
+override outline_width: f32 = 3.0;
+override outline_falloff: f32 = 6.0;
+
+@group(0) @binding(0) var t_seeds: texture_2d<u32>;
+@group(0) @binding(1) var<uniform> params: JumpFloodParams;
+
+// Outline of each highlight group, indexed by stencil reference
+@group(1) @binding(1) var<uniform> group_glow: array<GlowStyle, 256>;
+
+// Fullscreen triangle, the instance index carries the highlight group of the seed pass
+@vertex
+fn vs_main(@builtin(vertex_index) idx: u32, @builtin(instance_index) group: u32) -> VertexOutput {
+    let positions = array<vec2<f32>, 3>(
+        vec2(-1.0, -1.0),
+        vec2( 3.0, -1.0),
+        vec2(-1.0,  3.0),
+    );
+
+    var out: VertexOutput;
+    out.position = vec4(positions[idx], 0.0, 1.0);
+    out.group = group;
+    return out;
+}
+
+// Drawn once per group with the stencil equal to it
+@fragment
+fn fs_seed(in: VertexOutput) -> @location(0) vec4<u32> {
+    return vec4(vec2<u32>(in.position.xy), in.group, 1u);
+}
+
+// Keep the nearest of the 9 seeds `step` pixels apart
+@fragment
+fn fs_step(in: VertexOutput) -> @location(0) vec4<u32> {
+    let size = vec2<i32>(textureDimensions(t_seeds));
+    let pixel = vec2<i32>(in.position.xy);
+    let step = i32(params.step);
+
+    var best = vec4(0u);
+    var best_distance = 0x7fffffff;
+    for (var y = -1; y <= 1; y++) {
+        for (var x = -1; x <= 1; x++) {
+            let neighbour = pixel + vec2(x, y) * step;
+            if any(neighbour < vec2(0)) || any(neighbour >= size) {
+                continue;
+            }
+            let seed = textureLoad(t_seeds, neighbour, 0);
+            if seed.w == 0u {
+                continue;
+            }
+            let offset = vec2<i32>(seed.xy) - pixel;
+            let distance = dot(offset, offset);
+            if distance < best_distance {
+                best_distance = distance;
+                best = seed;
+            }
+        }
+    }
+    return best;
+}
+
+// Outline color from the distance to the nearest seed, nothing inside the mask
+@fragment
+fn fs_outline(in: VertexOutput) -> @location(0) vec4<f32> {
+    let pixel = vec2<i32>(in.position.xy);
+    let seed = textureLoad(t_seeds, pixel, 0);
+    let distance = length(vec2<f32>(vec2<i32>(seed.xy) - pixel));
+    if seed.w == 0u || distance == 0.0 {
+        return vec4(0.0);
+    }
+
+    // Solid up to the width (antialiased edge), then fades out over the falloff
+    let solid = clamp(outline_width + 0.5 - distance, 0.0, 1.0);
+    let fade = 1.0 - smoothstep(outline_width, outline_width + outline_falloff, distance);
+    let coverage = max(solid, select(0.0, fade, outline_falloff > 0.0));
+
+    let style = group_glow[seed.z];
+    let alpha = style.color.a * coverage;
+    // Premultiplied, as the extruded outline blended onto a transparent target
+    return vec4(style.color.rgb * alpha, alpha);
+}
@@ -26,65 +26,181 @@ fn vs_main(@builtin(vertex_index) idx: u32) -> VertexOutput {
 
 @group(0) @binding(0) var t_color: texture_2d<f32>;
 @group(0) @binding(1) var s_sampler: sampler;
-@group(0) @binding(2) var t_depth: texture_depth_2d;
+// Depth prepass bound as unfilterable float: depth textures with a non comparison
+// sampler do not translate to GLSL (GL / WebGL backends)
+@group(0) @binding(2) var t_depth: texture_2d<f32>;
 @group(0) @binding(3) var t_normal: texture_2d<f32>;
+// Instance + mesh of each pixel (model::ObjectId), 0 for the background
+@group(0) @binding(5) var t_object_id: texture_2d<u32>;
+
+// EdgeParams (GlowConfig.effects + camera clip planes)
+struct EdgeParams {
+    color: vec4<f32>, // rgb, unused
+    thresholds: vec4<f32>, // depth low / high, normal low / high
+    weights: vec4<f32>, // depth, normal, object ID, unused
+    params: vec4<f32>, // width in pixels, EdgeOperator, camera znear, zfar
+};
+@group(0) @binding(4) var<uniform> edge: EdgeParams;
+
+// EdgeOperator::shader_index
+const CROSS: u32 = 0u;
+const SOBEL: u32 = 1u;
+const ROBERTS: u32 = 2u;
 
 // Linearize depth so edges are consistent regardless of distance
 fn linearize_depth(depth: f32) -> f32 {
     let z = depth * 2.0 - 1.0;
-    let near = 0.1;
-    let far = 100.0;
+    let near = edge.params.z;
+    let far = edge.params.w;
     return (2.0 * near * far) / (far + near - z * (far - near));
 }
-@fragment
-fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    let tex_size = vec2<f32>(textureDimensions(t_color));
-    let offset = 1.0 / tex_size;
 
-    /* ------------------ DEPTH ------------------ */
+fn depth_at(uv: vec2<f32>) -> f32 {
+    return linearize_depth(textureSample(t_depth, s_sampler, uv).r);
+}
+
+fn normal_at(uv: vec2<f32>) -> vec3<f32> {
+    return normalize(textureSample(t_normal, s_sampler, uv).xyz);
+}
 
-    let d_c = linearize_depth(textureSample(t_depth, s_sampler, in.uv));
-    let d_l = linearize_depth(textureSample(t_depth, s_sampler, in.uv + vec2<f32>(-offset.x, 0.0)));
-    let d_r = linearize_depth(textureSample(t_depth, s_sampler, in.uv + vec2<f32>( offset.x, 0.0)));
-    let d_u = linearize_depth(textureSample(t_depth, s_sampler, in.uv + vec2<f32>(0.0,  offset.y)));
-    let d_d = linearize_depth(textureSample(t_depth, s_sampler, in.uv + vec2<f32>(0.0, -offset.y)));
+// Integer texture: no sampler, texel `offset` pixels away clamped to the screen
+fn object_id_at(pixel: vec2<i32>, offset: vec2<i32>) -> u32 {
+    let size = vec2<i32>(textureDimensions(t_object_id));
+    return textureLoad(t_object_id, clamp(pixel + offset, vec2(0), size - 1), 0).r;
+}
 
-    let depth_diff =
-          abs(d_c - d_l)
-        + abs(d_c - d_r)
-        + abs(d_c - d_u)
-        + abs(d_c - d_d);
+// 1 where a neighbour of the kernel shows another instance or mesh
+fn object_id_edge(uv: vec2<f32>, kernel: u32) -> f32 {
+    let size = vec2<f32>(textureDimensions(t_object_id));
+    let pixel = vec2<i32>(uv * size);
+    let step = max(i32(round(edge.params.x)), 1);
+    let id = object_id_at(pixel, vec2(0));
+
+    var differs = false;
+    switch kernel {
+        case SOBEL: {
+            for (var y = -1; y <= 1; y++) {
+                for (var x = -1; x <= 1; x++) {
+                    differs = differs || object_id_at(pixel, vec2(x, y) * step) != id;
+                }
+            }
+        }
+        case ROBERTS: {
+            differs = object_id_at(pixel, vec2(step, step)) != id
+                || object_id_at(pixel, vec2(step, 0)) != object_id_at(pixel, vec2(0, step));
+        }
+        default: {
+            differs = object_id_at(pixel, vec2(-step, 0)) != id
+                || object_id_at(pixel, vec2(step, 0)) != id
+                || object_id_at(pixel, vec2(0, step)) != id
+                || object_id_at(pixel, vec2(0, -step)) != id;
+        }
+    }
+    return select(0.0, 1.0, differs);
+}
 
-    let depth_edge = smoothstep(0.002, 0.006, depth_diff* 0.03);
+// Each kernel returns (depth difference, normal difference), scaled so a one pixel step
+// gives about the same response whatever the kernel: the thresholds stay valid.
+// Normal differences are 1 - cos of the angle (|n1 - n2|^2 / 2 for unit normals).
+
+// Differences with the 4 direct neighbours
+fn cross_edges(uv: vec2<f32>, offset: vec2<f32>) -> vec2<f32> {
+    let d_c = depth_at(uv);
+    let n_c = normal_at(uv);
+    let neighbours = array<vec2<f32>, 4>(
+        vec2(-offset.x, 0.0),
+        vec2( offset.x, 0.0),
+        vec2(0.0,  offset.y),
+        vec2(0.0, -offset.y),
+    );
 
-    /* ------------------ NORMAL ------------------ */
+    var diff = vec2(0.0);
+    for (var i = 0; i < 4; i++) {
+        let neighbour = uv + neighbours[i];
+        diff.x += abs(d_c - depth_at(neighbour));
+        diff.y += 1.0 - dot(n_c, normal_at(neighbour));
+    }
+    return diff;
+}
 
-    let n_c = normalize(textureSample(t_normal, s_sampler, in.uv).xyz);
-    let n_l = normalize(textureSample(t_normal, s_sampler, in.uv + vec2<f32>(-offset.x, 0.0)).xyz);
-    let n_r = normalize(textureSample(t_normal, s_sampler, in.uv + vec2<f32>( offset.x, 0.0)).xyz);
-    let n_u = normalize(textureSample(t_normal, s_sampler, in.uv + vec2<f32>(0.0,  offset.y)).xyz);
-    let n_d = normalize(textureSample(t_normal, s_sampler, in.uv + vec2<f32>(0.0, -offset.y)).xyz);
+// 3x3 horizontal and vertical gradients (weights 1 2 1 across)
+fn sobel_edges(uv: vec2<f32>, offset: vec2<f32>) -> vec2<f32> {
+    var depth_gradient = vec2(0.0);
+    var normal_gx = vec3(0.0);
+    var normal_gy = vec3(0.0);
+    for (var y = -1; y <= 1; y++) {
+        for (var x = -1; x <= 1; x++) {
+            let neighbour = uv + vec2(f32(x), f32(y)) * offset;
+            let d = depth_at(neighbour);
+            let n = normal_at(neighbour);
+            let wx = f32(x) * (2.0 - abs(f32(y)));
+            let wy = f32(y) * (2.0 - abs(f32(x)));
+            depth_gradient += vec2(wx, wy) * d;
+            normal_gx += wx * n;
+            normal_gy += wy * n;
+        }
+    }
+    // A step gives gradients 4 times its size
+    return vec2(
+        length(depth_gradient) / 4.0,
+        (dot(normal_gx, normal_gx) + dot(normal_gy, normal_gy)) / 32.0,
+    );
+}
 
-    let normal_diff =
-          (1.0 - dot(n_c, n_l))
-        + (1.0 - dot(n_c, n_r))
-        + (1.0 - dot(n_c, n_u))
-        + (1.0 - dot(n_c, n_d));
+// 2x2 diagonal differences
+fn roberts_edges(uv: vec2<f32>, offset: vec2<f32>) -> vec2<f32> {
+    let d_00 = depth_at(uv);
+    let d_11 = depth_at(uv + offset);
+    let d_10 = depth_at(uv + vec2(offset.x, 0.0));
+    let d_01 = depth_at(uv + vec2(0.0, offset.y));
+    let n_00 = normal_at(uv);
+    let n_11 = normal_at(uv + offset);
+    let n_10 = normal_at(uv + vec2(offset.x, 0.0));
+    let n_01 = normal_at(uv + vec2(0.0, offset.y));
+
+    // A step shows on both diagonals
+    return 0.5 * vec2(
+        abs(d_00 - d_11) + abs(d_10 - d_01),
+        (1.0 - dot(n_00, n_11)) + (1.0 - dot(n_10, n_01)),
+    );
+}
 
-    let normal_edge = smoothstep(0.01, 0.15, normal_diff*7.0);
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let tex_size = vec2<f32>(textureDimensions(t_color));
+    let offset = edge.params.x / tex_size;
+    let kernel = u32(edge.params.y);
+
+    var diff: vec2<f32>;
+    switch kernel {
+        case SOBEL: {
+            diff = sobel_edges(in.uv, offset);
+        }
+        case ROBERTS: {
+            diff = roberts_edges(in.uv, offset);
+        }
+        default: {
+            diff = cross_edges(in.uv, offset);
+        }
+    }
+
+    let depth_edge = smoothstep(edge.thresholds.x, edge.thresholds.y, diff.x);
+    let normal_edge = smoothstep(edge.thresholds.z, edge.thresholds.w, diff.y);
+    let id_edge = object_id_edge(in.uv, kernel);
 
     /* ------------------ COMBINE ------------------ */
 
-    let edge = max(depth_edge * 0.6, normal_edge * 1.2);
+    let edge_strength = max(
+        max(depth_edge * edge.weights.x, normal_edge * edge.weights.y),
+        id_edge * edge.weights.z,
+    );
 
     let base_color = textureSample(t_color, s_sampler, in.uv);
-    let edge_color = vec4<f32>(0.6, 0.7, 0.7, 1.0);
+    let edge_color = vec4<f32>(edge.color.rgb, 1.0);
 
     //return vec4(vec3(normal_edge), 1.0);
-    //return vec4(vec3(normal_diff * 5.0), 1.0);
-
     //return vec4(vec3(depth_edge), 1.0);
-    return mix(base_color, edge_color, edge);
+    //return vec4(vec3(id_edge), 1.0);
+    return mix(base_color, edge_color, edge_strength);
 
 }
-
@@ -17,10 +17,22 @@ struct VertexInput {
     @location(2) normal: vec3<f32>, // local normals
 };
 
+// Mesh of the draw (ObjectIdParams), one dynamic offset per mesh
+struct ObjectIdParams {
+    mesh: u32,
+}
+@group(3) @binding(0)
+var<uniform> draw: ObjectIdParams;
+
+// model::OBJECT_ID_MESH_BITS
+const OBJECT_ID_MESH_BITS: u32 = 8u;
+
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
     @location(0) tex_coords: vec2<f32>,
     @location(1) world_normal: vec3<f32>, // Passed to fragment shader
+    // Instance + 1 then mesh (model::ObjectId), 0 is the background
+    @location(2) @interpolate(flat) object_id: u32,
 }
 
 struct InstanceInput {
@@ -33,12 +45,15 @@ struct InstanceInput {
 struct FragmentOutput {
     @location(0) color: vec4<f32>,
     @location(1) normal: vec4<f32>,
+    // No target when multisampling: the object ID pass draws fs_object_id instead
+    @location(2) object_id: u32,
 }
 
 @vertex
 fn vs_main(
     model: VertexInput,
     instance: InstanceInput,
+    @builtin(instance_index) instance_index: u32,
 ) -> VertexOutput {
 
     var out: VertexOutput;
@@ -59,6 +74,9 @@ fn vs_main(
     ) * model.normal;
 
 
+    out.object_id = ((instance_index + 1u) << OBJECT_ID_MESH_BITS)
+        | (draw.mesh & ((1u << OBJECT_ID_MESH_BITS) - 1u));
+
     out.tex_coords = model.tex_coords;
     out.clip_position = camera.view_proj * model_matrix * spin.model * vec4<f32>(model.position, 1.0);
     return out;
@@ -80,7 +98,14 @@ fn fs_main(in: VertexOutput) -> FragmentOutput {
     // View-space normals provide better edge results than world-space
 
     out.normal = vec4<f32>(normalize(in.world_normal), 1.0);
+    out.object_id = in.object_id;
     return out;
 
 }
+
+// Object IDs only (single sampled pass when the scene is multisampled)
+@fragment
+fn fs_object_id(in: VertexOutput) -> @location(0) u32 {
+    return in.object_id;
+}
  
\ No newline at end of file
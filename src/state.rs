@@ -1,20 +1,195 @@
 use std::sync::Arc;
 
 use instant::Instant;
-use wgpu::BindGroup;
-use winit::{event_loop::ActiveEventLoop, keyboard::KeyCode, window::Window};
+use winit::{event::MouseButton, event_loop::ActiveEventLoop, keyboard::KeyCode, window::Window};
 
-use crate::{camera::{Camera, CameraController, CameraUniform, bind_group_for_camera_uniform, create_camera_buffer}, depth_stencil::{self, StencilTexture}, extra::{self, BlurParams, Spin, SpinUniform, create_blur_bind_group, create_blur_bind_group_layout, create_composite_bind_group_layout, create_edge_bind_group, create_edge_bind_group_layout, create_linear_sampler, create_tone_map_bind_group, create_tone_map_bind_group_layout}, model::{DrawModel, Instance, Model, create_instance_buffer}, pipeline::Pipeline, resources::{self, ModelFile}, texture::ColorTexture};
-use crate::extra::create_composite_bind_group;
+use anyhow::Context;
+use bytemuck::Zeroable;
+
+use crate::{config::{AutoExposure, BlurPath, COMPUTE_BLUR_APRON, EdgeOperator, OutlineMode, jump_flood_unsupported, DEFAULT_CONFIG_FILE, DEFAULT_HIGHLIGHT_GROUP, MAX_HIGHLIGHT_GROUPS, EffectsConfig, compute_unsupported, GlowConfig, GlowStyle, HighlightGroupConfig, OcclusionStyle, highlight_group_reference, PresentMode, RendererConfig, ToneMapOperator, parse_named, validate_tone_map, unrenderable_target, unsupported_sample_count}, camera::{Camera, CameraController, CameraUniform, bind_group_for_camera_uniform, create_camera_buffer}, extra::{ColorGradeParams, GlowStyleUniform, OcclusionUniform, Spin, SpinUniform, ToneMapParams, create_object_id_bind_group_layout}, model::{self, Instance, InstanceRaw, Model, ObjectId, create_instance_buffer}, passes::{self, GlowGraphSettings, SceneLayouts}, pipeline::Pipeline, profiler::GpuProfiler, render_graph::{RenderGraph, ResourceId, SceneData}, resources::{self, CubeLut}, texture::{ColorTexture, Texture}};
 use crate::visualizer::* ;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::readback::Capture;
+
+// / Intermediate targets of the glow pipeline, in pass order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameTarget {
+    Depth,
+    StencilMask,
+    Outline,
+    BlurHorizontal,
+    BlurVertical,
+    Scene,
+    Normal,
+    ObjectId,
+    Edge,
+    Composite,
+    ToneMap,
+}
+
+impl FrameTarget {
+    pub const ALL: [FrameTarget; 11] = [
+        FrameTarget::Depth,
+        FrameTarget::StencilMask,
+        FrameTarget::Outline,
+        FrameTarget::BlurHorizontal,
+        FrameTarget::BlurVertical,
+        FrameTarget::Scene,
+        FrameTarget::Normal,
+        FrameTarget::ObjectId,
+        FrameTarget::Edge,
+        FrameTarget::Composite,
+        FrameTarget::ToneMap,
+    ];
+
+    // / Graph target holding the pass output (the stencil mask and the final frame are special)
+    pub fn resource(&self) -> Option<ResourceId> {
+        match self {
+            FrameTarget::StencilMask | FrameTarget::ToneMap => None,
+            FrameTarget::Depth => Some(passes::PREPASS_DEPTH),
+            FrameTarget::Outline => Some(passes::OUTLINE),
+            FrameTarget::BlurHorizontal => Some(passes::BLUR_INTERMEDIATE),
+            FrameTarget::BlurVertical => Some(passes::BLOOM),
+            FrameTarget::Scene => Some(passes::SCENE),
+            FrameTarget::Normal => Some(passes::NORMAL),
+            FrameTarget::ObjectId => Some(passes::OBJECT_ID),
+            FrameTarget::Edge => Some(passes::EDGE),
+            FrameTarget::Composite => Some(passes::COMPOSITE),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            FrameTarget::Depth => "depth",
+            FrameTarget::StencilMask => "stencil_mask",
+            FrameTarget::Outline => "outline",
+            FrameTarget::BlurHorizontal => "blur_horizontal",
+            FrameTarget::BlurVertical => "blur_vertical",
+            FrameTarget::Scene => "scene",
+            FrameTarget::Normal => "normal",
+            FrameTarget::ObjectId => "object_id",
+            FrameTarget::Edge => "edge",
+            FrameTarget::Composite => "composite",
+            FrameTarget::ToneMap => "tone_map",
+        }
+    }
+}
+
+impl std::str::FromStr for FrameTarget {
+    type Err = anyhow::Error;
+
+    // / `tone_map` or `tone-map`
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        parse_named(&s.replace('-', "_"), &Self::ALL, Self::name, "frame target")
+    }
+}
+
+// / Directory of the F12 / F11 captures, relative to the working directory
+#[cfg(not(target_arch = "wasm32"))]
+pub const SCREENSHOT_DIR: &str = "screenshots";
+
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CaptureRequest {
+    // F12: the presented frame, as PNG
+    Frame,
+    // F11: every target of the frame, HDR ones also as OpenEXR
+    AllTargets,
+}
+
+// / Instance data with the group outlines resolved (an instance's own style first)
+fn instances_raw(instances: &[Instance], highlight_groups: &[HighlightGroupConfig]) -> Vec<InstanceRaw> {
+    instances
+        .iter()
+        .map(|instance| {
+            let group_glow = (instance.group as usize)
+                .checked_sub(DEFAULT_HIGHLIGHT_GROUP as usize + 1)
+                .and_then(|i| highlight_groups.get(i))
+                .map(|group| &group.glow);
+            instance.to_raw_in_group(group_glow)
+        })
+        .collect()
+}
+
+// / The compute blur falls back to render passes where it can't run: no compute shaders
+// / (WebGL), or a kernel reaching past the cached apron.
+fn usable_blur_path(adapter: &wgpu::Adapter, effects: &EffectsConfig) -> BlurPath {
+    if effects.blur_path == BlurPath::Compute {
+        if let Some(reason) = compute_unsupported(adapter) {
+            log::warn!("compute blur not supported ({reason}), using render passes");
+            return BlurPath::Render;
+        }
+        if !effects.blur_fits_compute_tile() {
+            log::warn!(
+                "blur_radius * blur_spread reaches past the {COMPUTE_BLUR_APRON} texels cached by the compute blur, using render passes"
+            );
+            return BlurPath::Render;
+        }
+    }
+    effects.blur_path
+}
+
+// / The histogram auto exposure falls back to the downsample one without compute shaders.
+fn usable_auto_exposure(adapter: &wgpu::Adapter, effects: &EffectsConfig) -> AutoExposure {
+    if effects.auto_exposure == AutoExposure::Histogram
+        && let Some(reason) = compute_unsupported(adapter)
+    {
+        log::warn!("histogram auto exposure not supported ({reason}), using downsample passes");
+        return AutoExposure::Downsample;
+    }
+    effects.auto_exposure
+}
+
+// / The jump flood outline falls back to the extruded one where its seeds can't be rendered.
+fn usable_outline_mode(adapter: &wgpu::Adapter, effects: &EffectsConfig) -> OutlineMode {
+    if effects.outline_mode == OutlineMode::JumpFlood
+        && let Some(reason) = jump_flood_unsupported(adapter)
+    {
+        log::warn!("jump flood outline not supported ({reason}), using extruded outlines");
+        return OutlineMode::Extrude;
+    }
+    effects.outline_mode
+}
+
+// / A loaded .cube LUT: the color grade pass binds its 3D texture, the params buffer gets
+// / its domain.
+struct ColorLut {
+    name: String,
+    // Strength set by write_color_grade
+    params: ColorGradeParams,
+    bind_group: wgpu::BindGroup,
+}
+
+impl ColorLut {
+    fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        layout: &wgpu::BindGroupLayout,
+        color_grade_buffer: &wgpu::Buffer,
+        name: &str,
+        lut: &CubeLut,
+    ) -> anyhow::Result<Self> {
+        let max_size = device.limits().max_texture_dimension_3d;
+        if lut.size > max_size {
+            anyhow::bail!("color LUT '{name}' is {0}x{0}x{0}, the adapter allows {max_size}", lut.size);
+        }
+
+        let texture = Texture::create_lut_texture(device, queue, lut, name);
+        Ok(Self {
+            name: name.to_string(),
+            params: ColorGradeParams::new(lut, 1.0),
+            bind_group: ColorGradeParams::bind_group_for_color_lut(color_grade_buffer, &texture, layout, device),
+        })
+    }
+}
 
 pub struct State {
-    pub surface: wgpu::Surface<'static>,
+    adapter: wgpu::Adapter,
+    pub surface: Option<wgpu::Surface<'static>>,
     pub device: wgpu::Device,
     pub queue: wgpu::Queue,
     pub config: wgpu::SurfaceConfiguration,
     pub is_surface_configured: bool,
-    pub render_pipeline: wgpu::RenderPipeline,
         is_paused: bool,
     pub diffuse_bind_group: wgpu::BindGroup,
     lib_model: Model,
@@ -25,39 +200,51 @@ pub struct State {
     camera_controller: CameraController,
     instances: Vec<Instance>,
     instance_buffer: wgpu::Buffer,
+    // Name, outline and occlusion of the highlight groups from stencil reference 2
+    highlight_groups: Vec<HighlightGroupConfig>,
+    // Instances drawn together with their group's stencil reference
+    highlight_runs: Vec<(u8, std::ops::Range<u32>)>,
     last_frame: Instant,
+    // Seconds stepped by the last `advance` (auto exposure adaptation), consumed by the
+    // next frame
+    delta_time: f32,
     spin: Spin,
     spin_uniform: SpinUniform,
     spin_buffer: wgpu::Buffer,
     spin_bind_group: wgpu::BindGroup,
-    depth_stencil: StencilTexture,
-    stencil_pipeline: wgpu::RenderPipeline,
+    glow_style_buffer: wgpu::Buffer,
+    group_styles_buffer: wgpu::Buffer,
+    group_occlusion_buffer: wgpu::Buffer,
+    glow_bind_group: wgpu::BindGroup,
     sample_count: u32,
-    color_texture: Option<ColorTexture>,
-    resolve_texture: Option<ColorTexture>,
-    outline_pipeline: wgpu::RenderPipeline,
-    blur_params_uniform_buffer: wgpu::Buffer,
-    blur_outline_resolved_bind_group: Option<BindGroup>,
-    blur_inter_bind_group: Option<BindGroup>,
-    blur_intermediate_texture: Option<ColorTexture>,
-    outline_bloom_texture: Option<ColorTexture>,
-    normal_texture: crate::texture::Texture,
-    normal_resolve_texture: crate::texture::Texture,
-    parallel_depth_texture: crate::texture::Texture,
-    parallel_pass_pipeline: wgpu::RenderPipeline,
-    linear_sampler: wgpu::Sampler,
-    blur_pipeline: wgpu::RenderPipeline,
-    scene_color_texture: Option<ColorTexture>,
-    edge_bind_group: Option<wgpu::BindGroup>,
-    edge_pipeline: wgpu::RenderPipeline,
-    edge_texture: Option<ColorTexture>,
-    composite_bind_group: Option<BindGroup>,
-    composite_pipeline: wgpu::RenderPipeline,
-    composite_texture: Option<ColorTexture>,
     is_hdr: bool,
-    tone_map_bind_group: Option<BindGroup>,
-    tone_map_pipeline: wgpu::RenderPipeline,
-    pub window: Arc<Window>,
+    effects: EffectsConfig,
+    // Tone map pass: operator, exposure (EV) and white point, uploaded by write_tone_map
+    tone_map: ToneMapOperator,
+    exposure: f32,
+    white_point: f32,
+    tone_map_buffer: wgpu::Buffer,
+    tone_map_bind_group: wgpu::BindGroup,
+    // Color grading after the tone map: the loaded LUTs, the one in use (None: no color
+    // grade pass) and how much of it shows, uploaded by write_color_grade
+    color_luts: Vec<ColorLut>,
+    color_lut: Option<usize>,
+    lut_strength: f32,
+    color_grade_buffer: wgpu::Buffer,
+    scene_layouts: SceneLayouts,
+    // Every glow pass and its targets
+    graph: RenderGraph,
+    // Per-pass timings, None when profiling is off
+    profiler: Option<GpuProfiler>,
+    last_profiler_report: Instant,
+    // Stands in for the swapchain when running headless
+    offscreen_texture: Option<ColorTexture>,
+    // F12 / F11: save the next frame (and its intermediates) in SCREENSHOT_DIR
+    #[cfg(not(target_arch = "wasm32"))]
+    pending_capture: Option<CaptureRequest>,
+    // Last cursor position in the window (physical pixels), for picking
+    cursor_position: Option<(u32, u32)>,
+    pub window: Option<Arc<Window>>,
 
 
 }
@@ -66,13 +253,46 @@ pub struct State {
 
 
 impl State {
-    pub async fn new(window: Arc<Window>) -> anyhow::Result<Self> {
- 
- 
+    // / `glow_config` comes from the command line (native). Without it, res/glow.toml is loaded.
+    pub async fn new(window: Arc<Window>, glow_config: Option<GlowConfig>) -> anyhow::Result<Self> {
+
+        // / User Arguments (res/glow.toml)
+
+        let glow_config = match glow_config {
+            Some(glow_config) => glow_config,
+            None => GlowConfig::load_or_default(DEFAULT_CONFIG_FILE).await?,
+        };
 
         // " V I S U A L I Z E R "   S E T U P
-        let visualizer = Visualizer::new(window.clone()).await?;
+        let visualizer = Visualizer::new(window.clone(), &glow_config.adapter, &glow_config.window).await?;
+
+        Self::from_visualizer(visualizer, Some(window), glow_config).await
+    }
+
+    // / Headless state: same passes, rendered into an offscreen Rgba8UnormSrgb target.
+    // / Use `render_to_image` to get the tone mapped frame back.
+    pub async fn new_headless(width: u32, height: u32, glow_config: GlowConfig) -> anyhow::Result<Self> {
+
+        let visualizer = Visualizer::new_headless(width, height, &glow_config.adapter).await?;
+
+        let mut state = Self::from_visualizer(visualizer, None, glow_config).await?;
+
+        // Allocate every target (there is no window to send a Resized event)
+        state.resize(width, height);
 
+        Ok(state)
+    }
+
+    async fn from_visualizer(
+        visualizer: Visualizer,
+        window: Option<Arc<Window>>,
+        glow_config: GlowConfig,
+    ) -> anyhow::Result<Self> {
+
+        glow_config.validate()?;
+        glow_config.validate_for_adapter(&visualizer.adapter, visualizer.config.format)?;
+
+        let adapter = visualizer.adapter;
         let surface= visualizer.surface;
         let device = visualizer.device ;
         let queue = visualizer.queue ;
@@ -80,32 +300,36 @@ impl State {
         // / \\\\\\\\\\\\\\\\\\\\\\\\\\\\\\\\\\\\\\\\\\\\
 
 
-        // / User Arguments
-
         // Sample count can be 1 or 4
-        let sample_count: u32 = 4;
+        let sample_count = glow_config.renderer.sample_count;
 
         // HDR
-        let is_hdr = true;
+        let is_hdr = glow_config.renderer.is_hdr;
+
+        let mut effects = glow_config.effects;
+        effects.blur_path = usable_blur_path(&adapter, &effects);
+        effects.outline_mode = usable_outline_mode(&adapter, &effects);
+        effects.auto_exposure = usable_auto_exposure(&adapter, &effects);
+
+        let RendererConfig { tone_map, exposure, white_point, .. } = glow_config.renderer;
 
         // /  
         // /
         // / C U B E   M O D E L  
 
         //  Texture for Cube
-        let url = "images/wgpu-logo.png";
-        //let url = "cube-diffuse.jpg";
+        let url = &glow_config.scene.texture;
         let diffuse_texture =
-            crate::texture::Texture::get_texture_from_image(&device, &queue, url).await?;
+            crate::texture::Texture::get_texture_from_image(&device, &queue, url)
+                .await
+                .with_context(|| format!("cannot load texture '{url}'"))?;
 
         let (diffuse_bind_group_layout, diffuse_bind_group) =
             diffuse_texture.bind_group_for_texture(&device);  
 
         // Load Mesh for Cube
 
-        //let file = ModelFile::Obj("models/cube.obj");
-        let file = ModelFile::Gltf("models/craft_speederD.gltf");
-        //let file = ModelFile::Gltf("models/Cube.gltf");
+        let file = glow_config.scene.model_file();
 
         let lib_model = resources::load_model(
             &file,
@@ -113,30 +337,45 @@ impl State {
             &queue,
             &diffuse_bind_group_layout,
         ).await
-        .unwrap();
+        .with_context(|| format!("cannot load model '{}'", glow_config.scene.model))?;
 
         // /
         // / I N S T A N C E S
         // /
 
-        let instances = Instance::generate_instances();
-        let instance_data = instances.iter().map(Instance::to_raw).collect::<Vec<_>>();
+        // [[scene.instances]], or a single default copy
+        let instances = if glow_config.scene.instances.is_empty() {
+            Instance::generate_instances()
+        } else {
+            glow_config
+                .scene
+                .instances
+                .iter()
+                .map(|instance| {
+                    let group = instance.group.as_deref().unwrap_or("default");
+                    Ok(Instance::from_config(instance, glow_config.highlight_group(group)?))
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?
+        };
+
+        // Stencil references 2, 3 ... (1 is the model outline)
+        let highlight_groups = glow_config.highlight_groups.clone();
+
+        let instance_data = instances_raw(&instances, &highlight_groups);
         let instance_buffer = create_instance_buffer(&device, &instance_data);
+        let highlight_runs = model::highlight_runs(&instances);
 
         // / C A M E R A
         // /
+        let camera_config = glow_config.camera;
         let camera = Camera::new(
-            // position the camera 1 unit up and 2 units back
-            // +z is out of the screen
-            (0.0, 1.0, 2.0),
-            // have it look at the origin
-            (0.0, 0.0, 0.0),
-            // which way is "up"
-            cgmath::Vector3::unit_y(),
+            camera_config.eye.into(),
+            camera_config.target.into(),
+            camera_config.up.into(),
             config.width as f32 / config.height as f32,
-            45.0,
-            0.1,
-            100.0,
+            camera_config.fovy,
+            camera_config.znear,
+            camera_config.zfar,
         );
 
         let mut camera_uniform = CameraUniform::new();
@@ -146,217 +385,142 @@ impl State {
         let (camera_bind_group_layout, camera_bind_group) =
             bind_group_for_camera_uniform(&camera_buffer, &device);
 
-        let camera_controller = CameraController::new(0.1);
-
-        // / D E P T H   T E X T U R E 
-        // /
-
-        // let depth_texture = crate::texture::Texture::create_depth_texture(&device, &config, "depth_texture");
-
-
-
-        // / S T E N C I L  T E X T U R E
-        // /
-
-        let depth_stencil = depth_stencil::StencilTexture::create_stencil_texture(
-            &device,
-            &config,
-            "depth_stencil",
-            sample_count,
-        );
-
-        // / N O R M A L  T E X T U R E 
-
-        let normal_texture = crate::texture::Texture::create_normal_texture(
-            &device,
-            &config, 
-            "normal texture",
-            sample_count,
-        );
-
-        let normal_resolve_texture = crate::texture::Texture::create_normal_texture(
-            &device,
-            &config, 
-            "normal resolve texture",
-            1,
-        );
-        // / C O L O R  T E X T U R E 
-
-        // Can be  multi-sampled
-        let color_texture = None;
+        let camera_controller = CameraController::new(camera_config.speed);
 
-        // /  R E S O L V E   T E X T U R E 
-        // /
-        let resolve_texture = None;
-        
-        // / D E P T H  P R E P A S S
-
-        let parallel_depth_texture = crate::texture::Texture::create_depth_texture(&device, &config, "pre pass depth_texture");
-    
         // / S P I N
 
         let last_frame = Instant::now();
-        let spin = Spin::new(1.5);
+        let spin = Spin::new(glow_config.scene.spin_speed);
         let spin_uniform = SpinUniform::new();
         let spin_buffer = spin_uniform.create_spin_uniform_buffer(&device);
         let (spin_bind_group_layout, spin_bind_group) =
             SpinUniform::bind_group_for_spin_uniform(&spin_buffer, &device);
 
-        // /   E D G E  D E T E C T I O N
-
-        let edge_bind_group_layout = create_edge_bind_group_layout(&device);
-
-        let edge_bind_group = None;
-
-        let edge_texture = None;
-
-
-        // /  B L U R  F O R  G L O W 
-
-        // // (1,0) = horizontal, (0,1) = vertical . Pipeline reuse for Horiz/Vert
-
-        let blur_params = BlurParams::new(1., 0.);
-
-        let blur_params_uniform_buffer = blur_params.create_blurparams_uniform_buffer(&device);
-
-        // Texture sampler
-        let linear_sampler = create_linear_sampler(&device);
-
-        let blur_outline_resolved_bind_group = None;
-
-        let blur_inter_bind_group = None;
-
-        // Texture used between blur horizotal and vertical
-        let blur_intermediate_texture = None;
-
-        // final texture out of blur H/V
-        let outline_bloom_texture = None;
-
-        // Texture for the main scene
-        let scene_color_texture= None;
-
-        // / C O M P O S I T E 
-
-        let composite_bind_group = None;
-
-        let composite_texture = None ;
-
-        // / T O N E  M A P 
+        // / G L O W   S T Y L E
+        // / Outline of the model, instances can override it
+
+        let glow_style_buffer = GlowStyleUniform::new(&effects.glow_style()).create_glow_style_buffer(&device);
+        // Every group's outline and occlusion, by stencil reference (written by write_group_styles)
+        let group_styles_buffer = GlowStyleUniform::create_group_styles_buffer(&device);
+        let group_occlusion_buffer = OcclusionUniform::create_group_occlusion_buffer(&device);
+        let (glow_bind_group_layout, glow_bind_group) = GlowStyleUniform::bind_group_for_glow_style(
+            &glow_style_buffer,
+            &group_styles_buffer,
+            &group_occlusion_buffer,
+            &device,
+        );
 
-        let tone_map_bind_group = None ;
+        // / T O N E   M A P
+
+        let tone_map_buffer = ToneMapParams::new(tone_map, exposure, white_point).create_tone_map_params_buffer(&device);
+        let (tone_map_bind_group_layout, tone_map_bind_group) =
+            ToneMapParams::bind_group_for_tone_map_params(&tone_map_buffer, &device);
+
+        // / C O L O R   G R A D I N G
+        // / Every LUT of the config is loaded, the first one is in use
+
+        let color_lut_bind_group_layout = ColorGradeParams::create_color_lut_bind_group_layout(&device);
+        let color_grade_buffer = ColorGradeParams::zeroed().create_color_grade_params_buffer(&device);
+        let mut color_luts = Vec::new();
+        for file_name in &glow_config.color_grading.luts {
+            let lut = resources::load_cube_lut(file_name)
+                .await
+                .with_context(|| format!("cannot load color LUT '{file_name}'"))?;
+            color_luts.push(ColorLut::new(
+                &device,
+                &queue,
+                &color_lut_bind_group_layout,
+                &color_grade_buffer,
+                file_name,
+                &lut,
+            )?);
+        }
+        let color_lut = (!color_luts.is_empty()).then_some(0);
 
         // /
-        // /      P I P E L I N E S
+        // /      R E N D E R   G R A P H
         // /
+        // / Targets are allocated on the first resize
+
+        let scene_layouts = SceneLayouts {
+            texture: diffuse_bind_group_layout,
+            camera: camera_bind_group_layout,
+            spin: spin_bind_group_layout,
+            glow: glow_bind_group_layout,
+            object_id: create_object_id_bind_group_layout(&device),
+            tone_map: tone_map_bind_group_layout,
+            color_lut: color_lut_bind_group_layout,
+        };
 
-        // Pipeline
-        let pipeline_struct = Pipeline::build_render_pipeline(
+        let graph = passes::build_glow_graph(
             &device,
             &config,
-            sample_count,
-            &diffuse_bind_group_layout,
-            &camera_bind_group_layout,
-            &spin_bind_group_layout,
-            is_hdr,
+            &scene_layouts,
+            GlowGraphSettings { sample_count, is_hdr, effects, is_color_graded: color_lut.is_some() },
         )?;
-        let render_pipeline = pipeline_struct.pipeline;        
-
-        // Stencil Pipeline
-        let stencil_pipeline_struct =
-            Pipeline::mask_render_pipeline(&device, &camera_bind_group_layout, &spin_bind_group_layout, sample_count)?;
-
-        let stencil_pipeline = stencil_pipeline_struct.pipeline;
-
-
-        // Outline Stencil pipeline
-
-        let outline_pipeline_struct =
-            Pipeline::outline_pipeline(&device, &config, &camera_bind_group_layout, &spin_bind_group_layout, sample_count, is_hdr)?;
 
-        let outline_pipeline = outline_pipeline_struct.pipeline;
+        log::info!("render graph: {}", graph.pass_names().join(" -> "));
 
-        // Blur pipeline 
+        let profiler = glow_config
+            .renderer
+            .profile
+            .then(|| GpuProfiler::new(&device, &queue));
 
-        let blur_bind_group_layout = create_blur_bind_group_layout(&device);
-
-        let blur_pipeline_struct = Pipeline::blur_pipeline(&device, &config, blur_bind_group_layout, is_hdr)?;
-        let blur_pipeline = blur_pipeline_struct.pipeline;
-
-        // Composite pipeline 
-
-        let composite_bind_group_layout = create_composite_bind_group_layout(&device);
-        let composite_pipeline_struct = Pipeline::composite_pipeline(&device, &config, composite_bind_group_layout, is_hdr)?;
-        let composite_pipeline =composite_pipeline_struct.pipeline;
-
-        // Tone Map pipeline
-
-        let tone_map_bind_group_layout = create_tone_map_bind_group_layout(&device);
-        let tone_map_pipeline_struct  = Pipeline::tone_map_pipeline(&device, &config, tone_map_bind_group_layout)?;
-        let tone_map_pipeline = tone_map_pipeline_struct.pipeline;
-
-
-        // Parallel pipeline 
-
-        let parallel_pass_pipeline_struct = Pipeline::parallel_depth_pipeline(&device,  &camera_bind_group_layout, &spin_bind_group_layout)?;
-
-        let parallel_pass_pipeline = parallel_pass_pipeline_struct.pipeline;
-
-        // Edge pipeline 
-        let edge_pipeline_struct =Pipeline::edge_pipeline(&device, &config, &edge_bind_group_layout, is_hdr)?;
-
-        let edge_pipeline = edge_pipeline_struct.pipeline;
-
-        Ok(Self {
+        let state = Self {
+            adapter,
             surface,
             device,
             queue,
             config,
             is_surface_configured: false,
-            render_pipeline,
             is_paused: false,
             diffuse_bind_group,
             lib_model,
             instances,
             instance_buffer,
+            highlight_groups,
+            highlight_runs,
             camera,
             camera_uniform,
             camera_bind_group,
             camera_buffer,
             camera_controller,
-            depth_stencil,
-            stencil_pipeline,
             last_frame,
+            delta_time: 0.0,
             spin,
             spin_uniform,
             spin_bind_group,
             spin_buffer,
+            glow_style_buffer,
+            group_styles_buffer,
+            group_occlusion_buffer,
+            glow_bind_group,
             sample_count,
-            color_texture,
-            resolve_texture,
-            outline_pipeline,
-            blur_params_uniform_buffer,
-            blur_outline_resolved_bind_group,
-            blur_inter_bind_group,
-            blur_intermediate_texture,
-            outline_bloom_texture,
-            normal_texture,
-            normal_resolve_texture,
-            parallel_depth_texture,
-            parallel_pass_pipeline,
-            linear_sampler,
-            blur_pipeline,
-            scene_color_texture,
-            edge_bind_group,
-            edge_pipeline,
-            edge_texture,
-            composite_bind_group,
-            composite_pipeline,
-            composite_texture,
             is_hdr,
+            effects,
+            tone_map,
+            exposure,
+            white_point,
+            tone_map_buffer,
             tone_map_bind_group,
-            tone_map_pipeline,
+            color_luts,
+            color_lut,
+            lut_strength: glow_config.color_grading.strength,
+            color_grade_buffer,
+            scene_layouts,
+            graph,
+            profiler,
+            last_profiler_report: Instant::now(),
+            offscreen_texture: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            pending_capture: None,
+            cursor_position: None,
             window,
-        })
+        };
+        state.write_group_styles();
+        state.write_color_grade();
+
+        Ok(state)
     }
 
     pub fn resize(&mut self, width: u32, height: u32) {
@@ -367,7 +531,16 @@ impl State {
             self.config.height = height;
 
             // Configure surface
-            self.surface.configure(&self.device, &self.config);
+            match &self.surface {
+                Some(surface) => surface.configure(&self.device, &self.config),
+                None => {
+                    self.offscreen_texture = Some(ColorTexture::create_output_texture(
+                        &self.device,
+                        &self.config,
+                        "Offscreen Output Texture",
+                    ));
+                }
+            }
             self.is_surface_configured = true;
 
             // Camera
@@ -375,582 +548,996 @@ impl State {
 
             // This is a fix from chatgpt otherwise it only works for desktop not for browser.
             self.camera_uniform.update_view_proj(&self.camera);
-            //self.depth_texture = crate::texture::Texture::create_depth_texture(&self.device, &self.config, "depth_texture");
-            
+
             // /
-            // /  T E X T U R E S
-
-            // Depth Stencil
-            self.depth_stencil = depth_stencil::StencilTexture::create_stencil_texture(
-                &self.device,
-                &self.config,
-                "depth_stencil",
-                self.sample_count,
-            );
+            // /  T E X T U R E S  (owned by the render graph)
 
-            self.normal_texture = crate::texture::Texture::create_normal_texture(
-                &self.device, 
-                &self.config, 
-                "normal_texture", 
-                self.sample_count);
-
-            self.normal_resolve_texture = crate::texture::Texture::create_normal_texture(
-                &self.device,
-                &self.config, 
-                "normal resolve texture",
-                1,
-             );
-
-            // Depth only
-            self.parallel_depth_texture = crate::texture::Texture::create_depth_texture(&self.device, &self.config, "pre pass depth_texture");
-
-            // Color texture - Can be multi Sampled -
-            self.color_texture = Some(ColorTexture::create_color_texture(
-                &self.device, 
-                &self.config, 
-                "Color Texture",
-                self.sample_count,
-                self.is_hdr,
-            ));
-
-
-
-            // Resolve texture - single sampled.
-            self.resolve_texture = Some(ColorTexture::create_color_texture(&self.device, &self.config, "Resolve Color  Texture", 1, self.is_hdr,)); 
-            
-            self.blur_intermediate_texture = Some(ColorTexture::create_color_texture(&self.device, &self.config, "Blur Intermediate Color Texture", 1, self.is_hdr,));
-            
-            self.outline_bloom_texture  = Some(ColorTexture::create_color_texture(&self.device, &self.config, "Bloom Color Texture", 1, self.is_hdr,));
-            
-            self.scene_color_texture  = Some(ColorTexture::create_color_texture(&self.device, &self.config, "Scene Color Texture", 1, self.is_hdr,));
-
-            let blur_bind_group_layout = create_blur_bind_group_layout(&self.device);
-
-            let blur_resolve_texture_view = if self.sample_count == 1 
-            {
-                &self.color_texture.as_ref().ok_or("cannot get texture").unwrap().view
+            if let Err(e) = self.graph.resize(&self.device, width, height) {
+                log::error!("render graph resize failed: {e:#}");
             }
-            else {
-                &self.resolve_texture.as_ref().ok_or("cannot get texture").unwrap().view
-            };
-
-            self.blur_outline_resolved_bind_group = Some(create_blur_bind_group(
-                &self.device, 
-                &blur_bind_group_layout, 
-                blur_resolve_texture_view,
-                &self.linear_sampler, 
-                &self.blur_params_uniform_buffer
-            ));
-
-            self.blur_inter_bind_group = Some(create_blur_bind_group(
-                &self.device, 
-                &blur_bind_group_layout, 
-                &self.blur_intermediate_texture.as_ref().ok_or("cannot get texture").unwrap().view,
-                &self.linear_sampler, 
-                &self.blur_params_uniform_buffer
-            ));
-
-
-
-            // edge detection 
-
-            // Edge texture 
-            self.edge_texture = Some(ColorTexture::create_color_texture(&self.device, &self.config, "Edge Color Texture", 1, self.is_hdr,));
-
-            let edge_bind_group_layout = create_edge_bind_group_layout(&self.device);
-
-            self.edge_bind_group = Some(create_edge_bind_group(
-                &self.device, 
-                &edge_bind_group_layout, 
-                &self.scene_color_texture.as_ref().ok_or("cannot get texture").unwrap().view, 
-                &self.normal_resolve_texture.sampler, 
-                &self.parallel_depth_texture.view, 
-                &self.normal_resolve_texture.view
-            ));
-
-
-        // / C O M P O S I T E 
-
-        let composite_bind_group_layout = create_composite_bind_group_layout(&self.device);
-
-        self.composite_bind_group = Some(create_composite_bind_group(
-            &self.device, 
-            &composite_bind_group_layout, 
-            //&self.scene_color_texture.as_ref().ok_or("cannot get texture").unwrap().view,
-            &self.edge_texture.as_ref().ok_or("cannot get texture").unwrap().view,
-            &self.outline_bloom_texture.as_ref().ok_or("cannot get texture").unwrap().view,
-            &self.resolve_texture.as_ref().ok_or("cannot get texture").unwrap().view,
-            &self.linear_sampler));
-        
-        self.composite_texture = Some(ColorTexture::create_color_texture(&self.device, &self.config, "Composite Color Texture", 1, self.is_hdr,));
-        
-
-        // / T O N E  M A P 
-
-        let tone_map_bind_group_layout = create_tone_map_bind_group_layout(&self.device);
-
-        self.tone_map_bind_group = Some(create_tone_map_bind_group(
-            &self.device, 
-            &tone_map_bind_group_layout,
-            &self.composite_texture.as_ref().ok_or("cannot get texture").unwrap().view,
-            &self.linear_sampler,
-        ));
-
         }
     }
 
-    pub fn update(&mut self) {
-                // Delta time
-        let now = Instant::now();
-        let mut dt = (now - self.last_frame).as_secs_f32();
-        self.last_frame = now;
+    // / Rebuild every glow pass (sample count / HDR changed) and reallocate the targets.
+    pub fn rebuild_render_graph(&mut self) -> anyhow::Result<()> {
+
+        self.graph = passes::build_glow_graph(
+            &self.device,
+            &self.config,
+            &self.scene_layouts,
+            GlowGraphSettings {
+                sample_count: self.sample_count,
+                is_hdr: self.is_hdr,
+                effects: self.effects,
+                is_color_graded: self.color_lut.is_some(),
+            },
+        )?;
 
-        // Clamp for browser tab resume
-        dt = dt.min(0.1);
+        self.graph.resize(&self.device, self.config.width, self.config.height)
+    }
 
-        if !self.is_paused {
-            // Update logic
-            self.spin.update(dt);
+    // / Switch MSAA (1 or 4) at runtime: every target and pipeline of the graph is rebuilt.
+    // / Falls back to 1 sample when the adapter can't multisample a glow target.
+    // / Returns the sample count in use.
+    pub fn set_sample_count(&mut self, sample_count: u32) -> anyhow::Result<u32> {
 
-            // Update GPU data
-            self.spin_uniform.update_from_angle(self.spin.angle());
+        if !matches!(sample_count, 1 | 4) {
+            anyhow::bail!("sample count must be 1 or 4, got {sample_count}");
+        }
 
-            self.queue.write_buffer(
-                &self.spin_buffer,
-                0,
-                bytemuck::bytes_of(&[self.spin_uniform]),
-            );
+        let sample_count = match unsupported_sample_count(&self.adapter, self.is_hdr, self.config.format, sample_count) {
+            Some((name, format)) => {
+                log::warn!("MSAA x{sample_count} not supported for the {name} target ({format:?}), using 1 sample");
+                1
+            }
+            None => sample_count,
+        };
+
+        if sample_count == self.sample_count {
+            return Ok(sample_count);
         }
-        // Camera
-        self.camera_controller.update_camera(&mut self.camera);
+
+        let previous = self.sample_count;
+        self.sample_count = sample_count;
+
+        // Keep a working graph if the rebuild fails
+        if let Err(e) = self.rebuild_render_graph() {
+            self.sample_count = previous;
+            self.rebuild_render_graph()?;
+            return Err(e);
+        }
+
+        Ok(sample_count)
     }
 
-    pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+    pub fn sample_count(&self) -> u32 {
+        self.sample_count
+    }
 
-        self.window.request_redraw();
+    // / Switch the separable blur between render and compute passes at runtime. Falls back
+    // / to render passes where the compute blur can't run. Returns the path in use.
+    pub fn set_blur_path(&mut self, blur_path: BlurPath) -> anyhow::Result<BlurPath> {
 
-        // We can't render unless the surface is configured
-        if !self.is_surface_configured {
-            return Ok(());
+        let blur_path = usable_blur_path(&self.adapter, &EffectsConfig { blur_path, ..self.effects });
+
+        if blur_path == self.effects.blur_path {
+            return Ok(blur_path);
         }
 
-        // Framebuffer / swapchain
-        let output = self.surface.get_current_texture()?;
+        let previous = self.effects.blur_path;
+        self.effects.blur_path = blur_path;
 
-        let view = output
-            .texture
-            .create_view(&wgpu::TextureViewDescriptor::default());
+        // Keep a working graph if the rebuild fails
+        if let Err(e) = self.rebuild_render_graph() {
+            self.effects.blur_path = previous;
+            self.rebuild_render_graph()?;
+            return Err(e);
+        }
 
+        Ok(blur_path)
+    }
 
-        let mut encoder = self
-            .device
-            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                label: Some("Render Encoder"),
-            });
+    pub fn blur_path(&self) -> BlurPath {
+        self.effects.blur_path
+    }
 
-        // Camera uniform normal mode (for non-reflected mode)
+    // / Switch between extruded and jump flood outlines at runtime. Falls back to extruded
+    // / outlines where the jump flood can't run. Returns the mode in use.
+    pub fn set_outline_mode(&mut self, outline_mode: OutlineMode) -> anyhow::Result<OutlineMode> {
 
-        self.camera_uniform.update_view_proj(&self.camera);
+        let outline_mode = usable_outline_mode(&self.adapter, &EffectsConfig { outline_mode, ..self.effects });
 
-        // / W R I T E  C A M E R A  B U F F E R 
+        if outline_mode == self.effects.outline_mode {
+            return Ok(outline_mode);
+        }
 
-        self.queue.write_buffer(
-            &self.camera_buffer,
-            0,
-            bytemuck::cast_slice(&[self.camera_uniform]),
-        );
+        let previous = self.effects.outline_mode;
+        self.effects.outline_mode = outline_mode;
 
+        // Keep a working graph if the rebuild fails
+        if let Err(e) = self.rebuild_render_graph() {
+            self.effects.outline_mode = previous;
+            self.rebuild_render_graph()?;
+            return Err(e);
+        }
 
-    
-        // P A R A L L E L  P A S S - D E P T H   O N L Y 
-
-        let mut parallel_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-            label: Some("Depth Prepass"),
-            color_attachments: &[],
-            depth_stencil_attachment: Some(
-                wgpu::RenderPassDepthStencilAttachment {
-                    view: &self.parallel_depth_texture.view,
-                    depth_ops: Some(wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(1.0),
-                        store: wgpu::StoreOp::Store,
-                    }),
-                    stencil_ops: None,
-                }
-            ),
-            occlusion_query_set: None,
-            timestamp_writes: None,
-            multiview_mask: None,
-        });
+        Ok(outline_mode)
+    }
 
-        parallel_pass.set_pipeline(&self.parallel_pass_pipeline);
-        parallel_pass.set_bind_group(0, &self.camera_bind_group, &[]);
-        parallel_pass.set_bind_group(1, &self.spin_bind_group, &[]);
-        parallel_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+    pub fn outline_mode(&self) -> OutlineMode {
+        self.effects.outline_mode
+    }
 
-        parallel_pass.draw_mesh_instanced(&self.lib_model.meshes[0], 0..self.instances.len() as u32);       
+    // / Switch the edge detection kernel at runtime (the edge pass is rebuilt).
+    pub fn set_edge_operator(&mut self, edge_operator: EdgeOperator) -> anyhow::Result<()> {
 
+        if edge_operator == self.effects.edge_operator {
+            return Ok(());
+        }
 
-        drop(parallel_pass);   
-        // /
-        //
-        // S T E N C I L   P A S S
-        //
+        let previous = self.effects.edge_operator;
+        self.effects.edge_operator = edge_operator;
 
+        // Keep a working graph if the rebuild fails
+        if let Err(e) = self.rebuild_render_graph() {
+            self.effects.edge_operator = previous;
+            self.rebuild_render_graph()?;
+            return Err(e);
+        }
 
-        let mut stencil_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-            label: Some("stencil pass"),
-            color_attachments: &[],
-            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                view: &self.depth_stencil.view,
-                depth_ops: Some(wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(1.),
-                    store: wgpu::StoreOp::Store,
-                }),
-                stencil_ops: Some(wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(0),
-                    store: wgpu::StoreOp::Store,
-                }),
-            }),
-            occlusion_query_set: None,
-            timestamp_writes: None,
-            multiview_mask: None,
-        });
-        stencil_pass.set_stencil_reference(1);
-        stencil_pass.set_pipeline(&self.stencil_pipeline);
-        stencil_pass.set_bind_group(0, &self.camera_bind_group, &[]);
-        stencil_pass.set_bind_group(1, &self.spin_bind_group, &[]);
-        stencil_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+        Ok(())
+    }
+
+    pub fn edge_operator(&self) -> EdgeOperator {
+        self.effects.edge_operator
+    }
 
-        //stencil_pass.draw_mesh_instanced(&self.lib_model.meshes[0], 0..self.instances.len() as u32);
-                
-                
-        for mesh in &self.lib_model.meshes {
+    // / Tone map operator of the last pass, switched without rebuilding the graph.
+    pub fn set_tone_map(&mut self, tone_map: ToneMapOperator) {
+        self.tone_map = tone_map;
+        self.write_tone_map();
+    }
 
-            stencil_pass.draw_mesh_instanced(&mesh, 0..self.instances.len() as u32);
- 
-        }
+    pub fn tone_map(&self) -> ToneMapOperator {
+        self.tone_map
+    }
 
-        drop(stencil_pass);
+    // / Exposure in stops (EV) before the tone map, 0 leaves the scene as is.
+    pub fn set_exposure(&mut self, exposure: f32) -> anyhow::Result<()> {
+        validate_tone_map(exposure, self.white_point)?;
+        self.exposure = exposure;
+        self.write_tone_map();
+        Ok(())
+    }
 
-        // /
-        // /   O U T L I N E   S T E N C I L
-        // /
+    pub fn exposure(&self) -> f32 {
+        self.exposure
+    }
 
+    // / Scene brightness mapped to white by the extended Reinhard and Uncharted 2 operators.
+    pub fn set_white_point(&mut self, white_point: f32) -> anyhow::Result<()> {
+        validate_tone_map(self.exposure, white_point)?;
+        self.white_point = white_point;
+        self.write_tone_map();
+        Ok(())
+    }
 
-        let outline_pass_color_attachments = if self.sample_count == 1 
-            {
-                wgpu::RenderPassColorAttachment {
-                    view: &self.color_texture.as_ref().ok_or(wgpu::SurfaceError::Lost)?.view,
-                    //view: &view,
-                    depth_slice: None,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                           load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
-                        store: wgpu::StoreOp::Store,
-                    },
-                }
-            } else {
-                wgpu::RenderPassColorAttachment {
-                    view: &self.color_texture.as_ref().ok_or(wgpu::SurfaceError::Lost)?.view,
-                    //view: &view,
-                    depth_slice: None,
-                    resolve_target: Some(&self.resolve_texture.as_ref().ok_or(wgpu::SurfaceError::Lost)?.view),
-                    ops: wgpu::Operations {
-                            load: wgpu::LoadOp::Clear(wgpu::Color::TRANSPARENT),
-                        store: wgpu::StoreOp::Store,
-                    },
-                }
-            };
-        
+    pub fn white_point(&self) -> f32 {
+        self.white_point
+    }
 
-        let outline_depth_stencil_attachment = wgpu::RenderPassDepthStencilAttachment {
-                view: &self.depth_stencil.view,
-                depth_ops: Some(wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(1.), // <- clear depth again
-                    store: wgpu::StoreOp::Store,
-                }),
+    fn write_tone_map(&self) {
+        let params = ToneMapParams::new(self.tone_map, self.exposure, self.white_point);
+        self.queue.write_buffer(&self.tone_map_buffer, 0, bytemuck::bytes_of(&params));
+    }
 
-                stencil_ops: Some(wgpu::Operations {
-                load: wgpu::LoadOp::Load, // <- keep mask
-                store: wgpu::StoreOp::Store,
-                }) ,
-            };
-
-        let mut outline_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: Some("outline Pass"),
-                color_attachments: &[Some(outline_pass_color_attachments)],
-                depth_stencil_attachment: Some(outline_depth_stencil_attachment),
-                occlusion_query_set: None,
-                timestamp_writes: None,
-                multiview_mask: None,
-        });
+    // / Upload a LUT for color grading, under `name` (its file for the config ones). Returns
+    // / its index for `set_color_lut`.
+    pub fn add_color_lut(&mut self, name: &str, lut: &CubeLut) -> anyhow::Result<usize> {
+        let layout = &self.scene_layouts.color_lut;
+        let color_lut = ColorLut::new(&self.device, &self.queue, layout, &self.color_grade_buffer, name, lut)?;
+        self.color_luts.push(color_lut);
+        Ok(self.color_luts.len() - 1)
+    }
 
-        outline_pass.set_pipeline(&self.outline_pipeline);
-        outline_pass.set_stencil_reference(1);
-        outline_pass.set_bind_group(0, &self.camera_bind_group, &[]);
-        outline_pass.set_bind_group(1, &self.spin_bind_group, &[]);
-        outline_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
+    // / Load a .cube LUT from res/ (fetched on the web), see `add_color_lut`.
+    pub async fn load_color_lut(&mut self, file_name: &str) -> anyhow::Result<usize> {
+        let lut = resources::load_cube_lut(file_name)
+            .await
+            .with_context(|| format!("cannot load color LUT '{file_name}'"))?;
+        self.add_color_lut(file_name, &lut)
+    }
 
-        // outline_pass.draw_mesh_instanced(&self.lib_model.meshes[0], 0..self.instances.len() as u32);
-        for mesh in &self.lib_model.meshes {
+    // / Grade the frame with a loaded LUT, or not (None). Swapping LUTs keeps the graph,
+    // / turning grading on or off adds or removes the color grade pass.
+    pub fn set_color_lut(&mut self, index: Option<usize>) -> anyhow::Result<()> {
 
-            outline_pass.draw_mesh_instanced(&mesh, 0..self.instances.len() as u32);
- 
+        if let Some(i) = index
+            && i >= self.color_luts.len()
+        {
+            anyhow::bail!("no color LUT {i}, {} loaded", self.color_luts.len());
         }
-        
-        drop(outline_pass);
 
-        // /
-        //  B L U R   O U T L I N E S
+        let previous = self.color_lut;
+        self.color_lut = index;
 
-        // Horizontal blur
-        self.queue.write_buffer(
-            &self.blur_params_uniform_buffer,
-            0,
-            bytemuck::bytes_of(&BlurParams {
-                direction: [1.0, 0.0],
-                _padding: [0.0; 2]
-            }),
-        );
+        // Keep a working graph if the rebuild fails
+        if previous.is_some() != index.is_some()
+            && let Err(e) = self.rebuild_render_graph()
+        {
+            self.color_lut = previous;
+            self.rebuild_render_graph()?;
+            return Err(e);
+        }
 
-        let blur_horizontal_pass_color_attachments = 
-                wgpu::RenderPassColorAttachment {
-                    view: &self.blur_intermediate_texture.as_ref().ok_or(wgpu::SurfaceError::Lost)?.view,
-                    depth_slice: None,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
-                        store: wgpu::StoreOp::Store,
-                    },
-                };
-    
-        let mut blur_horizontal_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-            label: Some("horizontal blur"),
-            color_attachments: &[Some(blur_horizontal_pass_color_attachments)],
-            depth_stencil_attachment: None,
-            timestamp_writes: None,
-            occlusion_query_set: None,
-            multiview_mask: None,
-        });
-        blur_horizontal_pass.set_pipeline(&self.blur_pipeline);
-        blur_horizontal_pass.set_bind_group(0, self.blur_outline_resolved_bind_group.as_ref().ok_or(wgpu::SurfaceError::Lost)?, &[]);
-        blur_horizontal_pass.draw(0..3, 0..1);
+        self.write_color_grade();
+        Ok(())
+    }
 
-        drop(blur_horizontal_pass);
+    pub fn color_lut(&self) -> Option<usize> {
+        self.color_lut
+    }
 
-        // Vertical blur
-        self.queue.write_buffer(
-            &self.blur_params_uniform_buffer,
-            0,
-            bytemuck::bytes_of(&BlurParams {
-                direction: [0.0, 1.0],
-                _padding: [0.0; 2]
-            }),
-        );
+    // / Names of the loaded LUTs, by index
+    pub fn color_lut_names(&self) -> Vec<&str> {
+        self.color_luts.iter().map(|lut| lut.name.as_str()).collect()
+    }
 
-        let blur_vertical_pass_color_attachments = 
-                    wgpu::RenderPassColorAttachment {
-                        view: &self.outline_bloom_texture.as_ref().ok_or(wgpu::SurfaceError::Lost)?.view,
-                        depth_slice: None,
-                        resolve_target: None,
-                        ops: wgpu::Operations {
-                            load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
-                            store: wgpu::StoreOp::Store,
-                    },
-                };
-        
-        let mut blur_vertical_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-            label: Some("vertical blur"),
-            color_attachments: &[Some(blur_vertical_pass_color_attachments)],
-            depth_stencil_attachment: None,
-            timestamp_writes: None,
-            occlusion_query_set: None,
-            multiview_mask: None,
-        });
-        blur_vertical_pass.set_pipeline(&self.blur_pipeline);
-        blur_vertical_pass.set_bind_group(0, self.blur_inter_bind_group.as_ref().ok_or(wgpu::SurfaceError::Lost)?, &[]);
-        blur_vertical_pass.draw(0..3, 0..1);
+    // / How much of the LUT shows: 0 (tone mapped frame) to 1 (fully graded).
+    pub fn set_lut_strength(&mut self, strength: f32) -> anyhow::Result<()> {
+        if !(0.0..=1.0).contains(&strength) {
+            anyhow::bail!("LUT strength must be in [0, 1], got {strength}");
+        }
+        self.lut_strength = strength;
+        self.write_color_grade();
+        Ok(())
+    }
 
-        drop(blur_vertical_pass);
+    pub fn lut_strength(&self) -> f32 {
+        self.lut_strength
+    }
 
+    fn write_color_grade(&self) {
+        let Some(i) = self.color_lut else { return };
+        let params = ColorGradeParams { strength: self.lut_strength, ..self.color_luts[i].params };
+        self.queue.write_buffer(&self.color_grade_buffer, 0, bytemuck::bytes_of(&params));
+    }
 
-        // /
-        // T O T A L  S C E N E
-        // /
+    // / Meter the exposure from the frame (histogram or downsample), or not. The histogram
+    // / falls back to downsample passes without compute shaders. Returns the mode in use.
+    pub fn set_auto_exposure(&mut self, auto_exposure: AutoExposure) -> anyhow::Result<AutoExposure> {
 
+        let auto_exposure = usable_auto_exposure(&self.adapter, &EffectsConfig { auto_exposure, ..self.effects });
 
-        
-        // Color attachments
-        let render_pass_color_attachments = if self.sample_count == 1
-             {  
-                wgpu::RenderPassColorAttachment {
-                    //view: &view,
-                    view: &self.scene_color_texture.as_ref().ok_or(wgpu::SurfaceError::Lost)?.view,
-                    depth_slice: None,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color{ 
-                            r: 0.67, 
-                            g: 0.27, 
-                            b: 0.15, 
-                            a: 1. }),                         
-                        store: wgpu::StoreOp::Store,
-                    },
-                }
+        if auto_exposure == self.effects.auto_exposure {
+            return Ok(auto_exposure);
+        }
 
-            } else {
-                wgpu::RenderPassColorAttachment {
-                    view: &self.color_texture.as_ref().ok_or(wgpu::SurfaceError::Lost)?.view,
-                    depth_slice: None,
-                    resolve_target: Some(&self.scene_color_texture.as_ref().ok_or(wgpu::SurfaceError::Lost)?.view),
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK), 
-                        store: wgpu::StoreOp::Discard,
-                    },
-                }
+        let previous = self.effects.auto_exposure;
+        self.effects.auto_exposure = auto_exposure;
+
+        // Keep a working graph if the rebuild fails
+        if let Err(e) = self.rebuild_render_graph() {
+            self.effects.auto_exposure = previous;
+            self.rebuild_render_graph()?;
+            return Err(e);
+        }
+
+        Ok(auto_exposure)
+    }
+
+    pub fn auto_exposure(&self) -> AutoExposure {
+        self.effects.auto_exposure
+    }
+
+    // / Exposure metered from the last rendered frame (EV, on top of `exposure`), None
+    // / without auto exposure.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn metered_exposure(&self) -> anyhow::Result<Option<f32>> {
+        let Ok(target) = self.graph.targets().get(passes::EXPOSURE) else {
+            return Ok(None);
         };
+        let texel = crate::readback::read_texture_rgba32f(&self.device, &self.queue, &target.texture)?;
+        Ok(Some(texel.get_pixel(0, 0)[0]))
+    }
 
-        // Normal attachments
-
-        let normal_color_attachments = if self.sample_count == 1 // 
-           { 
-                wgpu::RenderPassColorAttachment {
-                    view: &self.normal_resolve_texture.view,
-                    depth_slice: None,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color::RED),
-                        store: wgpu::StoreOp::Store, // We need this for the next pass!
-                    },
-                }
-            } else {
-                wgpu::RenderPassColorAttachment {
-                    view: &self.normal_texture.view,
-                    depth_slice: None,
-                    resolve_target: Some(&self.normal_resolve_texture.view),
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color::RED),
-                        store: wgpu::StoreOp::Store, 
-                    },
-                }
-            };
-        
-        let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-            label: Some("Render Total Scene Pass"),
-            color_attachments: &[Some(render_pass_color_attachments), Some(normal_color_attachments)],
-            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
-                view: &self.depth_stencil.view,
-                depth_ops: Some(wgpu::Operations {
-                    load: wgpu::LoadOp::Load, // <- clear depth again
-                    store: wgpu::StoreOp::Store,
-                }),
+    // / Switch the glow passes between HDR (Rgba16Float + tone map) and SDR (surface format,
+    // / tone map pass-through) at runtime. Every target and pipeline of the graph is rebuilt.
+    // / MSAA falls back to 1 sample if the new color format can't be multisampled.
+    pub fn set_hdr(&mut self, is_hdr: bool) -> anyhow::Result<()> {
 
-                stencil_ops: Some(wgpu::Operations {
-                load: wgpu::LoadOp::Load, // <- keep mask
-                store: wgpu::StoreOp::Store,
-                }) ,
-            }),
-            occlusion_query_set: None,
-            timestamp_writes: None,
-            multiview_mask: None,
-           
+        if is_hdr == self.is_hdr {
+            return Ok(());
+        }
+
+        if let Some((name, format)) = unrenderable_target(&self.adapter, is_hdr, self.config.format) {
+            anyhow::bail!("{name} target format {format:?} is not renderable on this adapter");
+        }
+
+        let previous = (self.is_hdr, self.sample_count);
+        self.is_hdr = is_hdr;
+
+        if let Some((name, format)) = unsupported_sample_count(&self.adapter, is_hdr, self.config.format, self.sample_count) {
+            log::warn!("MSAA x{} not supported for the {name} target ({format:?}), using 1 sample", self.sample_count);
+            self.sample_count = 1;
+        }
+
+        // Keep a working graph if the rebuild fails
+        if let Err(e) = self.rebuild_render_graph() {
+            (self.is_hdr, self.sample_count) = previous;
+            self.rebuild_render_graph()?;
+            return Err(e);
+        }
+
+        Ok(())
+    }
+
+    pub fn is_hdr(&self) -> bool {
+        self.is_hdr
+    }
+
+    // / VSync on/off/adaptive at runtime. Only modes the surface reports are used (see
+    // / `PresentMode::resolve`). Returns the mode in use. Headless: nothing to present, no-op.
+    pub fn set_present_mode(&mut self, present_mode: PresentMode) -> wgpu::PresentMode {
+
+        if let Some(surface) = &self.surface {
+            let supported = surface.get_capabilities(&self.adapter).present_modes;
+            self.config.present_mode = present_mode.resolve(&supported);
+            self.reconfigure_surface();
+        }
+
+        self.config.present_mode
+    }
+
+    // / VSync on -> adaptive -> off (mailbox) -> off (immediate), skipping the modes the surface lacks.
+    pub fn cycle_present_mode(&mut self) -> wgpu::PresentMode {
+
+        const CYCLE: [(wgpu::PresentMode, PresentMode); 4] = [
+            (wgpu::PresentMode::Fifo, PresentMode::Fifo),
+            (wgpu::PresentMode::FifoRelaxed, PresentMode::FifoRelaxed),
+            (wgpu::PresentMode::Mailbox, PresentMode::Mailbox),
+            (wgpu::PresentMode::Immediate, PresentMode::Immediate),
+        ];
+
+        let Some(surface) = &self.surface else {
+            return self.config.present_mode;
+        };
+        let supported = surface.get_capabilities(&self.adapter).present_modes;
+
+        let current = CYCLE.iter().position(|(mode, _)| *mode == self.config.present_mode);
+        let next = (1..=CYCLE.len())
+            .map(|step| CYCLE[(current.unwrap_or(CYCLE.len() - 1) + step) % CYCLE.len()])
+            .find(|(mode, _)| supported.contains(mode))
+            .map_or(PresentMode::Fifo, |(_, next)| next);
+
+        self.set_present_mode(next)
+    }
+
+    // / Frames queued ahead of the display (1 = lowest latency).
+    pub fn set_frame_latency(&mut self, frame_latency: u32) -> anyhow::Result<()> {
+
+        if frame_latency == 0 {
+            anyhow::bail!("frame latency must be >= 1");
+        }
+
+        self.config.desired_maximum_frame_latency = frame_latency;
+        self.reconfigure_surface();
+
+        Ok(())
+    }
+
+    fn reconfigure_surface(&self) {
+        if let Some(surface) = &self.surface
+            && self.is_surface_configured
+        {
+            surface.configure(&self.device, &self.config);
+        }
+    }
+
+    // / Per-pass timings on/off. GPU timestamps when the device has TIMESTAMP_QUERY,
+    // / CPU encoding times otherwise (WebGL).
+    pub fn set_profiling(&mut self, is_enabled: bool) {
+        if is_enabled == self.profiler.is_some() {
+            return;
+        }
+
+        self.profiler = is_enabled.then(|| GpuProfiler::new(&self.device, &self.queue));
+        self.last_profiler_report = Instant::now();
+
+        if let Some(profiler) = &self.profiler {
+            log::info!("profiler on ({:?})", profiler.mode());
+        }
+    }
+
+    pub fn profiler(&self) -> Option<&GpuProfiler> {
+        self.profiler.as_ref()
+    }
+
+    // / Read the finished timestamps (async), log the averages once per second.
+    fn collect_pass_timings(&mut self) {
+        let Some(profiler) = &mut self.profiler else {
+            return;
+        };
+
+        profiler.after_submit(&self.device);
+
+        if self.last_profiler_report.elapsed().as_secs_f32() >= 1.0 {
+            self.last_profiler_report = Instant::now();
+            log::info!("{}", profiler.report());
+        }
+    }
+
+    // / Insert, remove or reorder effects. The graph is recompiled and its targets reallocated.
+    pub fn edit_render_graph(
+        &mut self,
+        edit: impl FnOnce(&mut RenderGraph, &wgpu::Device, &wgpu::SurfaceConfiguration) -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+
+        edit(&mut self.graph, &self.device, &self.config)?;
+
+        self.graph.compile()?;
+        self.graph.resize(&self.device, self.config.width, self.config.height)
+    }
+
+    pub fn update(&mut self) {
+                // Delta time
+        let now = Instant::now();
+        let mut dt = (now - self.last_frame).as_secs_f32();
+        self.last_frame = now;
+
+        // Clamp for browser tab resume
+        dt = dt.min(0.1);
+
+        self.advance(dt);
+    }
+
+    // / Step the animation by a fixed `dt` (seconds), independent of the wall clock.
+    pub fn advance(&mut self, dt: f32) {
+        self.delta_time = dt;
+        if !self.is_paused {
+            // Update logic
+            self.spin.update(dt);
+
+            // Update GPU data
+            self.spin_uniform.update_from_angle(self.spin.angle());
+
+            self.queue.write_buffer(
+                &self.spin_buffer,
+                0,
+                bytemuck::bytes_of(&[self.spin_uniform]),
+            );
+        }
+        // Camera
+        self.camera_controller.update_camera(&mut self.camera);
+    }
+
+    pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+
+        if let Some(window) = &self.window {
+            window.request_redraw();
+        }
+
+        // We can't render unless the surface is configured
+        if !self.is_surface_configured {
+            return Ok(());
+        }
+
+        // Framebuffer / swapchain
+        let output = match &self.surface {
+            Some(surface) => surface.get_current_texture()?,
+            None => return Ok(()), // headless: see render_to_image
+        };
+
+        let view = output
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder"),
+            });
+
+        self.write_camera_buffer();
+
+        self.encode_frame(&mut encoder, &view)?;
+
+        // submit will accept anything that implements IntoIter
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        // Before present: the swapchain texture is still ours
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(request) = self.pending_capture.take() {
+            match self.save_capture(request, &output.texture) {
+                Ok(path) => log::info!("capture saved to {}", path.display()),
+                Err(e) => log::error!("capture failed: {e:#}"),
+            }
+        }
+
+        output.present();
+
+        self.collect_pass_timings();
+
+        Ok(())
+    }
+
+    // / Headless render: run every pass into the offscreen target and read the
+    // / tone mapped frame back to the CPU.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn render_to_image(&mut self) -> anyhow::Result<image::RgbaImage> {
+
+        self.write_camera_buffer();
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Offscreen Render Encoder"),
+            });
+
+        // wgpu handles: cheap clones, `self` stays free for encode_frame
+        let (output_texture, output_view) = self
+            .offscreen_texture
+            .as_ref()
+            .map(|output| (output.texture.clone(), output.view.clone()))
+            .ok_or(anyhow::anyhow!("no offscreen target, state is not headless"))?;
+
+        self.encode_frame(&mut encoder, &output_view)?;
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        let image = crate::readback::read_texture_rgba8(&self.device, &self.queue, &output_texture);
+
+        self.collect_pass_timings();
+
+        image
+    }
+
+    // / Copy a target of the last rendered frame to the CPU (save it with `Capture::save`).
+    // / ToneMap is the final frame: the offscreen target when headless, otherwise the frame is
+    // / rendered again offscreen in the surface format.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn capture(&mut self, target: FrameTarget) -> anyhow::Result<Capture> {
+
+        let missing = || anyhow::anyhow!("target {} is not allocated", target.name());
+
+        let texture = match target {
+            FrameTarget::StencilMask => return Ok(Capture::Rgba8(self.read_stencil_mask()?)),
+            FrameTarget::ToneMap => match &self.offscreen_texture {
+                Some(output) => output.texture.clone(),
+                None => return self.capture_offscreen_frame(),
+            },
+            _ => {
+                let id = target.resource().ok_or_else(missing)?;
+                self.graph.targets().get(id)?.texture.clone()
+            }
+        };
+
+        if texture.format().is_depth_stencil_format() {
+            return Ok(Capture::Depth(self.read_depth(&texture)?));
+        }
+
+        Capture::read(&self.device, &self.queue, &texture)
+    }
+
+    // / 8 bit version of `capture` (golden images).
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn read_target(&mut self, target: FrameTarget) -> anyhow::Result<image::RgbaImage> {
+        Ok(self.capture(target)?.to_rgba8())
+    }
+
+    // / Instance and mesh under pixel (x, y) of the last rendered frame, None on the background.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn pick(&self, x: u32, y: u32) -> anyhow::Result<Option<ObjectId>> {
+
+        let texture = &self.graph.targets().get(passes::OBJECT_ID)?.texture;
+        let texel = crate::readback::read_texture_region_bytes(
+            &self.device,
+            &self.queue,
+            texture,
+            wgpu::TextureAspect::All,
+            4,
+            (x, y),
+            (1, 1),
+        )?;
+
+        Ok(ObjectId::from_raw(u32::from_le_bytes([texel[0], texel[1], texel[2], texel[3]])))
+    }
+
+    // / Windowed: render one more frame into a texture that can be copied.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn capture_offscreen_frame(&mut self) -> anyhow::Result<Capture> {
+
+        let output = ColorTexture::create_color_texture(&self.device, &self.config, "capture frame texture", 1, false);
+
+        self.write_camera_buffer();
+
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Capture Render Encoder"),
         });
+        self.encode_frame(&mut encoder, &output.view)?;
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        self.collect_pass_timings();
 
-        render_pass.set_pipeline(&self.render_pipeline);
-        render_pass.set_stencil_reference(1);
-       // render_pass.set_bind_group(0, &self.diffuse_bind_group, &[]);
-        render_pass.set_bind_group(1, &self.camera_bind_group, &[]);
-        render_pass.set_bind_group(2, &self.spin_bind_group, &[]);
-        render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
-   
-        //render_pass.draw_mesh_instanced(&self.lib_model.meshes[0], 0..self.instances.len() as u32);
+        Capture::read(&self.device, &self.queue, &output.texture)
+    }
+
+    // / F12 / F11, called with the swapchain texture of the frame just submitted.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn save_capture(&mut self, request: CaptureRequest, frame: &wgpu::Texture) -> anyhow::Result<std::path::PathBuf> {
 
-        for mesh in &self.lib_model.meshes {
-            render_pass.set_bind_group(0, &self.lib_model.materials[mesh.material].bind_group, &[]);
-            //render_pass.set_bind_group(0, &self.diffuse_bind_group, &[]);
+        let stamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let dir = std::path::Path::new(SCREENSHOT_DIR);
 
-            render_pass.draw_mesh_instanced(&mesh, 0..self.instances.len() as u32);
- 
+        // The swapchain can only be copied with COPY_SRC (see Visualizer::new)
+        let capture_frame = |state: &mut Self| {
+            if frame.usage().contains(wgpu::TextureUsages::COPY_SRC) {
+                Capture::read(&state.device, &state.queue, frame)
+            } else {
+                state.capture(FrameTarget::ToneMap)
+            }
+        };
+
+        match request {
+            CaptureRequest::Frame => {
+                std::fs::create_dir_all(dir)?;
+                let path = dir.join(format!("glow-{stamp}.png"));
+                capture_frame(self)?.save(&path)?;
+                Ok(path)
+            }
+            CaptureRequest::AllTargets => {
+                let dir = dir.join(format!("glow-{stamp}"));
+                std::fs::create_dir_all(&dir)?;
+
+                for target in FrameTarget::ALL {
+                    let capture = match target {
+                        FrameTarget::ToneMap => capture_frame(self),
+                        _ => self.capture(target),
+                    };
+                    let capture = match capture {
+                        Ok(capture) => capture,
+                        Err(e) => {
+                            log::warn!("cannot capture {}: {e:#}", target.name());
+                            continue;
+                        }
+                    };
+
+                    capture.save(&dir.join(format!("{}.png", target.name())))?;
+                    if !matches!(capture, Capture::Rgba8(_)) {
+                        capture.save(&dir.join(format!("{}.exr", target.name())))?;
+                    }
+                }
+                Ok(dir)
+            }
         }
+    }
 
-        drop(render_pass);
+    // / Depth can't be copied to a buffer everywhere (GL): pack it into an 8 bit target, unpack here.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn read_depth(&self, depth_texture: &wgpu::Texture) -> anyhow::Result<image::Rgba32FImage> {
 
-        // / E D G E  P A S S
+        let layout = crate::extra::create_depth_debug_bind_group_layout(&self.device);
+        let pipeline = Pipeline::depth_debug_pipeline(&self.device, &layout)?.pipeline;
 
-        let mut edge_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view: &self.edge_texture.as_ref().ok_or(wgpu::SurfaceError::Lost)?.view, 
-                resolve_target: None,
-                ops: wgpu::Operations { load: wgpu::LoadOp::Clear(wgpu::Color::BLACK), store: wgpu::StoreOp::Store },
-                depth_slice: None,
-            })],
-            depth_stencil_attachment:  None,
+        let depth_view = depth_texture.create_view(&wgpu::TextureViewDescriptor {
+            aspect: wgpu::TextureAspect::DepthOnly,
             ..Default::default()
         });
-        edge_pass.set_pipeline(&self.edge_pipeline);
-        //edge_pass.set_bind_group(0, ping_pong_texture_bind_group, &[]);
-        edge_pass.set_bind_group(0, &self.edge_bind_group, &[]); // Uses your textures
-        edge_pass.draw(0..3, 0..1);
-    
-        drop(edge_pass);
-
+        let bind_group = crate::extra::create_depth_debug_bind_group(&self.device, &layout, &depth_view);
+
+        let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("depth capture texture"),
+            size: depth_texture.size(),
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
 
-        // /  C O M P O S I T E   P A S S 
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("depth capture encoder"),
+        });
 
-        let mut composite_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-            label: Some("composite pass"),
+        let mut depth_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("depth capture pass"),
             color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view: &self.composite_texture.as_ref().ok_or(wgpu::SurfaceError::Lost)?.view, 
-                resolve_target: None,
+                view: &view,
                 depth_slice: None,
+                resolve_target: None,
                 ops: wgpu::Operations {
-                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    load: wgpu::LoadOp::Clear(wgpu::Color::WHITE),
                     store: wgpu::StoreOp::Store,
                 },
             })],
             depth_stencil_attachment: None,
-            timestamp_writes: None,
             occlusion_query_set: None,
+            timestamp_writes: None,
             multiview_mask: None,
         });
 
-        composite_pass.set_pipeline(&self.composite_pipeline);
-        composite_pass.set_bind_group(0, &self.composite_bind_group, &[]);
-        composite_pass.draw(0..3, 0..1);
+        depth_pass.set_pipeline(&pipeline);
+        depth_pass.set_bind_group(0, &bind_group, &[]);
+        depth_pass.draw(0..3, 0..1);
+
+        drop(depth_pass);
+
+        self.queue.submit(std::iter::once(encoder.finish()));
+
+        let packed = crate::readback::read_texture_rgba8(&self.device, &self.queue, &texture)?;
+
+        Ok(image::Rgba32FImage::from_fn(packed.width(), packed.height(), |x, y| {
+            let [r, g, b, _] = packed.get_pixel(x, y).0;
+            let depth = (u32::from_be_bytes([0, r, g, b]) as f64 / 16_777_215.0) as f32;
+            image::Rgba([depth, depth, depth, 1.0])
+        }))
+    }
 
-        drop(composite_pass);
+    // / Stencil can't be copied to a buffer everywhere (GL): draw it as white into a color target.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn read_stencil_mask(&self) -> anyhow::Result<image::RgbaImage> {
 
+        let pipeline = Pipeline::stencil_debug_pipeline(&self.device, self.sample_count)?.pipeline;
 
-        // / T O N E  M A P   P A S S 
+        let mask_desc = |label, sample_count, usage| wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width: self.config.width,
+                height: self.config.height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage,
+            view_formats: &[],
+        };
 
-        let mut tone_map_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-            label: Some("Tone Map Pass"),
+        let mask_texture = self.device.create_texture(&mask_desc(
+            "stencil mask texture",
+            1,
+            wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+        ));
+        let mask_view = mask_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let msaa_view = (self.sample_count > 1).then(|| {
+            self.device
+                .create_texture(&mask_desc(
+                    "stencil mask msaa texture",
+                    self.sample_count,
+                    wgpu::TextureUsages::RENDER_ATTACHMENT,
+                ))
+                .create_view(&wgpu::TextureViewDescriptor::default())
+        });
+
+        let mut encoder = self.device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("stencil mask encoder"),
+        });
+
+        let mut mask_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("stencil mask pass"),
             color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                view: &view, // ⬅ swapchain
-                resolve_target: None,
+                view: msaa_view.as_ref().unwrap_or(&mask_view),
                 depth_slice: None,
+                resolve_target: msaa_view.as_ref().map(|_| &mask_view),
                 ops: wgpu::Operations {
                     load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
                     store: wgpu::StoreOp::Store,
                 },
             })],
-            depth_stencil_attachment: None,
-            ..Default::default()
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: self.graph.targets().view(passes::DEPTH_STENCIL)?,
+                depth_ops: None,
+                stencil_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Load, // <- keep mask
+                    store: wgpu::StoreOp::Store,
+                }),
+            }),
+            occlusion_query_set: None,
+            timestamp_writes: None,
+            multiview_mask: None,
         });
 
-        tone_map_pass.set_pipeline(&self.tone_map_pipeline);
-        tone_map_pass.set_bind_group(0, &self.tone_map_bind_group, &[]);
-        tone_map_pass.draw(0..3, 0..1);
+        mask_pass.set_pipeline(&pipeline);
+        mask_pass.set_stencil_reference(0);
+        mask_pass.draw(0..3, 0..1);
 
-        drop(tone_map_pass);
+        drop(mask_pass);
 
-        // submit will accept anything that implements IntoIter
         self.queue.submit(std::iter::once(encoder.finish()));
-        output.present();
 
+        crate::readback::read_texture_rgba8(&self.device, &self.queue, &mask_texture)
+    }
+
+    // / Outline of the model, drawn around every instance without its own style.
+    pub fn set_glow_style(&mut self, style: GlowStyle) -> anyhow::Result<()> {
+        style.validate()?;
+        self.effects.set_glow_style(&style);
+        self.queue.write_buffer(
+            &self.glow_style_buffer,
+            0,
+            bytemuck::bytes_of(&GlowStyleUniform::new(&style)),
+        );
+        self.write_group_styles();
+        Ok(())
+    }
+
+    // / Outline of one instance, None for the one of its group.
+    pub fn set_instance_glow(&mut self, index: usize, glow: Option<GlowStyle>) -> anyhow::Result<()> {
+        if let Some(style) = &glow {
+            style.validate()?;
+        }
+        self.instance_mut(index)?.glow = glow;
+        self.write_instances();
+        Ok(())
+    }
+
+    // / Move an instance to a highlight group (stencil reference, NO_HIGHLIGHT for no outline).
+    pub fn set_instance_group(&mut self, index: usize, group: u8) -> anyhow::Result<()> {
+        if group as usize > self.highlight_groups.len() + 1 {
+            anyhow::bail!("no highlight group {group}, the last one is {}", self.highlight_groups.len() + 1);
+        }
+        self.instance_mut(index)?.group = group;
+        self.write_instances();
+        Ok(())
+    }
+
+    // / New highlight group, returns its stencil reference.
+    pub fn add_highlight_group(&mut self, name: &str, style: GlowStyle) -> anyhow::Result<u8> {
+        style.validate()?;
+        if self.highlight_group(name).is_some() {
+            anyhow::bail!("highlight group '{name}' already exists");
+        }
+        if self.highlight_groups.len() >= MAX_HIGHLIGHT_GROUPS {
+            anyhow::bail!("at most {MAX_HIGHLIGHT_GROUPS} highlight groups");
+        }
+        self.highlight_groups.push(HighlightGroupConfig {
+            name: name.to_string(),
+            glow: style,
+            occlusion: OcclusionStyle::default(),
+        });
+        self.write_group_styles();
+        highlight_group_reference(&self.highlight_groups, name)
+    }
+
+    // / Stencil reference of a highlight group, by name ("none", "default" or an added one).
+    pub fn highlight_group(&self, name: &str) -> Option<u8> {
+        highlight_group_reference(&self.highlight_groups, name).ok()
+    }
+
+    // / Outline of a highlight group, DEFAULT_HIGHLIGHT_GROUP being the model one.
+    pub fn set_highlight_group_style(&mut self, group: u8, style: GlowStyle) -> anyhow::Result<()> {
+        if group == DEFAULT_HIGHLIGHT_GROUP {
+            return self.set_glow_style(style);
+        }
+        style.validate()?;
+        self.highlight_group_mut(group)?.glow = style;
+        self.write_instances();
+        self.write_group_styles();
         Ok(())
     }
 
+    // / How a highlight group shows behind other geometry: always, visible or occluded
+    // / (x-ray), with the color / dashes of its hidden parts.
+    pub fn set_highlight_group_occlusion(&mut self, group: u8, occlusion: OcclusionStyle) -> anyhow::Result<()> {
+        occlusion.validate()?;
+        if group == DEFAULT_HIGHLIGHT_GROUP {
+            self.effects.set_occlusion_style(&occlusion);
+        } else {
+            self.highlight_group_mut(group)?.occlusion = occlusion;
+        }
+        self.write_group_styles();
+        Ok(())
+    }
+
+    // / Groups from stencil reference 2 (the default one lives in `effects`)
+    fn highlight_group_mut(&mut self, group: u8) -> anyhow::Result<&mut HighlightGroupConfig> {
+        (group as usize)
+            .checked_sub(DEFAULT_HIGHLIGHT_GROUP as usize + 1)
+            .and_then(|i| self.highlight_groups.get_mut(i))
+            .ok_or_else(|| anyhow::anyhow!("no highlight group {group}"))
+    }
+
+    fn instance_mut(&mut self, index: usize) -> anyhow::Result<&mut Instance> {
+        let count = self.instances.len();
+        self.instances
+            .get_mut(index)
+            .ok_or_else(|| anyhow::anyhow!("instance {index} out of {count}"))
+    }
+
+    // / Model / group outlines changed: upload the per-group tables again
+    fn write_group_styles(&self) {
+        let styles = GlowStyleUniform::group_glow_styles(
+            &self.effects.glow_style(),
+            self.highlight_groups.iter().map(|group| &group.glow),
+        );
+        self.queue.write_buffer(&self.group_styles_buffer, 0, bytemuck::cast_slice(&styles));
+
+        let occlusion = OcclusionUniform::group_occlusion_styles(
+            (&self.effects.glow_style(), &self.effects.occlusion_style()),
+            self.highlight_groups.iter().map(|group| (&group.glow, &group.occlusion)),
+        );
+        self.queue.write_buffer(&self.group_occlusion_buffer, 0, bytemuck::cast_slice(&occlusion));
+    }
+
+    // / Instance styles / groups changed: upload them again
+    fn write_instances(&mut self) {
+        let instance_data = instances_raw(&self.instances, &self.highlight_groups);
+        self.queue.write_buffer(&self.instance_buffer, 0, bytemuck::cast_slice(&instance_data));
+        self.highlight_runs = model::highlight_runs(&self.instances);
+    }
+
+    pub fn instance_count(&self) -> usize {
+        self.instances.len()
+    }
+
+    // / Pin the model rotation (deterministic frames). Pauses the spin.
+    pub fn set_spin_angle(&mut self, angle: f32) {
+        self.is_paused = true;
+        self.spin.set_angle(angle);
+        self.spin_uniform.update_from_angle(self.spin.angle());
+        self.queue.write_buffer(
+            &self.spin_buffer,
+            0,
+            bytemuck::bytes_of(&[self.spin_uniform]),
+        );
+    }
+
+    fn write_camera_buffer(&mut self) {
+
+        // Camera uniform normal mode (for non-reflected mode)
+
+        self.camera_uniform.update_view_proj(&self.camera);
+
+        // / W R I T E  C A M E R A  B U F F E R 
+
+        self.queue.write_buffer(
+            &self.camera_buffer,
+            0,
+            bytemuck::cast_slice(&[self.camera_uniform]),
+        );
+    }
+
+    // / Record every pass of the frame. The last one (tone map) writes into `view`,
+    // / the swapchain or the offscreen target.
+    fn encode_frame(
+        &mut self,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+    ) -> Result<(), wgpu::SurfaceError> {
+
+        // Used by this frame only: the next one adapts again after an `advance`
+        let delta_time = std::mem::take(&mut self.delta_time);
+
+        let scene = SceneData {
+            model: &self.lib_model,
+            instance_buffer: &self.instance_buffer,
+            instance_count: self.instances.len() as u32,
+            camera_bind_group: &self.camera_bind_group,
+            spin_bind_group: &self.spin_bind_group,
+            glow_bind_group: &self.glow_bind_group,
+            highlight_runs: &self.highlight_runs,
+            camera: &self.camera,
+            tone_map_bind_group: &self.tone_map_bind_group,
+            delta_time,
+            color_lut_bind_group: self.color_lut.map(|i| &self.color_luts[i].bind_group),
+        };
+
+        // Missing targets: report as lost so the surface (and the graph) gets resized
+        self.graph
+            .execute(encoder, &self.queue, &scene, view, self.profiler.as_mut())
+            .map_err(|e| {
+                log::error!("render graph: {e:#}");
+                wgpu::SurfaceError::Lost
+            })
+    }
+
     pub fn handle_key(&mut self, event_loop: &ActiveEventLoop, code: KeyCode, is_pressed: bool) {
         match (code, is_pressed) {
             (KeyCode::Space, is_pressed) =>  {
@@ -969,8 +1556,140 @@ impl State {
                 | KeyCode::ArrowRight,
                 is_pressed,
             ) => self.camera_controller.handle_key(code, is_pressed),
+            // MSAA on/off
+            (KeyCode::KeyM, true) => {
+                let sample_count = if self.sample_count == 1 { 4 } else { 1 };
+                match self.set_sample_count(sample_count) {
+                    Ok(sample_count) => log::info!("MSAA x{sample_count}"),
+                    Err(e) => log::error!("cannot switch MSAA: {e:#}"),
+                }
+            }
+            // HDR / SDR
+            (KeyCode::KeyH, true) => match self.set_hdr(!self.is_hdr) {
+                Ok(()) => log::info!("{}", if self.is_hdr { "HDR" } else { "SDR" }),
+                Err(e) => log::error!("cannot switch HDR: {e:#}"),
+            },
+            // Blur in render / compute passes
+            (KeyCode::KeyB, true) => {
+                let blur_path = match self.effects.blur_path {
+                    BlurPath::Render => BlurPath::Compute,
+                    BlurPath::Compute => BlurPath::Render,
+                };
+                match self.set_blur_path(blur_path) {
+                    Ok(blur_path) => log::info!("{} blur", blur_path.name()),
+                    Err(e) => log::error!("cannot switch the blur path: {e:#}"),
+                }
+            }
+            // Extruded / jump flood outlines
+            (KeyCode::KeyO, true) => {
+                let outline_mode = match self.effects.outline_mode {
+                    OutlineMode::Extrude => OutlineMode::JumpFlood,
+                    OutlineMode::JumpFlood => OutlineMode::Extrude,
+                };
+                match self.set_outline_mode(outline_mode) {
+                    Ok(outline_mode) => log::info!("{} outline", outline_mode.name()),
+                    Err(e) => log::error!("cannot switch the outline mode: {e:#}"),
+                }
+            }
+            // Edge detection cross -> Sobel -> Roberts
+            (KeyCode::KeyE, true) => {
+                let edge_operator = match self.effects.edge_operator {
+                    EdgeOperator::Cross => EdgeOperator::Sobel,
+                    EdgeOperator::Sobel => EdgeOperator::Roberts,
+                    EdgeOperator::Roberts => EdgeOperator::Cross,
+                };
+                match self.set_edge_operator(edge_operator) {
+                    Ok(()) => log::info!("{} edges", edge_operator.name()),
+                    Err(e) => log::error!("cannot switch the edge operator: {e:#}"),
+                }
+            }
+            // Next tone map operator, exposure -/+ half a stop
+            (KeyCode::KeyT, true) => {
+                let index = ToneMapOperator::ALL.iter().position(|t| *t == self.tone_map).unwrap_or(0);
+                let tone_map = ToneMapOperator::ALL[(index + 1) % ToneMapOperator::ALL.len()];
+                self.set_tone_map(tone_map);
+                log::info!("{} tone map", tone_map.name());
+            }
+            (KeyCode::Minus | KeyCode::Equal, true) => {
+                let step = if code == KeyCode::Minus { -0.5 } else { 0.5 };
+                match self.set_exposure(self.exposure + step) {
+                    Ok(()) => log::info!("exposure {:+} EV", self.exposure),
+                    Err(e) => log::error!("cannot set the exposure: {e:#}"),
+                }
+            }
+            // Auto exposure off -> histogram -> downsample
+            (KeyCode::KeyX, true) => {
+                let auto_exposure = match self.effects.auto_exposure {
+                    AutoExposure::Off => AutoExposure::Histogram,
+                    AutoExposure::Histogram => AutoExposure::Downsample,
+                    AutoExposure::Downsample => AutoExposure::Off,
+                };
+                match self.set_auto_exposure(auto_exposure) {
+                    Ok(auto_exposure) => log::info!("auto exposure {}", auto_exposure.name()),
+                    Err(e) => log::error!("cannot switch the auto exposure: {e:#}"),
+                }
+            }
+            // Color LUTs none -> first -> ... -> last, strength -/+ 0.1
+            (KeyCode::KeyG, true) => {
+                let color_lut = match self.color_lut {
+                    None if !self.color_luts.is_empty() => Some(0),
+                    Some(i) if i + 1 < self.color_luts.len() => Some(i + 1),
+                    _ => None,
+                };
+                match self.set_color_lut(color_lut) {
+                    Ok(()) => match color_lut {
+                        Some(i) => log::info!("color LUT {}", self.color_luts[i].name),
+                        None => log::info!("no color grading"),
+                    },
+                    Err(e) => log::error!("cannot switch the color LUT: {e:#}"),
+                }
+            }
+            (KeyCode::BracketLeft | KeyCode::BracketRight, true) => {
+                let step = if code == KeyCode::BracketLeft { -0.1 } else { 0.1 };
+                match self.set_lut_strength((self.lut_strength + step).clamp(0.0, 1.0)) {
+                    Ok(()) => log::info!("LUT strength {:.1}", self.lut_strength),
+                    Err(e) => log::error!("cannot set the LUT strength: {e:#}"),
+                }
+            }
+            (KeyCode::KeyV, true) => log::info!("present mode {:?}", self.cycle_present_mode()),
+            // Frame latency 1 -> 2 -> 3
+            (KeyCode::KeyL, true) => {
+                let frame_latency = self.config.desired_maximum_frame_latency % 3 + 1;
+                match self.set_frame_latency(frame_latency) {
+                    Ok(()) => log::info!("frame latency {frame_latency}"),
+                    Err(e) => log::error!("cannot set frame latency: {e:#}"),
+                }
+            }
+            // Per-pass timings in the log
+            (KeyCode::KeyP, true) => self.set_profiling(self.profiler.is_none()),
+            // Screenshot of the next frame / of every target of the next frame
+            #[cfg(not(target_arch = "wasm32"))]
+            (KeyCode::F12, true) => self.pending_capture = Some(CaptureRequest::Frame),
+            #[cfg(not(target_arch = "wasm32"))]
+            (KeyCode::F11, true) => self.pending_capture = Some(CaptureRequest::AllTargets),
             (KeyCode::Escape, true) => event_loop.exit(),
             _ => {}
         }
     }
+
+    pub fn handle_cursor_moved(&mut self, x: f64, y: f64) {
+        self.cursor_position = (x >= 0.0 && y >= 0.0).then_some((x as u32, y as u32));
+    }
+
+    // / Left click: log the instance and mesh under the cursor
+    pub fn handle_mouse_button(&mut self, button: MouseButton, is_pressed: bool) {
+        let (MouseButton::Left, true, Some((x, y))) = (button, is_pressed, self.cursor_position) else {
+            return;
+        };
+
+        #[cfg(not(target_arch = "wasm32"))]
+        match self.pick(x, y) {
+            Ok(Some(object)) => log::info!("picked instance {} mesh {} at ({x}, {y})", object.instance, object.mesh),
+            Ok(None) => log::info!("picked nothing at ({x}, {y})"),
+            Err(e) => log::error!("cannot pick at ({x}, {y}): {e:#}"),
+        }
+        // No blocking readback on the web
+        #[cfg(target_arch = "wasm32")]
+        log::info!("picking is not available on the web ({x}, {y})");
+    }
 }
\ No newline at end of file
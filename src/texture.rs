@@ -2,7 +2,7 @@ use anyhow::*;
 use image::GenericImageView;
 use wgpu::TextureUsages;
 
-use crate::{pipeline::compute_pipeline, utils::create_texture_from_rgba};
+use crate::{pipeline::compute_pipeline, resources::CubeLut, utils::{create_texture_from_rgba, f32_to_f16}};
 #[cfg(not(target_arch = "wasm32"))]
 use crate::utils::{create_texture_from_image, load_image};
 #[cfg(target_arch = "wasm32")]
@@ -324,6 +324,71 @@ impl Texture {
         )
     }
 
+    // / 3D texture of a .cube LUT (Rgba16Float, red along x), sampled trilinearly.
+    pub fn create_lut_texture(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        lut: &CubeLut,
+        label: &str,
+    ) -> Texture {
+        let size = wgpu::Extent3d {
+            width: lut.size,
+            height: lut.size,
+            depth_or_array_layers: lut.size,
+        };
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D3,
+            format: wgpu::TextureFormat::Rgba16Float,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+
+        // Rgba32Float is not filterable everywhere: halves
+        let texels = lut
+            .data
+            .iter()
+            .flat_map(|[r, g, b]| [*r, *g, *b, 1.0].map(f32_to_f16))
+            .collect::<Vec<u16>>();
+
+        queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            bytemuck::cast_slice(&texels),
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(8 * lut.size),
+                rows_per_image: Some(lut.size),
+            },
+            size,
+        );
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some(label),
+            address_mode_u: wgpu::AddressMode::ClampToEdge,
+            address_mode_v: wgpu::AddressMode::ClampToEdge,
+            address_mode_w: wgpu::AddressMode::ClampToEdge,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        Texture {
+            texture,
+            view,
+            sampler,
+        }
+    }
+
     fn load_texture_from_buffer_native(
         data: &[u8],
         device: &wgpu::Device,
@@ -514,7 +579,8 @@ impl ColorTexture {
         };
 
         let texture_format = if is_hdr {wgpu::TextureFormat::Rgba16Float} else {config.format };
-        let usage = if sample_count == 1 {wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING} else {wgpu::TextureUsages::RENDER_ATTACHMENT};
+        // COPY_SRC so intermediates can be read back (tests, captures)
+        let usage = if sample_count == 1 {wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_SRC} else {wgpu::TextureUsages::RENDER_ATTACHMENT};
         // This is your internal "canvas"
         let texture = device.create_texture(&wgpu::TextureDescriptor {
             label: Some(label),
@@ -537,6 +603,37 @@ impl ColorTexture {
 
         Self { texture, view }
     }
+
+    // / Final target when running headless. Stands in for the swapchain, in the surface format,
+    // / with COPY_SRC so the tone mapped frame can be read back.
+    pub fn create_output_texture(
+            device: &wgpu::Device,
+            config: &wgpu::SurfaceConfiguration,
+            label: &str,
+        )
+        -> Self {
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size: wgpu::Extent3d {
+                width: config.width.max(1),
+                height: config.height.max(1),
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: config.format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+                | wgpu::TextureUsages::TEXTURE_BINDING
+                | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        Self { texture, view }
+    }
 }
 
 
@@ -209,4 +209,25 @@ pub fn rgba_f32_to_u8(c: [f32; 4]) -> [u8; 4] {
     }
 
     [to_u8(c[0]), to_u8(c[1]), to_u8(c[2]), to_u8(c[3])]
-}
\ No newline at end of file
+}
+
+// / Single -> IEEE 754 half precision, rounded to nearest. Below the smallest normal half
+// / flushes to zero, above the largest gives infinity.
+pub fn f32_to_f16(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    if value.is_nan() {
+        return sign | 0x7e00;
+    }
+
+    // Half an ulp of the half mantissa, carries into the exponent
+    let rounded = (bits & 0x7fff_ffff) + 0x1000;
+    let exponent = (rounded >> 23) as i32 - 127 + 15;
+    let mantissa = ((rounded >> 13) & 0x3ff) as u16;
+
+    match exponent {
+        e if e >= 0x1f => sign | 0x7c00,
+        e if e <= 0 => sign,
+        e => sign | ((e as u16) << 10) | mantissa,
+    }
+}
@@ -3,8 +3,24 @@ use std::sync::Arc;
 
 use winit::{event_loop::ActiveEventLoop, keyboard::KeyCode, window::Window};
 
+use crate::config::{AdapterConfig, PowerPreference, WindowConfig};
+
+// / No adapter matches the backends, name filter and software only settings. Headless
+// / tests skip on this one (machines without GPU) and fail on any other error.
+#[derive(Debug)]
+pub struct AdapterNotFound(pub String);
+
+impl std::fmt::Display for AdapterNotFound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for AdapterNotFound {}
+
 pub struct Visualizer {
-   pub surface: wgpu::Surface<'static>,
+   pub adapter: wgpu::Adapter,
+   pub surface: Option<wgpu::Surface<'static>>,
    pub device: wgpu::Device,
    pub queue: wgpu::Queue,
    pub config: wgpu::SurfaceConfiguration,
@@ -13,17 +29,25 @@ pub struct Visualizer {
 }
 
 impl Visualizer {
-    pub async fn new(window:  Arc<Window>) -> anyhow::Result<Self> {
+    pub async fn new(
+        window:  Arc<Window>,
+        adapter_config: &AdapterConfig,
+        window_config: &WindowConfig,
+    ) -> anyhow::Result<Self> {
 
         let size = window.inner_size();
 
             // The instance is a handle to our GPU
             // BackendBit::PRIMARY => Vulkan + Metal + DX12 + Browser WebGPU
+            let default_backends = if cfg!(target_arch = "wasm32") {
+                wgpu::Backends::GL
+            } else {
+                wgpu::Backends::PRIMARY
+            };
+            let backends = adapter_config.backend.backends().unwrap_or(default_backends);
+
             let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
-                #[cfg(not(target_arch = "wasm32"))]
-                backends: wgpu::Backends::PRIMARY,
-                #[cfg(target_arch = "wasm32")]
-                backends: wgpu::Backends::GL,
+                backends,
                 ..Default::default()
             });
 
@@ -32,28 +56,14 @@ impl Visualizer {
 
             // Adapter
 
-            let adapter = if cfg!(target_arch = "wasm32") {
-                instance //  TODO select the most relevant adapter
-                    .request_adapter(&wgpu::RequestAdapterOptions {
-                        power_preference: wgpu::PowerPreference::default(),
-                        compatible_surface: Some(&surface),
-                        force_fallback_adapter: false,
-                    })
-                    .await?
-            } else {
-                instance // TODO select the most relevant adapter
-                    .enumerate_adapters(wgpu::Backends::all())
-                    .await
-                    .into_iter()
-                    .find(|adapter| adapter.is_surface_supported(&surface))
-                    .ok_or(anyhow::anyhow!("No adapter found"))?
-            };
+            let adapter = select_adapter(&instance, backends, adapter_config, Some(&surface)).await?;
 
             // Device & Queue
             let (device, queue) = adapter
                 .request_device(&wgpu::DeviceDescriptor {
                     label: None,
-                    required_features: wgpu::Features::empty(),
+                    // Timestamps for the profiler, when available (not on WebGL)
+                    required_features: adapter.features() & wgpu::Features::TIMESTAMP_QUERY,
                     experimental_features: wgpu::ExperimentalFeatures::disabled(),
                     // WebGL doesn't support all of wgpu's features, so if
                     // we're building for the web we'll have to disable some.
@@ -68,28 +78,167 @@ impl Visualizer {
                 .await?;
 
             let surface_caps = surface.get_capabilities(&adapter);
-            // Shader code in this tutorial assumes an sRGB surface texture. Using a different
-            // one will result in all the colors coming out darker. If you want to support non
-            // sRGB surfaces, you'll need to account for that when drawing to the frame.
-            let surface_format = surface_caps
-                .formats
-                .iter()
-                .find(|f| f.is_srgb())
-                .copied()
-                .unwrap_or(surface_caps.formats[0]);
+            let surface_format = window_config.surface_format.resolve(&surface_caps.formats);
+            let present_mode = window_config.present_mode.resolve(&surface_caps.present_modes);
+
+            log::info!(
+                "surface: {surface_format:?}, {present_mode:?}, frame latency {} (supported: {:?}, {:?})",
+                window_config.frame_latency, surface_caps.formats, surface_caps.present_modes
+            );
+            // COPY_SRC when available: screenshots copy the swapchain frame itself (F12)
+            let usage = wgpu::TextureUsages::RENDER_ATTACHMENT
+                | (surface_caps.usages & wgpu::TextureUsages::COPY_SRC);
+
+            // Opaque: the frame's alpha is not meant for the compositor
+            let alpha_mode = if surface_caps.alpha_modes.contains(&wgpu::CompositeAlphaMode::Opaque) {
+                wgpu::CompositeAlphaMode::Opaque
+            } else {
+                let mode = surface_caps.alpha_modes[0];
+                log::warn!("surface: no opaque alpha mode, using {mode:?} (supported: {:?})", surface_caps.alpha_modes);
+                mode
+            };
+
             let config = wgpu::SurfaceConfiguration {
-                usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                usage,
                 format: surface_format,
                 width: size.width,
                 height: size.height,
-                present_mode: surface_caps.present_modes[0],
-                alpha_mode: surface_caps.alpha_modes[0],
-                //alpha_mode: wgpu::CompositeAlphaMode::Opaque,
+                present_mode,
+                alpha_mode,
+                view_formats: vec![],
+                desired_maximum_frame_latency: window_config.frame_latency,
+            };
+
+            Ok(Self {adapter, surface: Some(surface), device, queue, config})
+
+    }
+
+    // / Headless visualizer: no window, no surface.
+    // / The `config` only carries the offscreen target size and format.
+    pub async fn new_headless(width: u32, height: u32, adapter_config: &AdapterConfig) -> anyhow::Result<Self> {
+
+            let backends = adapter_config.backend.backends().unwrap_or(wgpu::Backends::all());
+
+            let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor {
+                backends,
+                ..Default::default()
+            });
+
+            // Real GPUs first, software adapters last (CI machines without GPU)
+            let adapter = select_adapter(&instance, backends, adapter_config, None).await?;
+
+            let (device, queue) = adapter
+                .request_device(&wgpu::DeviceDescriptor {
+                    label: Some("headless device"),
+                    // Timestamps for the profiler, when available (not on WebGL)
+                    required_features: adapter.features() & wgpu::Features::TIMESTAMP_QUERY,
+                    experimental_features: wgpu::ExperimentalFeatures::disabled(),
+                    // Software adapters may not reach the default limits
+                    required_limits: adapter.limits(),
+                    memory_hints: Default::default(),
+                    trace: wgpu::Trace::Off,
+                })
+                .await?;
+
+            let config = wgpu::SurfaceConfiguration {
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+                format: wgpu::TextureFormat::Rgba8UnormSrgb,
+                width: width.max(1),
+                height: height.max(1),
+                present_mode: wgpu::PresentMode::Fifo,
+                alpha_mode: wgpu::CompositeAlphaMode::Opaque,
                 view_formats: vec![],
                 desired_maximum_frame_latency: 2,
             };
 
-            Ok(Self {surface, device, queue, config})
+            Ok(Self {adapter, surface: None, device, queue, config})
+    }
+}
+
+// /
+// /  A D A P T E R   S E L E C T I O N
+// /
+// / Candidates: adapters of `backends` that can present to the surface, matching the name filter
+// / (software only with `force_fallback`). Ranked by power preference, software adapters last.
+
+async fn select_adapter(
+    instance: &wgpu::Instance,
+    backends: wgpu::Backends,
+    adapter_config: &AdapterConfig,
+    surface: Option<&wgpu::Surface<'static>>,
+) -> anyhow::Result<wgpu::Adapter> {
+
+    let name_filter = adapter_config.name.as_ref().map(|name| name.to_lowercase());
+
+    let adapter = if cfg!(target_arch = "wasm32") {
+        // The browser exposes a single adapter per request
+        instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: adapter_config.power_preference.to_wgpu(),
+                compatible_surface: surface,
+                force_fallback_adapter: adapter_config.force_fallback,
+            })
+            .await?
+    } else {
+        let mut candidates = instance
+            .enumerate_adapters(backends)
+            .await
+            .into_iter()
+            .filter(|adapter| {
+                let info = adapter.get_info();
+                log::debug!("adapter candidate: {} ({:?}, {:?})", info.name, info.backend, info.device_type);
+
+                surface.is_none_or(|surface| adapter.is_surface_supported(surface))
+                    && (!adapter_config.force_fallback || info.device_type == wgpu::DeviceType::Cpu)
+                    && name_filter
+                        .as_ref()
+                        .is_none_or(|name| info.name.to_lowercase().contains(name))
+            })
+            .collect::<Vec<_>>();
+
+        // Stable: equally ranked adapters keep the enumeration order
+        candidates.sort_by_key(|adapter| device_type_rank(adapter_config.power_preference, adapter.get_info().device_type));
+
+        candidates.into_iter().next().ok_or_else(|| AdapterNotFound(format!(
+            "No adapter found for backends {backends:?}{}{}",
+            if adapter_config.force_fallback { ", software only" } else { "" },
+            adapter_config.name.as_ref().map(|name| format!(", name containing '{name}'")).unwrap_or_default(),
+        )))?
+    };
 
+    if let Some(name) = &name_filter
+        && !adapter.get_info().name.to_lowercase().contains(name)
+    {
+        log::warn!("adapter '{}' doesn't match the name filter '{name}'", adapter.get_info().name);
     }
-}
\ No newline at end of file
+
+    let info = adapter.get_info();
+    let limits = adapter.limits();
+    log::info!(
+        "adapter: {} ({:?}, {:?}, driver {} {})",
+        info.name, info.backend, info.device_type, info.driver, info.driver_info
+    );
+    log::info!(
+        "adapter limits: texture 2D {}, bind groups {}, color attachments {}, samplers per stage {}, uniform buffer {} B",
+        limits.max_texture_dimension_2d,
+        limits.max_bind_groups,
+        limits.max_color_attachments,
+        limits.max_samplers_per_shader_stage,
+        limits.max_uniform_buffer_binding_size,
+    );
+    log::debug!("adapter limits: {limits:#?}");
+
+    Ok(adapter)
+}
+
+fn device_type_rank(power_preference: PowerPreference, device_type: wgpu::DeviceType) -> u8 {
+    match (power_preference, device_type) {
+        (_, wgpu::DeviceType::Cpu) => 3,
+        (PowerPreference::HighPerformance, wgpu::DeviceType::DiscreteGpu) => 0,
+        (PowerPreference::HighPerformance, wgpu::DeviceType::IntegratedGpu) => 1,
+        (PowerPreference::LowPower, wgpu::DeviceType::IntegratedGpu) => 0,
+        (PowerPreference::LowPower, wgpu::DeviceType::DiscreteGpu) => 1,
+        (PowerPreference::None, _) => 0,
+        _ => 2,
+    }
+}
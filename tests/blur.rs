@@ -0,0 +1,56 @@
+// /
+// /  G A U S S I A N   B L U R   K E R N E L
+// /
+// / The merged taps of BlurParams::gaussian must keep the weight and the centroid of
+// / the discrete kernel.
+
+use glow::extra::{BlurParams, MAX_BLUR_RADIUS, MAX_BLUR_TAPS};
+
+fn discrete(radius: u32, sigma: f32) -> Vec<f32> {
+    let weights = (0..=radius)
+        .map(|i| (-((i * i) as f32) / (2.0 * sigma * sigma)).exp())
+        .collect::<Vec<_>>();
+    let total = weights[0] + 2.0 * weights[1..].iter().sum::<f32>();
+    weights.into_iter().map(|w| w / total).collect()
+}
+
+#[test]
+fn weights_are_normalized() {
+    for (radius, sigma) in [(1, 0.5), (4, 2.0), (9, 3.0), (MAX_BLUR_RADIUS, 10.0)] {
+        let params = BlurParams::gaussian(radius, sigma);
+        let taps = &params.taps[..params.tap_count as usize];
+
+        let total = taps[0][1] + 2.0 * taps[1..].iter().map(|t| t[1]).sum::<f32>();
+        assert!((total - 1.0).abs() < 1e-5, "radius {radius}: weights sum to {total}");
+    }
+}
+
+#[test]
+fn neighbour_texels_are_merged() {
+    // 8 texels per side: center + 4 pairs
+    let params = BlurParams::gaussian(8, 3.0);
+    assert_eq!(params.tap_count, 5);
+
+    let texels = discrete(8, 3.0);
+    for (k, tap) in params.taps[1..5].iter().enumerate() {
+        let (a, b) = (2 * k + 1, 2 * k + 2);
+        let weight = texels[a] + texels[b];
+        let offset = (a as f32 * texels[a] + b as f32 * texels[b]) / weight;
+
+        assert!((tap[1] - weight).abs() < 1e-6, "tap {k}: weight {} != {weight}", tap[1]);
+        assert!((tap[0] - offset).abs() < 1e-4, "tap {k}: offset {} != {offset}", tap[0]);
+    }
+
+    // Odd radius: the last texel has no neighbour, sampled at its center
+    let params = BlurParams::gaussian(3, 2.0);
+    assert_eq!(params.tap_count, 3);
+    assert_eq!(params.taps[2][0], 3.0);
+}
+
+#[test]
+fn radius_is_clamped_to_the_uniform_array() {
+    let params = BlurParams::gaussian(MAX_BLUR_RADIUS + 10, 8.0);
+    assert_eq!(params.tap_count as usize, MAX_BLUR_TAPS);
+    assert_eq!(params.direction, [0.0, 0.0]);
+    assert_eq!(params.with_direction(0.0, 1.0).direction, [0.0, 1.0]);
+}
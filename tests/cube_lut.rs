@@ -0,0 +1,69 @@
+// /
+// /  C U B E   L U T   F I L E S
+// /
+// / CubeLut::parse must read Adobe .cube 3D LUTs (red fastest) and reject the files the
+// / color grade pass can't use.
+
+use glow::resources::{CubeLut, MAX_LUT_SIZE};
+
+const INVERT: &str = "# Inverts every channel
+TITLE \"Invert\"
+LUT_3D_SIZE 2
+
+1 1 1
+0 1 1
+1 0 1
+0 0 1
+1 1 0
+0 1 0
+1 0 0
+0 0 0
+";
+
+#[test]
+fn cube_files_are_parsed() {
+    let lut = CubeLut::parse(INVERT).expect("cannot parse the LUT");
+    assert_eq!(lut.title.as_deref(), Some("Invert"));
+    assert_eq!(lut.size, 2);
+    assert_eq!((lut.domain_min, lut.domain_max), ([0.0; 3], [1.0; 3]));
+    assert_eq!(lut.data.len(), 8);
+    // Red varies fastest, then green, then blue
+    assert_eq!(lut.data[1], [0.0, 1.0, 1.0]);
+    assert_eq!(lut.data[2], [1.0, 0.0, 1.0]);
+    assert_eq!(lut.data[4], [1.0, 1.0, 0.0]);
+
+    let text = INVERT.replace("LUT_3D_SIZE 2", "LUT_3D_SIZE 2\nDOMAIN_MIN -0.5 0 0\nDOMAIN_MAX 1.5 1 2");
+    let lut = CubeLut::parse(&text).expect("cannot parse the LUT with a domain");
+    assert_eq!((lut.domain_min, lut.domain_max), ([-0.5, 0.0, 0.0], [1.5, 1.0, 2.0]));
+
+    let text = INVERT.replace("LUT_3D_SIZE 2", "LUT_3D_SIZE 2\nLUT_3D_INPUT_RANGE -1 2");
+    let lut = CubeLut::parse(&text).expect("cannot parse the LUT with an input range");
+    assert_eq!((lut.domain_min, lut.domain_max), ([-1.0; 3], [2.0; 3]));
+}
+
+#[test]
+fn identity_keeps_every_color() {
+    let lut = CubeLut::identity(5);
+    assert_eq!(lut.data.len(), 125);
+    assert_eq!(lut.data[0], [0.0; 3]);
+    assert_eq!(lut.data[1 + 2 * 5 + 3 * 25], [0.25, 0.5, 0.75]);
+    assert_eq!(lut.data[124], [1.0; 3]);
+}
+
+#[test]
+fn unusable_files_are_rejected() {
+    for (text, error) in [
+        (INVERT.replace("LUT_3D_SIZE 2\n", ""), "missing LUT_3D_SIZE"),
+        (INVERT.replace("LUT_3D_SIZE 2", "LUT_3D_SIZE 3"), "needs 27 colors, got 8"),
+        (INVERT.replace("LUT_3D_SIZE 2", "LUT_1D_SIZE 2"), "1D LUTs are not supported"),
+        (INVERT.replace("LUT_3D_SIZE 2", &format!("LUT_3D_SIZE {}", MAX_LUT_SIZE + 1)), "LUT_3D_SIZE must be in"),
+        (INVERT.replace("0 0 0\n", "0 0\n"), "expected 3 values, got 2"),
+        (INVERT.replace("0 0 0\n", "0 0 x\n"), "'x' is not a number"),
+        (INVERT.replace("LUT_3D_SIZE 2", "LUT_3D_SIZE 2\nDOMAIN_MIN 1 0 0"), "must be below DOMAIN_MAX"),
+        (INVERT.replace("LUT_3D_SIZE 2", "LUT_3D_SIZE 2\nLUT_3D_INPUT_RANGE 1"), "LUT_3D_INPUT_RANGE expects 2 values, got 1"),
+        (INVERT.replace("LUT_3D_SIZE 2", "LUT_3D_SIZE 2\nLUT_3D_INPUT_RANGE 1 0"), "must be below DOMAIN_MAX"),
+    ] {
+        let e = CubeLut::parse(&text).expect_err(error);
+        assert!(e.to_string().contains(error), "'{e}' instead of '{error}'");
+    }
+}
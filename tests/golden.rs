@@ -0,0 +1,819 @@
+// /
+// /  G O L D E N   I M A G E S
+// /
+// / Renders a fixed scene (fixed camera, fixed spin angle, single sampled) headless and
+// / compares every intermediate target with the reference PNGs in tests/golden/.
+// /
+// / GLOW_BLESS=1 cargo test --test golden   -> (re)writes the references.
+// / A missing reference fails the test, bless to write it.
+// / Without any adapter (no GPU, no software rasterizer) the tests are skipped, any other
+// / setup error fails them.
+// / On failure the actual frame and an amplified diff are written in the cargo tmp dir.
+
+#![cfg(not(target_arch = "wasm32"))]
+
+use std::path::{Path, PathBuf};
+
+use glow::config::{
+    AutoExposure, BloomMode, BlurPath, EdgeOperator, GlowConfig, GlowStyle, HighlightGroupConfig, InstanceConfig, OcclusionStyle, OutlineMode,
+    ToneMapOperator, Visibility,
+};
+use glow::model::ObjectId;
+use glow::readback::Capture;
+use glow::resources::CubeLut;
+use glow::state::{FrameTarget, State};
+use glow::visualizer::AdapterNotFound;
+
+const WIDTH: u32 = 256;
+const HEIGHT: u32 = 192;
+const SPIN_ANGLE: f32 = 0.6;
+
+// Max difference allowed on any channel of a pixel
+const CHANNEL_TOLERANCE: u8 = 6;
+// Fraction of pixels allowed to exceed the tolerance (rasterization differences between adapters)
+const MAX_MISMATCH_RATIO: f64 = 0.002;
+
+fn golden_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden")
+}
+
+fn output_dir() -> PathBuf {
+    Path::new(env!("CARGO_TARGET_TMPDIR")).join("golden")
+}
+
+struct Comparison {
+    mismatched: usize,
+    max_delta: u8,
+    diff: image::RgbaImage,
+}
+
+fn compare(actual: &image::RgbaImage, expected: &image::RgbaImage) -> Comparison {
+    let mut mismatched = 0;
+    let mut max_delta = 0;
+    let mut diff = image::RgbaImage::new(actual.width(), actual.height());
+
+    for (x, y, a) in actual.enumerate_pixels() {
+        let e = expected.get_pixel(x, y);
+        let delta = (0..4).map(|c| a[c].abs_diff(e[c])).max().unwrap_or(0);
+
+        max_delta = max_delta.max(delta);
+        if delta > CHANNEL_TOLERANCE {
+            mismatched += 1;
+            diff.put_pixel(x, y, image::Rgba([255, 0, 255, 255]));
+        } else {
+            // Faint amplified difference, so near misses are visible too
+            let v = delta.saturating_mul(16);
+            diff.put_pixel(x, y, image::Rgba([v, v, v, 255]));
+        }
+    }
+
+    Comparison { mismatched, max_delta, diff }
+}
+
+fn check_target(name: &str, actual: &image::RgbaImage, bless: bool) -> Result<(), String> {
+    let reference = golden_dir().join(format!("{name}.png"));
+
+    if !bless && !reference.exists() {
+        return Err(format!("{name}: no reference {}, run with GLOW_BLESS=1 to write it", reference.display()));
+    }
+
+    if bless {
+        std::fs::create_dir_all(golden_dir()).map_err(|e| e.to_string())?;
+        actual.save(&reference).map_err(|e| e.to_string())?;
+        eprintln!("golden: wrote reference {}", reference.display());
+        return Ok(());
+    }
+
+    let expected = image::open(&reference).map_err(|e| e.to_string())?.into_rgba8();
+
+    if expected.dimensions() != actual.dimensions() {
+        return Err(format!(
+            "{name}: size {:?} does not match reference {:?}",
+            actual.dimensions(),
+            expected.dimensions()
+        ));
+    }
+
+    let comparison = compare(actual, &expected);
+    let ratio = comparison.mismatched as f64 / (WIDTH * HEIGHT) as f64;
+
+    if ratio > MAX_MISMATCH_RATIO {
+        std::fs::create_dir_all(output_dir()).map_err(|e| e.to_string())?;
+        let actual_path = output_dir().join(format!("{name}.actual.png"));
+        let diff_path = output_dir().join(format!("{name}.diff.png"));
+        actual.save(&actual_path).map_err(|e| e.to_string())?;
+        comparison.diff.save(&diff_path).map_err(|e| e.to_string())?;
+
+        return Err(format!(
+            "{name}: {} pixels ({:.3}%) differ by more than {CHANNEL_TOLERANCE} (max {}), see {}",
+            comparison.mismatched,
+            ratio * 100.0,
+            comparison.max_delta,
+            diff_path.display()
+        ));
+    }
+
+    Ok(())
+}
+
+// Default scene, single sampled, separable blur (every FrameTarget is rendered).
+// GLOW_FORCE_FALLBACK_ADAPTER=1 etc. pick the adapter (CI).
+fn headless_state() -> Option<State> {
+    headless_state_with(golden_config())
+}
+
+fn golden_config() -> GlowConfig {
+    let mut config = GlowConfig::default();
+    config.effects.bloom = BloomMode::Separable;
+    config
+}
+
+fn headless_state_with(mut config: GlowConfig) -> Option<State> {
+    config.renderer.sample_count = 1;
+    config.adapter.apply_env().expect("invalid GLOW_* adapter env var");
+
+    match pollster::block_on(State::new_headless(WIDTH, HEIGHT, config)) {
+        Ok(state) => Some(state),
+        Err(e) if e.is::<AdapterNotFound>() => {
+            eprintln!("golden: skipped, no adapter available ({e})");
+            None
+        }
+        Err(e) => panic!("cannot create the headless state: {e:#}"),
+    }
+}
+
+// Scene of `config` at the golden spin angle
+fn spun_state_with(config: GlowConfig) -> Option<State> {
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    let mut state = headless_state_with(config)?;
+    state.set_spin_angle(SPIN_ANGLE);
+    Some(state)
+}
+
+fn render_target(state: &mut State, target: FrameTarget) -> image::RgbaImage {
+    state.render_to_image().expect("headless render failed");
+    state.read_target(target).unwrap_or_else(|e| panic!("cannot read {}: {e}", target.name()))
+}
+
+fn count_pixels(image: &image::RgbaImage, test: fn(&image::Rgba<u8>) -> bool) -> usize {
+    image.pixels().filter(|p| test(p)).count()
+}
+
+// Render a frame and count the pixels of `target` passing each test
+fn render_and_count<const N: usize>(
+    state: &mut State,
+    target: FrameTarget,
+    tests: [fn(&image::Rgba<u8>) -> bool; N],
+) -> [usize; N] {
+    let image = render_target(state, target);
+    tests.map(|test| count_pixels(&image, test))
+}
+
+// Outline colors (premultiplied by the blend): red dominant or blue dominant (teal)
+fn is_orange(p: &image::Rgba<u8>) -> bool {
+    p[0] > 30 && p[2] == 0
+}
+
+fn is_blue(p: &image::Rgba<u8>) -> bool {
+    p[2] > 30 && p[0] == 0
+}
+
+// Pixels clearly apart between two frames of the same size
+fn differing_pixels(a: &image::RgbaImage, b: &image::RgbaImage) -> usize {
+    a.pixels().zip(b.pixels()).filter(|(a, b)| (0..3).any(|c| a[c].abs_diff(b[c]) > 40)).count()
+}
+
+// Mean color channel of a frame
+fn mean_brightness(frame: &image::RgbaImage) -> f64 {
+    frame.pixels().map(|p| p[0] as f64 + p[1] as f64 + p[2] as f64).sum::<f64>() / (3 * frame.len() / 4) as f64
+}
+
+#[test]
+fn glow_passes_match_golden_images() {
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    let Some(mut state) = headless_state() else { return };
+
+    state.set_spin_angle(SPIN_ANGLE);
+    state.render_to_image().expect("headless render failed");
+
+    let bless = std::env::var_os("GLOW_BLESS").is_some();
+
+    let failures = FrameTarget::ALL
+        .iter()
+        .filter_map(|target| {
+            let actual = state
+                .read_target(*target)
+                .unwrap_or_else(|e| panic!("cannot read {}: {e}", target.name()));
+            check_target(target.name(), &actual, bless).err()
+        })
+        .collect::<Vec<_>>();
+
+    assert!(failures.is_empty(), "golden image mismatch:\n{}", failures.join("\n"));
+}
+
+// Switching MSAA on and back off must rebuild the graph to the exact same single sampled frame
+#[test]
+fn sample_count_switch_round_trips() {
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    let Some(mut state) = headless_state() else { return };
+
+    state.set_spin_angle(SPIN_ANGLE);
+
+    let sample_count = state.set_sample_count(4).expect("cannot switch to MSAA x4");
+    assert_eq!(state.sample_count(), sample_count);
+    state.render_to_image().expect("multisampled render failed");
+
+    assert_eq!(state.set_sample_count(1).expect("cannot switch back to 1 sample"), 1);
+    let actual = state.render_to_image().expect("headless render failed");
+
+    if let Err(failure) = check_target(FrameTarget::ToneMap.name(), &actual, false) {
+        panic!("golden image mismatch after MSAA round trip:\n{failure}");
+    }
+
+    assert!(state.set_sample_count(3).is_err());
+}
+
+// SDR renders through the pass-through tone map, and switching back gives the HDR frame again
+#[test]
+fn hdr_switch_round_trips() {
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    let Some(mut state) = headless_state() else { return };
+
+    state.set_spin_angle(SPIN_ANGLE);
+
+    state.set_hdr(false).expect("cannot switch to SDR");
+    assert!(!state.is_hdr());
+    let sdr = state.render_to_image().expect("SDR render failed");
+    assert!(sdr.pixels().any(|p| p.0 != [0, 0, 0, 255]), "SDR frame is black");
+
+    state.set_hdr(true).expect("cannot switch back to HDR");
+    let actual = state.render_to_image().expect("headless render failed");
+
+    if let Err(failure) = check_target(FrameTarget::ToneMap.name(), &actual, false) {
+        panic!("golden image mismatch after HDR round trip:\n{failure}");
+    }
+}
+
+// The mip chain replaces the two blur passes: only the bloom target and the final frame change
+#[test]
+fn mip_chain_bloom_matches_golden_images() {
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    let mut config = GlowConfig::default();
+    config.effects.bloom = BloomMode::MipChain;
+    let Some(mut state) = headless_state_with(config) else { return };
+
+    state.set_spin_angle(SPIN_ANGLE);
+    let frame = state.render_to_image().expect("headless render failed");
+
+    assert!(state.read_target(FrameTarget::BlurHorizontal).is_err(), "no horizontal blur with the mip chain");
+    let bloom = state.read_target(FrameTarget::BlurVertical).expect("cannot read the bloom target");
+
+    let bless = std::env::var_os("GLOW_BLESS").is_some();
+    let failures = [("mip_chain_bloom", &bloom), ("mip_chain_tone_map", &frame)]
+        .into_iter()
+        .filter_map(|(name, actual)| check_target(name, actual, bless).err())
+        .collect::<Vec<_>>();
+
+    assert!(failures.is_empty(), "golden image mismatch:\n{}", failures.join("\n"));
+}
+
+// Each blur pass has its own uniform slot: the horizontal pass must not spread the outline
+// to rows where it has nothing (it did when both passes shared one params buffer)
+#[test]
+fn horizontal_blur_stays_in_its_rows() {
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    let Some(mut state) = headless_state() else { return };
+
+    state.set_spin_angle(SPIN_ANGLE);
+    state.render_to_image().expect("headless render failed");
+
+    let outline = state.read_target(FrameTarget::Outline).expect("cannot read the outline");
+    let blurred = state.read_target(FrameTarget::BlurHorizontal).expect("cannot read the horizontal blur");
+
+    let row_is_empty = |image: &image::RgbaImage, y: u32| (0..image.width()).all(|x| image.get_pixel(x, y)[3] == 0);
+    let empty_rows = (0..HEIGHT).filter(|&y| row_is_empty(&outline, y)).collect::<Vec<_>>();
+    assert!(!empty_rows.is_empty() && empty_rows.len() < HEIGHT as usize, "the outline should cover some rows only");
+
+    let leaked = empty_rows.iter().filter(|&&y| !row_is_empty(&blurred, y)).count();
+    assert_eq!(leaked, 0, "the horizontal blur wrote {leaked} rows without outline");
+}
+
+// The compute blur runs the same kernel: same targets as the render passes, within tolerance
+#[test]
+fn compute_blur_matches_golden_images() {
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    let mut config = GlowConfig::default();
+    config.effects.bloom = BloomMode::Separable;
+    config.effects.blur_path = BlurPath::Compute;
+    let Some(mut state) = headless_state_with(config) else { return };
+
+    if state.blur_path() != BlurPath::Compute {
+        eprintln!("golden: compute blur skipped, not supported by this adapter");
+        return;
+    }
+
+    state.set_spin_angle(SPIN_ANGLE);
+    state.render_to_image().expect("headless render failed");
+
+    let failures = [FrameTarget::BlurHorizontal, FrameTarget::BlurVertical, FrameTarget::ToneMap]
+        .into_iter()
+        .filter_map(|target| {
+            let actual = state
+                .read_target(target)
+                .unwrap_or_else(|e| panic!("cannot read {}: {e}", target.name()));
+            check_target(target.name(), &actual, false).err()
+        })
+        .collect::<Vec<_>>();
+
+    assert!(failures.is_empty(), "compute blur differs from the render passes:\n{}", failures.join("\n"));
+
+    assert_eq!(state.set_blur_path(BlurPath::Render).expect("cannot switch to render passes"), BlurPath::Render);
+    let actual = state.render_to_image().expect("headless render failed");
+    if let Err(failure) = check_target(FrameTarget::ToneMap.name(), &actual, false) {
+        panic!("golden image mismatch after switching back to render passes:\n{failure}");
+    }
+}
+
+// Two copies of the model, the second one with its own outline: both colors in one frame
+#[test]
+fn instances_have_their_own_glow_style() {
+    let orange = GlowStyle { color: [1.0, 0.3, 0.0], alpha: 1.0, ..GlowStyle::default() };
+
+    let mut config = GlowConfig::default();
+    config.scene.instances = vec![
+        InstanceConfig { position: [-1.5, 0.0, 0.0], ..InstanceConfig::default() },
+        InstanceConfig { position: [1.5, 0.0, 0.0], glow: Some(orange), ..InstanceConfig::default() },
+    ];
+    let Some(mut state) = spun_state_with(config) else { return };
+    assert_eq!(state.instance_count(), 2);
+
+    let [orange_pixels, teal_pixels] = render_and_count(&mut state, FrameTarget::Outline, [is_orange, is_blue]);
+    assert!(orange_pixels > 100, "no orange outline ({orange_pixels} pixels)");
+    assert!(teal_pixels > 100, "no teal outline ({teal_pixels} pixels)");
+
+    // Back to the model outline, then a new model outline for both
+    state.set_instance_glow(1, None).expect("cannot reset the instance glow");
+    let [orange_pixels] = render_and_count(&mut state, FrameTarget::Outline, [is_orange]);
+    assert_eq!(orange_pixels, 0, "the instance kept its outline");
+
+    state.set_glow_style(orange).expect("cannot set the model glow");
+    let [teal_pixels] = render_and_count(&mut state, FrameTarget::Outline, [is_blue]);
+    assert_eq!(teal_pixels, 0, "the model outline did not change");
+
+    assert!(state.set_instance_glow(2, None).is_err());
+    assert!(state.set_glow_style(GlowStyle { alpha: 2.0, ..orange }).is_err());
+}
+
+#[test]
+fn highlight_groups_have_their_own_outline() {
+    let orange = GlowStyle { color: [1.0, 0.3, 0.0], alpha: 1.0, ..GlowStyle::default() };
+
+    let mut config = GlowConfig {
+        highlight_groups: vec![HighlightGroupConfig { name: "selected".into(), glow: orange, ..Default::default() }],
+        ..GlowConfig::default()
+    };
+    // Far enough to see the three instances side by side
+    config.camera.eye = [0.0, 1.0, 9.0];
+    config.scene.instances = vec![
+        InstanceConfig { position: [-3.0, 0.0, 0.0], ..InstanceConfig::default() },
+        InstanceConfig { position: [0.0, 0.0, 0.0], group: Some("selected".into()), ..InstanceConfig::default() },
+        InstanceConfig { position: [3.0, 0.0, 0.0], group: Some("none".into()), ..InstanceConfig::default() },
+    ];
+    let Some(mut state) = spun_state_with(config) else { return };
+    assert_eq!(state.highlight_group("selected"), Some(2));
+    assert_eq!(state.highlight_group("hovered"), None);
+
+    // Orange and teal outline pixels per third of the image: left (default), middle (selected), right (none)
+    let count_colors = |state: &mut State| {
+        let outline = render_target(state, FrameTarget::Outline);
+        let third = outline.width() / 3;
+        [0, 1, 2].map(|column| {
+            let part = image::imageops::crop_imm(&outline, column * third, 0, third, outline.height()).to_image();
+            [count_pixels(&part, is_orange), count_pixels(&part, is_blue)]
+        })
+    };
+
+    let [left, middle, right] = count_colors(&mut state);
+    assert!(left[1] > 30 && left[0] == 0, "default group outline is not teal: {left:?}");
+    assert!(middle[0] > 30 && middle[1] == 0, "selected group outline is not orange: {middle:?}");
+    assert_eq!(right, [0, 0], "an unhighlighted instance has an outline");
+
+    // Runtime groups: a new one for the right instance, the default one restyled
+    let hovered = state
+        .add_highlight_group("hovered", GlowStyle { color: [0.0, 0.0, 1.0], alpha: 1.0, ..GlowStyle::default() })
+        .expect("cannot add a highlight group");
+    assert_eq!(hovered, 3);
+    state.set_instance_group(2, hovered).expect("cannot move the instance");
+    state.set_highlight_group_style(2, GlowStyle::default()).expect("cannot restyle the group");
+
+    let [_, middle, right] = count_colors(&mut state);
+    assert!(middle[1] > 30 && middle[0] == 0, "selected group outline did not change: {middle:?}");
+    assert!(right[1] > 30, "hovered group has no outline: {right:?}");
+
+    assert!(state.add_highlight_group("hovered", orange).is_err());
+    assert!(state.set_instance_group(0, 4).is_err());
+    assert!(state.set_highlight_group_style(4, orange).is_err());
+}
+
+#[test]
+fn jump_flood_outline_has_exact_pixel_width() {
+    const WIDTH_PX: f32 = 4.0;
+
+    let mut config = GlowConfig::default();
+    config.effects.outline_mode = OutlineMode::JumpFlood;
+    config.effects.outline_width = WIDTH_PX;
+    config.effects.outline_falloff = 0.0;
+    let Some(mut state) = spun_state_with(config) else { return };
+    if state.outline_mode() != OutlineMode::JumpFlood {
+        eprintln!("golden: jump flood skipped, not supported by the adapter");
+        return;
+    }
+
+    let outline = render_target(&mut state, FrameTarget::Outline);
+    let mask = state.read_target(FrameTarget::StencilMask).expect("cannot read the stencil mask");
+
+    // Distance from each pixel to the nearest mask pixel, looked up a few pixels around
+    let reach = WIDTH_PX as i32 + 2;
+    let distance_to_mask = |x: i32, y: i32| {
+        let mut nearest = f32::INFINITY;
+        for dy in -reach..=reach {
+            for dx in -reach..=reach {
+                let (mx, my) = (x + dx, y + dy);
+                if mx >= 0
+                    && my >= 0
+                    && (mx as u32) < mask.width()
+                    && (my as u32) < mask.height()
+                    && mask.get_pixel(mx as u32, my as u32)[0] > 127
+                {
+                    nearest = nearest.min(((dx * dx + dy * dy) as f32).sqrt());
+                }
+            }
+        }
+        nearest
+    };
+
+    // Outline alpha 0.7: solid up to the width, nothing past it (half a pixel of antialiasing)
+    let solid_alpha = (0.7 * 255.0_f32).round() as u8;
+    let mut outline_pixels = 0;
+    for (x, y, pixel) in outline.enumerate_pixels() {
+        let distance = distance_to_mask(x as i32, y as i32);
+        let alpha = pixel[3];
+        if distance == 0.0 {
+            assert_eq!(alpha, 0, "outline inside the mask at ({x}, {y})");
+        } else if distance <= WIDTH_PX - 0.5 {
+            assert!(alpha.abs_diff(solid_alpha) <= 1, "outline not solid at ({x}, {y}): distance {distance}, alpha {alpha}");
+        } else if distance >= WIDTH_PX + 0.5 {
+            assert_eq!(alpha, 0, "outline too wide at ({x}, {y}): distance {distance}");
+        }
+        if alpha > 0 {
+            outline_pixels += 1;
+        }
+    }
+    assert!(outline_pixels > 100, "no outline ({outline_pixels} pixels)");
+
+    // Multisampled: seeded from a single sampled copy of the mask
+    state.set_sample_count(4).expect("cannot switch to MSAA");
+    let [msaa_pixels] = render_and_count(&mut state, FrameTarget::Outline, [|p| p[3] > 0]);
+    assert!(
+        msaa_pixels.abs_diff(outline_pixels) * 10 < outline_pixels,
+        "MSAA outline differs: {msaa_pixels} pixels instead of {outline_pixels}"
+    );
+}
+
+#[test]
+fn occluded_outline_shows_through_walls() {
+    // Thick enough to count its hidden part
+    let orange = GlowStyle { color: [1.0, 0.3, 0.0], alpha: 1.0, thickness: 0.2, ..GlowStyle::default() };
+
+    let mut config = GlowConfig {
+        highlight_groups: vec![HighlightGroupConfig { name: "selected".into(), glow: orange, ..Default::default() }],
+        ..GlowConfig::default()
+    };
+    // An unhighlighted instance in front of half of the selected one
+    config.camera.eye = [0.0, 1.0, 9.0];
+    config.scene.instances = vec![
+        InstanceConfig { position: [0.0, 0.0, 0.0], group: Some("selected".into()), ..InstanceConfig::default() },
+        InstanceConfig { position: [0.6, 0.0, 3.0], group: Some("none".into()), ..InstanceConfig::default() },
+    ];
+    let Some(mut state) = spun_state_with(config) else { return };
+    let selected = state.highlight_group("selected").expect("no selected group");
+
+    // Orange (glow color) and blue (occluded color) outline pixels
+    let count_colors = |state: &mut State, occlusion: OcclusionStyle| {
+        state.set_highlight_group_occlusion(selected, occlusion).expect("cannot set the occlusion");
+        render_and_count(state, FrameTarget::Outline, [is_orange, is_blue])
+    };
+
+    let always = count_colors(&mut state, OcclusionStyle::default());
+    let visible = count_colors(&mut state, OcclusionStyle { visibility: Visibility::Visible, ..Default::default() });
+    let occluded = count_colors(&mut state, OcclusionStyle { visibility: Visibility::Occluded, ..Default::default() });
+    assert!(visible[0] > 30 && occluded[0] > 30, "no visible or hidden outline: {visible:?} {occluded:?}");
+    assert!(
+        (visible[0] + occluded[0]).abs_diff(always[0]) * 10 < always[0],
+        "visible {visible:?} and hidden {occluded:?} parts do not add up to the whole outline {always:?}"
+    );
+
+    // X-ray: the hidden parts recolored, then dashed
+    let blue = OcclusionStyle { color: Some([0.0, 0.0, 1.0]), ..Default::default() };
+    let recolored = count_colors(&mut state, blue);
+    assert!(recolored[0].abs_diff(visible[0]) * 10 < visible[0], "visible parts changed: {recolored:?}");
+    assert!(recolored[1].abs_diff(occluded[0]) * 10 < occluded[0], "hidden parts not recolored: {recolored:?}");
+
+    let dashed = count_colors(&mut state, OcclusionStyle { dash: 4.0, ..blue });
+    assert!(
+        dashed[1] * 10 > recolored[1] * 3 && dashed[1] * 10 < recolored[1] * 7,
+        "hidden parts not dashed: {dashed:?} instead of {recolored:?}"
+    );
+
+    assert!(state.set_highlight_group_occlusion(selected, OcclusionStyle { dash: -1.0, ..blue }).is_err());
+    assert!(state.set_highlight_group_occlusion(selected + 1, blue).is_err());
+}
+
+// The scene draws every instance whatever its group: an unhighlighted occluder hides the
+// highlighted object behind it (it left a hole when the scene was gated on the group stencil)
+#[test]
+fn unhighlighted_instances_occlude_highlighted_ones() {
+    let render_scene = |front_group: &str| {
+        let mut config = GlowConfig {
+            highlight_groups: vec![HighlightGroupConfig { name: "selected".into(), ..Default::default() }],
+            ..golden_config()
+        };
+        config.camera.eye = [0.0, 1.0, 9.0];
+        config.scene.instances = vec![
+            InstanceConfig { position: [0.0, 0.0, 0.0], group: Some("selected".into()), ..InstanceConfig::default() },
+            InstanceConfig { position: [0.6, 0.0, 3.0], group: Some(front_group.into()), ..InstanceConfig::default() },
+        ];
+        Some(render_target(&mut spun_state_with(config)?, FrameTarget::Scene))
+    };
+
+    let Some(same_group) = render_scene("selected") else { return };
+    for front_group in ["none", "default"] {
+        let Some(scene) = render_scene(front_group) else { return };
+        let different = differing_pixels(&scene, &same_group);
+        assert_eq!(different, 0, "a '{front_group}' occluder changed the scene: {different} pixels");
+    }
+}
+
+#[test]
+fn edges_follow_the_camera_clip_planes() {
+    // Edge pixels: the edge target away from the scene below it
+    let count_edges = |state: &mut State| {
+        let edges = render_target(state, FrameTarget::Edge);
+        differing_pixels(&state.read_target(FrameTarget::Scene).expect("cannot read the scene"), &edges)
+    };
+
+    let Some(mut state) = spun_state_with(golden_config()) else { return };
+    let default_edges = count_edges(&mut state);
+    assert!(default_edges > 100, "no edges ({default_edges} pixels)");
+
+    // The model sits between both configurations of clip planes: same linear depth
+    let mut config = golden_config();
+    config.camera.znear = 1.0;
+    config.camera.zfar = 10.0;
+    let Some(mut state) = spun_state_with(config) else { return };
+    let clipped_edges = count_edges(&mut state);
+    assert!(
+        clipped_edges.abs_diff(default_edges) * 10 < default_edges,
+        "edges depend on the clip planes: {clipped_edges} pixels instead of {default_edges}"
+    );
+
+    for edge_operator in [EdgeOperator::Sobel, EdgeOperator::Roberts, EdgeOperator::Cross] {
+        state.set_edge_operator(edge_operator).expect("cannot switch the edge operator");
+        assert_eq!(state.edge_operator(), edge_operator);
+        let edges = count_edges(&mut state);
+        assert!(
+            edges.abs_diff(default_edges) * 2 < default_edges,
+            "{} edges far from the cross ones: {edges} pixels instead of {default_edges}",
+            edge_operator.name()
+        );
+    }
+}
+
+#[test]
+fn object_ids_pick_instances() {
+    // Raw object IDs of the last frame
+    let read_ids = |state: &mut State| {
+        state.render_to_image().expect("headless render failed");
+        match state.capture(FrameTarget::ObjectId).expect("cannot read the object ids") {
+            Capture::ObjectId(ids) => ids,
+            _ => panic!("the object id target is not R32Uint"),
+        }
+    };
+
+    // A second instance in front of half of the first one
+    let mut config = GlowConfig::default();
+    config.camera.eye = [0.0, 1.0, 9.0];
+    config.scene.instances = vec![
+        InstanceConfig { position: [0.0, 0.0, 0.0], ..InstanceConfig::default() },
+        InstanceConfig { position: [0.6, 0.0, 3.0], ..InstanceConfig::default() },
+    ];
+    let Some(mut state) = spun_state_with(config) else { return };
+
+    let ids = read_ids(&mut state);
+    let instance_pixels = |instance| {
+        ids.pixels().filter(|p| ObjectId::from_raw(p[0]).is_some_and(|id| id.instance == instance)).count()
+    };
+    let (back, front) = (instance_pixels(0), instance_pixels(1));
+    assert!(back > 100 && front > 100, "instances missing from the object ids: {back} and {front} pixels");
+    assert_eq!(
+        back + front,
+        ids.pixels().filter(|p| p[0] != 0).count(),
+        "object ids of unknown instances"
+    );
+
+    // Picking reads the same texel
+    let (x, y, raw) = ids.enumerate_pixels().find(|(_, _, p)| p[0] != 0).map(|(x, y, p)| (x, y, p[0])).unwrap();
+    assert_eq!(state.pick(x, y).expect("cannot pick"), ObjectId::from_raw(raw));
+    let (x, y) = ids.enumerate_pixels().find(|(_, _, p)| p[0] == 0).map(|(x, y, _)| (x, y)).unwrap();
+    assert_eq!(state.pick(x, y).expect("cannot pick"), None, "picked the background");
+    for (x, y) in [(ids.width(), 0), (u32::MAX, 0), (0, u32::MAX)] {
+        assert!(state.pick(x, y).is_err(), "picked ({x}, {y}) outside the frame");
+    }
+
+    // Multisampled: the ids come from their own single sampled pass
+    state.set_sample_count(4).expect("cannot switch to 4x MSAA");
+    let msaa_ids = read_ids(&mut state);
+    let different = ids.pixels().zip(msaa_ids.pixels()).filter(|(a, b)| a != b).count();
+    assert!(different * 100 < back + front, "multisampled object ids differ: {different} pixels");
+}
+
+#[test]
+fn object_id_edges_outline_instances() {
+    let read_edges = |id_weight: f32| {
+        let mut config = golden_config();
+        config.effects.edge_id_weight = id_weight;
+        Some(render_target(&mut spun_state_with(config)?, FrameTarget::Edge))
+    };
+
+    // Silhouettes and mesh boundaries the depth and normal edges miss
+    let Some(without_ids) = read_edges(0.0) else { return };
+    let Some(with_ids) = read_edges(1.0) else { return };
+    let id_edges = differing_pixels(&with_ids, &without_ids);
+    assert!(id_edges > 50, "no object id edges ({id_edges} pixels)");
+}
+
+#[test]
+fn tone_map_operators_switch_at_runtime() {
+    let Some(mut state) = spun_state_with(golden_config()) else { return };
+
+    let brightness = |state: &mut State| mean_brightness(&state.render_to_image().expect("headless render failed"));
+    let aces = brightness(&mut state);
+
+    let mut curves = vec![aces];
+    for tone_map in ToneMapOperator::ALL.into_iter().skip(1) {
+        state.set_tone_map(tone_map);
+        assert_eq!(state.tone_map(), tone_map);
+        let curve = brightness(&mut state);
+        assert!(curve > 10.0, "{} frame is black ({curve})", tone_map.name());
+        assert!(
+            curves.iter().all(|other| (curve - other).abs() > 0.01),
+            "{} looks like another operator ({curve} in {curves:?})",
+            tone_map.name()
+        );
+        curves.push(curve);
+    }
+
+    // The white point only bends the curves that use it
+    state.set_tone_map(ToneMapOperator::ReinhardExtended);
+    let reinhard_extended = brightness(&mut state);
+    state.set_white_point(1.5).expect("cannot set the white point");
+    assert!(brightness(&mut state) > reinhard_extended + 1.0, "lower white point not brighter");
+    state.set_tone_map(ToneMapOperator::Aces);
+    assert_eq!(brightness(&mut state), aces, "the white point changed ACES");
+
+    // One stop up and down, back to the golden frame at 0 EV
+    state.set_exposure(1.0).expect("cannot set the exposure");
+    assert!(brightness(&mut state) > aces + 1.0, "+1 EV not brighter");
+    state.set_exposure(-1.0).expect("cannot set the exposure");
+    assert!(brightness(&mut state) < aces - 1.0, "-1 EV not darker");
+    state.set_exposure(0.0).expect("cannot set the exposure");
+    let frame = state.render_to_image().expect("headless render failed");
+    check_target(FrameTarget::ToneMap.name(), &frame, false).expect("operators do not round trip");
+
+    assert!(state.set_exposure(f32::NAN).is_err());
+    assert!(state.set_white_point(0.0).is_err());
+    assert_eq!((state.exposure(), state.white_point()), (0.0, 1.5));
+}
+
+#[test]
+fn auto_exposure_meters_the_frame() {
+    // First frame, with its mean brightness
+    let auto_exposed = |auto_exposure, range| {
+        let mut config = GlowConfig::default();
+        config.effects.auto_exposure = auto_exposure;
+        config.effects.auto_exposure_range = range;
+        let mut state = spun_state_with(config)?;
+        let brightness = mean_brightness(&state.render_to_image().expect("headless render failed"));
+        Some((state, brightness))
+    };
+    let metered = |state: &State| state.metered_exposure().expect("cannot read the exposure");
+
+    let Some((manual, manual_brightness)) = auto_exposed(AutoExposure::Off, [-8.0, 8.0]) else { return };
+    assert_eq!(metered(&manual), None, "metered without auto exposure");
+
+    // Both paths meter the same average (within a histogram bin or two), the frame follows
+    let Some((mut histogram, brightness)) = auto_exposed(AutoExposure::Histogram, [-8.0, 8.0]) else { return };
+    let Some((downsample, _)) = auto_exposed(AutoExposure::Downsample, [-8.0, 8.0]) else { return };
+    assert_eq!((histogram.auto_exposure(), downsample.auto_exposure()), (AutoExposure::Histogram, AutoExposure::Downsample));
+    let (from_histogram, from_downsample) = (metered(&histogram).unwrap(), metered(&downsample).unwrap());
+    assert!(
+        (from_histogram - from_downsample).abs() < 0.15,
+        "histogram {from_histogram} EV, downsample {from_downsample} EV"
+    );
+    assert!(
+        (brightness - manual_brightness) * (from_histogram as f64) > 0.0,
+        "{from_histogram} EV did not change the frame: {brightness} instead of {manual_brightness}"
+    );
+
+    // Brighter than the whole metering range: its top is exposed to middle grey
+    for auto_exposure in [AutoExposure::Histogram, AutoExposure::Downsample] {
+        let Some((state, _)) = auto_exposed(auto_exposure, [-16.0, -12.0]) else { return };
+        let exposure = metered(&state).unwrap();
+        assert!(
+            (exposure - (0.18f32.log2() + 12.0)).abs() < 0.02,
+            "{} exposure {exposure} EV outside the metering range",
+            auto_exposure.name()
+        );
+    }
+
+    // A brighter frame: eased towards its exposure at auto_exposure_speed (1.5 per second)
+    histogram
+        .set_glow_style(GlowStyle { intensity: 50.0, thickness: 0.4, alpha: 1.0, ..GlowStyle::default() })
+        .expect("cannot set the glow style");
+    histogram.advance(0.1);
+    histogram.render_to_image().expect("headless render failed");
+    let eased = metered(&histogram).unwrap();
+    // The time step is used up by the frame that adapted
+    histogram.render_to_image().expect("headless render failed");
+    assert_eq!(metered(&histogram), Some(eased), "adapted again without a time step");
+    histogram.advance(100.0);
+    histogram.render_to_image().expect("headless render failed");
+    let adapted = metered(&histogram).unwrap();
+    assert!(adapted < from_histogram - 0.5, "brighter frame not exposed down: {adapted} EV");
+    let expected = from_histogram + (adapted - from_histogram) * (1.0 - (-0.15f32).exp());
+    assert!((eased - expected).abs() < 0.02, "eased to {eased} EV instead of {expected}");
+
+    assert_eq!(histogram.set_auto_exposure(AutoExposure::Off).unwrap(), AutoExposure::Off);
+    histogram.render_to_image().expect("headless render failed");
+    assert_eq!(metered(&histogram), None);
+}
+
+#[test]
+fn color_luts_grade_the_frame() {
+    let Some(mut state) = spun_state_with(golden_config()) else { return };
+    let ungraded = state.render_to_image().expect("headless render failed");
+    assert_eq!(state.color_lut(), None);
+
+    // The identity LUT gives the golden frame back
+    let identity = state.add_color_lut("identity", &CubeLut::identity(17)).expect("cannot add the LUT");
+    state.set_color_lut(Some(identity)).expect("cannot grade");
+    let frame = state.render_to_image().expect("headless render failed");
+    check_target(FrameTarget::ToneMap.name(), &frame, false).expect("identity LUT changed the frame");
+
+    // Swapped for another LUT (no rebuild): inverted display colors, then half of it (grey)
+    let invert = CubeLut::parse(
+        "LUT_3D_SIZE 2\n1 1 1\n0 1 1\n1 0 1\n0 0 1\n1 1 0\n0 1 0\n1 0 0\n0 0 0\n",
+    )
+    .expect("cannot parse the LUT");
+    let invert = state.add_color_lut("invert", &invert).expect("cannot add the LUT");
+    state.set_color_lut(Some(invert)).expect("cannot swap the LUT");
+    let frame = state.render_to_image().expect("headless render failed");
+    let max_delta = frame
+        .pixels()
+        .zip(ungraded.pixels())
+        .flat_map(|(graded, original)| (0..3).map(move |c| graded[c].abs_diff(255 - original[c])))
+        .max()
+        .unwrap_or(0);
+    assert!(max_delta <= 3, "inverted frame off by {max_delta}");
+
+    state.set_lut_strength(0.5).expect("cannot set the LUT strength");
+    let frame = state.render_to_image().expect("headless render failed");
+    assert!(
+        frame.pixels().all(|p| (0..3).all(|c| p[c].abs_diff(128) <= 2)),
+        "half inverted frame is not grey"
+    );
+
+    // Loaded through the resources, from res/luts
+    let teal_orange = pollster::block_on(state.load_color_lut("luts/teal_orange.cube")).expect("cannot load the LUT");
+    assert_eq!(state.color_lut_names(), ["identity", "invert", "luts/teal_orange.cube"]);
+    state.set_lut_strength(1.0).expect("cannot set the LUT strength");
+    state.set_color_lut(Some(teal_orange)).expect("cannot swap the LUT");
+    let frame = state.render_to_image().expect("headless render failed");
+    assert!(check_target(FrameTarget::ToneMap.name(), &frame, false).is_err(), "the look did not change the frame");
+
+    assert!(state.set_color_lut(Some(3)).is_err());
+    assert!(state.set_lut_strength(1.5).is_err());
+    assert_eq!((state.color_lut(), state.lut_strength()), (Some(teal_orange), 1.0));
+
+    // No color grade pass: the golden frame again
+    state.set_color_lut(None).expect("cannot stop grading");
+    let frame = state.render_to_image().expect("headless render failed");
+    check_target(FrameTarget::ToneMap.name(), &frame, false).expect("ungraded frame differs");
+}
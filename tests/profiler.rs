@@ -0,0 +1,72 @@
+// /
+// /  P R O F I L E R
+// /
+// / Headless frames with the profiler on: every pass of the graph gets a timing.
+
+#![cfg(not(target_arch = "wasm32"))]
+
+use glow::config::{BloomMode, GlowConfig};
+use glow::state::State;
+use glow::visualizer::AdapterNotFound;
+
+// A few frames with the profiler on: timestamps are read back asynchronously, frames later
+fn profiled_state(mut config: GlowConfig) -> Option<State> {
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    config.renderer.sample_count = 1;
+    config.renderer.profile = true;
+    config.adapter.apply_env().expect("invalid GLOW_* adapter env var");
+
+    let mut state = match pollster::block_on(State::new_headless(128, 96, config)) {
+        Ok(state) => state,
+        Err(e) if e.is::<AdapterNotFound>() => {
+            eprintln!("profiler: skipped, no adapter available ({e})");
+            return None;
+        }
+        Err(e) => panic!("cannot create the headless state: {e:#}"),
+    };
+
+    for _ in 0..8 {
+        state.render_to_image().expect("headless render failed");
+    }
+    Some(state)
+}
+
+fn timed_passes(state: &State) -> Vec<&'static str> {
+    state.profiler().expect("profiler is on").timings().iter().map(|t| t.name).collect()
+}
+
+#[test]
+fn profiler_times_every_pass() {
+    let Some(mut state) = profiled_state(GlowConfig::default()) else { return };
+
+    let profiler = state.profiler().expect("profiler is on");
+    let names = timed_passes(&state);
+    // "bloom" records several passes under one timestamp pair
+    for pass in ["depth prepass", "bloom", "edge", "composite", "tone map"] {
+        assert!(names.contains(&pass), "no timing for '{pass}' in {names:?}");
+    }
+    assert!(
+        profiler.timings().iter().all(|t| t.average_ms >= 0.0 && t.average_ms.is_finite()),
+        "invalid {:?} timings: {}",
+        profiler.mode(),
+        profiler.report()
+    );
+
+    state.set_profiling(false);
+    assert!(state.profiler().is_none());
+}
+
+// Each blur iteration is a node of its own name: timed apart, not merged into one entry
+#[test]
+fn blur_iterations_are_timed_apart() {
+    let mut config = GlowConfig::default();
+    config.effects.bloom = BloomMode::Separable;
+    config.effects.blur_iterations = 2;
+    let Some(state) = profiled_state(config) else { return };
+
+    let names = timed_passes(&state);
+    for pass in ["blur horizontal", "blur vertical", "blur horizontal 2", "blur vertical 2"] {
+        assert!(names.contains(&pass), "no timing for '{pass}' in {names:?}");
+    }
+}
@@ -0,0 +1,22 @@
+// /
+// /  R E A D B A C K   R O W S
+// /
+// / Rows of a texture -> buffer copy are padded to 256 bytes, without overflowing for
+// / large float textures.
+
+use glow::readback::padded_bytes_per_row;
+
+#[test]
+fn rows_are_padded() {
+    assert_eq!(padded_bytes_per_row(1, 4), Some(256));
+    assert_eq!(padded_bytes_per_row(64, 4), Some(256));
+    assert_eq!(padded_bytes_per_row(65, 4), Some(512));
+    // 16384 Rgba32Float texels: 256 KiB rows, the whole texture is past u32 bytes
+    assert_eq!(padded_bytes_per_row(16384, 16), Some(16384 * 16));
+}
+
+#[test]
+fn oversized_rows_are_rejected() {
+    assert_eq!(padded_bytes_per_row(u32::MAX, 16), None);
+    assert_eq!(padded_bytes_per_row(u32::MAX, 1), None);
+}
@@ -0,0 +1,87 @@
+// /
+// /  T U R N T A B L E   R E C O R D E R
+// /
+// / Frame count / time step of a recording, and two recordings of the same settings
+// / must give the same frames (fixed time step, no wall clock).
+
+#![cfg(not(target_arch = "wasm32"))]
+
+use std::path::Path;
+
+use glow::config::GlowConfig;
+use glow::recorder::{frame_path, record_turntable, RecordLength, Recording};
+use glow::visualizer::AdapterNotFound;
+
+fn recording(output: &Path, length: RecordLength) -> Recording {
+    Recording {
+        output: output.to_path_buf(),
+        width: 128,
+        height: 96,
+        fps: 30,
+        length,
+    }
+}
+
+#[test]
+fn revolution_adds_up_to_one_turn() {
+    let recording = recording(Path::new("spin.gif"), RecordLength::Revolution);
+    assert!(recording.is_gif());
+
+    for spin_speed in [1.5, -0.7, 10.0] {
+        let (frames, dt) = recording.frames(spin_speed).expect("valid recording");
+        let turn = frames as f64 * dt * (spin_speed as f64).abs();
+
+        assert!((turn - std::f64::consts::TAU).abs() < 1e-9, "{frames} frames x {dt}s = {turn} rad");
+        assert!((dt * 30.0 - 1.0).abs() < 0.1, "time step {dt}s is too far from 1 / fps");
+    }
+
+    assert!(recording.frames(0.0).is_err());
+}
+
+#[test]
+fn seconds_use_the_frame_rate() {
+    let recording = recording(Path::new("frames"), RecordLength::Seconds(2.0));
+    assert!(!recording.is_gif());
+
+    assert_eq!(recording.frames(1.5).expect("valid recording"), (60, 1.0 / 30.0));
+    assert!(self::recording(Path::new("frames"), RecordLength::Seconds(0.0)).frames(1.5).is_err());
+}
+
+#[test]
+fn recordings_are_deterministic() {
+    let _ = env_logger::builder().is_test(true).try_init();
+
+    let mut config = GlowConfig::default();
+    config.renderer.sample_count = 1;
+    config.adapter.apply_env().expect("invalid GLOW_* adapter env var");
+
+    let dir = Path::new(env!("CARGO_TARGET_TMPDIR")).join("recorder");
+    let runs = ["a", "b"].map(|run| {
+        let output = dir.join(run);
+        let _ = std::fs::remove_dir_all(&output);
+        let recording = recording(&output, RecordLength::Seconds(0.1));
+        pollster::block_on(record_turntable(config.clone(), &recording)).map(|frames| (output, frames))
+    });
+
+    let [(a, frames), (b, frames_b)] = match runs {
+        [Ok(a), Ok(b)] => [a, b],
+        [Err(e), _] | [_, Err(e)] if e.is::<AdapterNotFound>() => {
+            eprintln!("recorder: skipped, no adapter available ({e})");
+            return;
+        }
+        [Err(e), _] | [_, Err(e)] => panic!("recording failed: {e:#}"),
+    };
+
+    assert_eq!(frames, 3);
+    assert_eq!(frames, frames_b);
+
+    for i in 0..frames {
+        let read = |dir: &Path| std::fs::read(frame_path(dir, i)).expect("frame written");
+        assert!(read(&a) == read(&b), "frame {i} differs between two recordings");
+    }
+    assert!(read_differs(&a, 0, 2), "the model does not spin");
+}
+
+fn read_differs(dir: &Path, i: u32, j: u32) -> bool {
+    std::fs::read(frame_path(dir, i)).ok() != std::fs::read(frame_path(dir, j)).ok()
+}